@@ -4,17 +4,160 @@ use arcis_imports::*;
 mod circuits {
     use arcis_imports::*;
 
+    /// Keep in sync with `MAX_CONFIDENTIAL_DEDUCTIONS` in the program.
+    const MAX_DEDUCTIONS: usize = 4;
+    /// Keep in sync with `MAX_TOTAL_NET_PAYROLL_INPUTS` in the program.
+    const MAX_PAYROLL_INPUTS: usize = 3;
+
     pub struct PayrollInput {
         salary: u64,
-        tax_rate: u16, // basis points (10000 = 100%)
-        deductions: u64,
+        deductions: [u64; MAX_DEDUCTIONS], // unused slots are encrypted zeros
+    }
+
+    /// Salary above the tax-free allowance, or zero. Arcis evaluates both branches of a
+    /// secret condition and selects one, so the cost doesn't depend on which side of the
+    /// allowance the salary falls.
+    fn taxable_amount(salary: u64, tax_free_allowance: u64) -> u64 {
+        if salary > tax_free_allowance {
+            salary - tax_free_allowance
+        } else {
+            0
+        }
+    }
+
+    /// Rounds `numerator / 10000` according to the payroll's rounding mode
+    /// (0 = floor, 1 = ceil, 2 = half-up), matching `RoundingMode::apply` on-chain.
+    fn round_bps(numerator: u128, rounding_mode: u8) -> u128 {
+        let floor = numerator / 10000;
+        let remainder = numerator - floor * 10000;
+        let ceil_adjust = if remainder > 0 { 1 } else { 0 };
+        let round_adjust = if remainder >= 5000 { 1 } else { 0 };
+        if rounding_mode == 1 {
+            floor + ceil_adjust
+        } else if rounding_mode == 2 {
+            floor + round_adjust
+        } else {
+            floor
+        }
+    }
+
+    /// Withholding on `salary`, or zero for classes that aren't withheld (contractors).
+    fn withholding(
+        salary: u64,
+        tax_rate: u16,
+        rounding_mode: u8,
+        tax_free_allowance: u64,
+        withhold_tax: bool,
+    ) -> u128 {
+        let taxable = taxable_amount(salary, tax_free_allowance);
+        let tax_amount = round_bps(taxable as u128 * tax_rate as u128, rounding_mode);
+        if withhold_tax {
+            tax_amount
+        } else {
+            0
+        }
+    }
+
+    /// Net pay for one input, shared by the per-employee and payroll-wide circuits.
+    fn net_pay_of(
+        input: &PayrollInput,
+        tax_rate: u16,
+        rounding_mode: u8,
+        deduction_bps: u16,
+        tax_free_allowance: u64,
+        withhold_tax: bool,
+    ) -> u64 {
+        let tax_amount = withholding(
+            input.salary,
+            tax_rate,
+            rounding_mode,
+            tax_free_allowance,
+            withhold_tax,
+        );
+        // Fixed-length loop over every slot, so the work done doesn't depend on how many are used
+        let mut deductions = ((input.salary as u128 * deduction_bps as u128) / 10000) as u64;
+        for i in 0..MAX_DEDUCTIONS {
+            deductions += input.deductions[i];
+        }
+        input.salary - tax_amount as u64 - deductions
     }
 
     #[instruction]
-    pub fn calculate_net_pay(input_ctxt: Enc<Shared, PayrollInput>) -> Enc<Shared, u64> {
+    pub fn calculate_net_pay(
+        input_ctxt: Enc<Shared, PayrollInput>,
+        tax_rate_ctxt: Enc<Shared, u16>, // basis points (10000 = 100%), stored on the payroll
+        rounding_mode: u8,
+        deduction_bps: u16, // percentage-of-gross deduction, floored like the program's
+        tax_free_allowance: u64, // salary exempt from withholding, stored on the payroll
+        withhold_tax: bool, // false for contractors, from the employee's classification
+    ) -> Enc<Shared, u64> {
         let input = input_ctxt.to_arcis();
-        let tax_amount = (input.salary as u128 * input.tax_rate as u128) / 10000;
-        let net_pay = input.salary - tax_amount as u64 - input.deductions;
+        let tax_rate = tax_rate_ctxt.to_arcis();
+        let net_pay = net_pay_of(
+            &input,
+            tax_rate,
+            rounding_mode,
+            deduction_bps,
+            tax_free_allowance,
+            withhold_tax,
+        );
         input_ctxt.owner.from_arcis(net_pay)
     }
+
+    /// Sum of net pay over a fixed-size array of inputs, revealing only the encrypted total.
+    /// Unused slots are encrypted zeros and add nothing. Percentage-of-gross deductions and
+    /// classifications are per employee, so every input is withheld at the payroll rate.
+    #[instruction]
+    pub fn calculate_total_net_payroll(
+        inputs_ctxt: Enc<Shared, [PayrollInput; MAX_PAYROLL_INPUTS]>,
+        tax_rate_ctxt: Enc<Shared, u16>,
+        rounding_mode: u8,
+        tax_free_allowance: u64,
+    ) -> Enc<Shared, u64> {
+        let inputs = inputs_ctxt.to_arcis();
+        let tax_rate = tax_rate_ctxt.to_arcis();
+        let mut total = 0u64;
+        for i in 0..MAX_PAYROLL_INPUTS {
+            total += net_pay_of(&inputs[i], tax_rate, rounding_mode, 0, tax_free_allowance, true);
+        }
+        inputs_ctxt.owner.from_arcis(total)
+    }
+
+    /// Whether a net pay result stays within the salary it was computed from. Underflowing
+    /// deductions wrap the net pay above the salary, so this catches inflated results.
+    /// Only the comparison is revealed.
+    #[instruction]
+    pub fn validate_net_pay(salary_ctxt: Enc<Shared, u64>, net_pay_ctxt: Enc<Shared, u64>) -> bool {
+        let salary = salary_ctxt.to_arcis();
+        let net_pay = net_pay_ctxt.to_arcis();
+        (net_pay <= salary).reveal()
+    }
+
+    /// Withholding alone, so the confidential path can report tax separately from net pay.
+    #[instruction]
+    pub fn calculate_tax_withholding(
+        salary_ctxt: Enc<Shared, u64>,
+        tax_rate_ctxt: Enc<Shared, u16>,
+        rounding_mode: u8,
+        tax_free_allowance: u64,
+        withhold_tax: bool,
+    ) -> Enc<Shared, u64> {
+        let salary = salary_ctxt.to_arcis();
+        let tax_rate = tax_rate_ctxt.to_arcis();
+        let tax_amount =
+            withholding(salary, tax_rate, rounding_mode, tax_free_allowance, withhold_tax);
+        salary_ctxt.owner.from_arcis(tax_amount as u64)
+    }
+
+    /// Adds one withholding to the year-to-date total without revealing either amount.
+    /// The result stays encrypted for the YTD owner, so it can feed the next call.
+    #[instruction]
+    pub fn accumulate_ytd_tax(
+        ytd_ctxt: Enc<Shared, u64>,
+        tax_ctxt: Enc<Shared, u64>,
+    ) -> Enc<Shared, u64> {
+        let ytd = ytd_ctxt.to_arcis();
+        let tax = tax_ctxt.to_arcis();
+        ytd_ctxt.owner.from_arcis(ytd + tax)
+    }
 }
@@ -1,11 +1,257 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+use anchor_lang::system_program;
+use anchor_spl::token_interface::spl_token_2022::extension::{
+    BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+};
+use anchor_spl::token_interface::spl_token_2022::state::{
+    Account as TokenAccountState, Mint as MintState,
+};
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
 use arcium_anchor::prelude::*;
+use arcium_client::idl::arcium::types::CallbackAccount;
 
 const COMP_DEF_OFFSET_CALCULATE_NET_PAY: u32 = comp_def_offset("calculate_net_pay");
+const COMP_DEF_OFFSET_CALCULATE_TAX_WITHHOLDING: u32 = comp_def_offset("calculate_tax_withholding");
+const COMP_DEF_OFFSET_ACCUMULATE_YTD_TAX: u32 = comp_def_offset("accumulate_ytd_tax");
+const COMP_DEF_OFFSET_CALCULATE_TOTAL_NET_PAYROLL: u32 =
+    comp_def_offset("calculate_total_net_payroll");
+const COMP_DEF_OFFSET_VALIDATE_NET_PAY: u32 = comp_def_offset("validate_net_pay");
+
+/// Number of missed pay periods a single `process_payment` call may settle by default.
+pub const DEFAULT_MAX_CATCHUP_PERIODS: u8 = 4;
+/// Keep in sync with the `max_len` attributes on `Employee::deduction_items` and `DeductionItem::label`.
+pub const MAX_DEDUCTION_ITEMS: usize = 8;
+pub const MAX_DEDUCTION_LABEL_LEN: usize = 32;
+/// Encrypted deduction slots summed by the `calculate_net_pay` circuit.
+/// Keep in sync with `MAX_DEDUCTIONS` in encrypted-ixs.
+pub const MAX_CONFIDENTIAL_DEDUCTIONS: usize = 4;
+/// Employees summed by one `calculate_total_net_payroll` call. Each slot carries a salary
+/// and `MAX_CONFIDENTIAL_DEDUCTIONS` ciphertexts, so transaction size sets the bound.
+/// Keep in sync with `MAX_PAYROLL_INPUTS` in encrypted-ixs.
+pub const MAX_TOTAL_NET_PAYROLL_INPUTS: usize = 3;
+/// Upper bound on employees created by one `add_employees_batch` call, keeping it
+/// within transaction size and compute limits.
+pub const MAX_EMPLOYEES_PER_BATCH: usize = 5;
+/// Keep in sync with the `max_len` attribute on `Employee::deposit_splits`.
+pub const MAX_DEPOSIT_SPLITS: usize = 4;
+/// Upper bound on employee accounts summed by one `check_solvency` call.
+pub const MAX_EMPLOYEES_PER_SOLVENCY_CHECK: usize = 20;
+/// Upper bound on payroll accounts updated by one `update_tax_rate_batch` call.
+pub const MAX_PAYROLLS_PER_TAX_BATCH: usize = 10;
+/// Compute units a batch instruction plans to spend on its per-account work, leaving
+/// headroom under the default 200k-unit instruction limit for the fixed costs.
+pub const BATCH_COMPUTE_BUDGET: u64 = 150_000;
+/// Estimated cost of loading and checking one account in a read-style batch
+/// (`check_solvency`, `recount_employees`).
+pub const COMPUTE_UNITS_PER_EMPLOYEE_READ: u64 = 6_000;
+/// Estimated cost of creating and writing one employee in `add_employees_batch`.
+pub const COMPUTE_UNITS_PER_EMPLOYEE_INIT: u64 = 30_000;
+/// Estimated cost of rewriting one payroll in `update_tax_rate_batch`.
+pub const COMPUTE_UNITS_PER_PAYROLL_UPDATE: u64 = 10_000;
+/// Most accounts any batch instruction accepts; the cheapest per-account cost sets it.
+pub const MAX_BATCH_SIZE: usize = (BATCH_COMPUTE_BUDGET / COMPUTE_UNITS_PER_EMPLOYEE_READ) as usize;
+/// Payments kept in an employee's `PayHistory` ring buffer; 32 records keep the account
+/// around 1 KB.
+pub const PAY_HISTORY_LEN: usize = 32;
+/// Keep in sync with the `max_len` attribute on `Payroll::guardians`.
+pub const MAX_RECOVERY_GUARDIANS: usize = 5;
+/// Keep in sync with the `max_len` attributes on `Payroll::withdrawal_signers` and
+/// `WithdrawalApprovals::approvals`.
+pub const MAX_WITHDRAWAL_SIGNERS: usize = 5;
+/// Upper bound on `Payroll::platform_fee_bps` (10%).
+pub const MAX_PLATFORM_FEE_BPS: u16 = 1000;
+/// Ids are whole PDA seeds, so the 32-byte seed limit caps them. A longer id fails seed
+/// derivation, in the client and in account validation, before any handler runs, so it
+/// was never usable. Keep in sync with the `max_len` attributes on `Payroll::payroll_id`
+/// and `Employee::employee_id`; accounts sized for the old 64 still decode.
+pub const MAX_ID_LEN: usize = anchor_lang::solana_program::pubkey::MAX_SEED_LEN;
+/// Keep in sync with the `max_len` attributes on `Employee::department` and `Employee::cost_center`.
+pub const MAX_EMPLOYEE_METADATA_LEN: usize = 32;
+/// Pyth Solana receiver program that owns `PriceUpdateV2` accounts.
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey =
+    anchor_lang::solana_program::pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+/// USD-denominated salaries are expressed in micro-dollars.
+pub const USD_DECIMALS: u32 = 6;
+/// SPL Memo program (v2), invoked when a payment carries a memo.
+pub const MEMO_PROGRAM_ID: Pubkey =
+    anchor_lang::solana_program::pubkey!("MemoSq4gqABAXKb96qnH8TuNzTRBsFhTNKELCC5eJmS");
+/// Upper bound on a payment memo, in bytes.
+pub const MAX_PAYMENT_MEMO_LEN: usize = 128;
+/// Longest business-day shift, in seconds: reaches the preceding business day across a
+/// weekend plus a one-day holiday.
+pub const MAX_BUSINESS_DAY_SHIFT_SECONDS: i64 = 3 * 24 * 60 * 60;
+/// Wrapped SOL mint; native payrolls record it as their payment token so salaries use 9 decimals.
+pub const NATIVE_MINT: Pubkey =
+    anchor_lang::solana_program::pubkey!("So11111111111111111111111111111111111111112");
+
+/// `Payroll::event_level` that emits only essential events: payments and fund movements,
+/// failures and warnings, confidential computation results, the reports of read-only
+/// instructions, and changes to authority, roles, custody, recovery, freezing and pausing.
+/// Events not tied to one payroll are always emitted.
+pub const EVENT_LEVEL_ESSENTIAL: u8 = 0;
+/// `Payroll::event_level` that also emits everything else: configuration and employee
+/// record updates, onboarding, queued computations, and per-step payment detail
+/// (pause shifts, scheduled raises, clawbacks, deferrals, deposit splits).
+pub const EVENT_LEVEL_ALL: u8 = 1;
+
+/// `emit!` for an event outside the essential set, skipped below `EVENT_LEVEL_ALL`.
+macro_rules! emit_verbose {
+    ($payroll:expr, $event:expr) => {
+        if $payroll.event_level >= EVENT_LEVEL_ALL {
+            emit!($event);
+        }
+    };
+}
 
 declare_id!("5w4okCHwmXCS84u93nBQWNfZ3gRVV2UTYt4diyUR7d8c");
 
+/// PDA derivations matching the `seeds = [...]` constraints below, for clients and for
+/// instructions that derive accounts by hand. Every helper returns `(address, bump)`.
+pub mod pdas {
+    use anchor_lang::prelude::Pubkey;
+
+    pub const PAYROLL_SEED: &[u8] = b"payroll";
+    pub const EMPLOYEE_SEED: &[u8] = b"employee";
+    pub const PAYROLL_VAULT_SEED: &[u8] = b"payroll_vault";
+    pub const NATIVE_VAULT_SEED: &[u8] = b"native_vault";
+    pub const PROGRAM_CONFIG_SEED: &[u8] = b"program_config";
+    pub const VESTING_SEED: &[u8] = b"vesting";
+    pub const PAYSTUB_SEED: &[u8] = b"paystub";
+    pub const APPROVAL_SEED: &[u8] = b"approval";
+    pub const RECOVERY_SEED: &[u8] = b"recovery";
+    pub const PAY_HISTORY_SEED: &[u8] = b"history";
+    pub const WITHDRAWAL_APPROVALS_SEED: &[u8] = b"withdrawal_approvals";
+
+    pub fn payroll_pda(payroll_id: &str) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[PAYROLL_SEED, payroll_id.as_bytes()], &crate::ID)
+    }
+
+    pub fn employee_pda(payroll: &Pubkey, employee_id: &str) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[EMPLOYEE_SEED, payroll.as_ref(), employee_id.as_bytes()],
+            &crate::ID,
+        )
+    }
+
+    pub fn payroll_vault_pda(payroll: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[PAYROLL_VAULT_SEED, payroll.as_ref()], &crate::ID)
+    }
+
+    pub fn native_vault_pda(payroll: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[NATIVE_VAULT_SEED, payroll.as_ref()], &crate::ID)
+    }
+
+    pub fn program_config_pda() -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[PROGRAM_CONFIG_SEED], &crate::ID)
+    }
+
+    pub fn vesting_pda(employee: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[VESTING_SEED, employee.as_ref()], &crate::ID)
+    }
+
+    /// `payment_count` is the employee's count before the payment the stub records.
+    pub fn paystub_pda(employee: &Pubkey, payment_count: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[PAYSTUB_SEED, employee.as_ref(), &payment_count.to_le_bytes()],
+            &crate::ID,
+        )
+    }
+
+    /// `payment_count` is the employee's count when the approved payment is processed.
+    pub fn approval_pda(employee: &Pubkey, payment_count: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[APPROVAL_SEED, employee.as_ref(), &payment_count.to_le_bytes()],
+            &crate::ID,
+        )
+    }
+
+    pub fn pay_history_pda(employee: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[PAY_HISTORY_SEED, employee.as_ref()], &crate::ID)
+    }
+
+    /// A payroll has at most one recovery proposal open at a time.
+    pub fn recovery_pda(payroll: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[RECOVERY_SEED, payroll.as_ref()], &crate::ID)
+    }
+
+    /// Reused across withdrawal requests; approvals are bound to the request they sign.
+    pub fn withdrawal_approvals_pda(payroll: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[WITHDRAWAL_APPROVALS_SEED, payroll.as_ref()], &crate::ID)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn payroll_pda_matches_raw_seeds() {
+            let (address, bump) = payroll_pda("payroll_001");
+            let expected = Pubkey::create_program_address(
+                &[b"payroll", b"payroll_001", &[bump]],
+                &crate::ID,
+            )
+            .unwrap();
+            assert_eq!(address, expected);
+        }
+
+        #[test]
+        fn employee_pda_is_scoped_to_its_payroll() {
+            let (payroll_a, _) = payroll_pda("payroll_a");
+            let (payroll_b, _) = payroll_pda("payroll_b");
+            let (address, bump) = employee_pda(&payroll_a, "emp_001");
+            let expected = Pubkey::create_program_address(
+                &[b"employee", payroll_a.as_ref(), b"emp_001", &[bump]],
+                &crate::ID,
+            )
+            .unwrap();
+            assert_eq!(address, expected);
+            assert_ne!(address, employee_pda(&payroll_b, "emp_001").0);
+        }
+
+        #[test]
+        fn vaults_match_raw_seeds() {
+            let (payroll, _) = payroll_pda("payroll_001");
+            let (vault, vault_bump) = payroll_vault_pda(&payroll);
+            let (native, native_bump) = native_vault_pda(&payroll);
+            assert_eq!(
+                vault,
+                Pubkey::create_program_address(
+                    &[b"payroll_vault", payroll.as_ref(), &[vault_bump]],
+                    &crate::ID
+                )
+                .unwrap()
+            );
+            assert_eq!(
+                native,
+                Pubkey::create_program_address(
+                    &[b"native_vault", payroll.as_ref(), &[native_bump]],
+                    &crate::ID
+                )
+                .unwrap()
+            );
+            assert_ne!(vault, native);
+            assert_ne!(recovery_pda(&payroll).0, vault);
+        }
+
+        #[test]
+        fn per_payment_pdas_differ_by_count() {
+            let (payroll, _) = payroll_pda("payroll_001");
+            let (employee, _) = employee_pda(&payroll, "emp_001");
+            let (stub_0, bump) = paystub_pda(&employee, 0);
+            assert_eq!(
+                stub_0,
+                Pubkey::create_program_address(
+                    &[b"paystub", employee.as_ref(), &0u64.to_le_bytes(), &[bump]],
+                    &crate::ID
+                )
+                .unwrap()
+            );
+            assert_ne!(stub_0, paystub_pda(&employee, 1).0);
+            assert_ne!(approval_pda(&employee, 0).0, approval_pda(&employee, 1).0);
+        }
+    }
+}
+
 #[arcium_program]
 pub mod confidential_payroll {
     use super::*;
@@ -15,25 +261,162 @@ pub mod confidential_payroll {
         Ok(())
     }
 
+    pub fn init_calculate_tax_withholding_comp_def(
+        ctx: Context<InitCalculateTaxWithholdingCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn init_accumulate_ytd_tax_comp_def(
+        ctx: Context<InitAccumulateYtdTaxCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn init_calculate_total_net_payroll_comp_def(
+        ctx: Context<InitCalculateTotalNetPayrollCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn init_validate_net_pay_comp_def(ctx: Context<InitValidateNetPayCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, true, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn initialize_program_config(
+        ctx: Context<InitializeProgramConfig>,
+        admin: Pubkey,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.program_config;
+        config.admin = admin;
+        config.global_paused = false;
+
+        emit!(ProgramConfigInitialized { admin });
+
+        Ok(())
+    }
+
+    pub fn set_global_pause(ctx: Context<SetGlobalPause>, paused: bool) -> Result<()> {
+        let config = &mut ctx.accounts.program_config;
+        config.global_paused = paused;
+
+        emit!(GlobalPauseUpdated {
+            admin: config.admin,
+            paused,
+        });
+
+        Ok(())
+    }
+
+    /// A non-zero `initial_deposit` funds the vault from `authority_token_account` in the
+    /// same transaction, as if `deposit_funds` had been called right after.
+    /// `default_frequency` applies to employees added without one.
     pub fn initialize_payroll(
         ctx: Context<InitializePayroll>,
         payroll_id: String,
         tax_rate: u16,
+        max_employees: u64,
+        default_frequency: PaymentFrequency,
+        initial_deposit: u64,
     ) -> Result<()> {
+        require!(is_valid_id(&payroll_id), ErrorCode::InvalidPayrollId);
         let payroll = &mut ctx.accounts.payroll;
-        payroll.authority = ctx.accounts.authority.key();
-        payroll.payroll_id = payroll_id;
-        payroll.payment_token = ctx.accounts.payment_token.key();
-        payroll.employee_count = 0;
-        payroll.is_active = true;
-        payroll.tax_rate = tax_rate;
-        payroll.total_funds = 0;
-        payroll.vault_bump = ctx.bumps.payroll_vault;
+        payroll.set_inner(Payroll::new(
+            ctx.accounts.authority.key(),
+            payroll_id,
+            ctx.accounts.payment_token.key(),
+            ctx.accounts.payment_token.decimals,
+            tax_rate,
+            max_employees,
+            default_frequency,
+            ctx.bumps.payroll_vault,
+            false,
+        ));
+        let sequence = payroll.next_sequence()?;
+
+        emit!(PayrollInitialized {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            authority: payroll.authority,
+            tax_rate: payroll.tax_rate,
+            default_frequency,
+        });
+
+        if initial_deposit > 0 {
+            let sequence = payroll.next_sequence()?;
+            let authority_token_account = ctx
+                .accounts
+                .authority_token_account
+                .as_ref()
+                .ok_or(ErrorCode::DepositAccountRequired)?;
+            let received = deposit_to_vault(
+                &ctx.accounts.token_program,
+                authority_token_account,
+                &ctx.accounts.payment_token,
+                &mut ctx.accounts.payroll_vault,
+                &ctx.accounts.authority,
+                initial_deposit,
+            )?;
+            payroll.total_funds = received;
+            payroll.lifetime_deposited = received;
+
+            emit!(FundsDeposited {
+                payroll_id: payroll.payroll_id.clone(),
+                sequence,
+                amount: initial_deposit,
+                received,
+                total_funds: payroll.total_funds,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Like `initialize_payroll`, but the vault is a system-owned PDA holding lamports.
+    /// The vault is seeded with its rent-exempt minimum, which is never counted in total_funds.
+    pub fn initialize_native_payroll(
+        ctx: Context<InitializeNativePayroll>,
+        payroll_id: String,
+        tax_rate: u16,
+        max_employees: u64,
+        default_frequency: PaymentFrequency,
+    ) -> Result<()> {
+        require!(is_valid_id(&payroll_id), ErrorCode::InvalidPayrollId);
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: ctx.accounts.native_vault.to_account_info(),
+                },
+            ),
+            Rent::get()?.minimum_balance(0),
+        )?;
+
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.set_inner(Payroll::new(
+            ctx.accounts.authority.key(),
+            payroll_id,
+            NATIVE_MINT,
+            9,
+            tax_rate,
+            max_employees,
+            default_frequency,
+            ctx.bumps.native_vault,
+            true,
+        ));
+        let sequence = payroll.next_sequence()?;
 
         emit!(PayrollInitialized {
             payroll_id: payroll.payroll_id.clone(),
+            sequence,
             authority: payroll.authority,
             tax_rate: payroll.tax_rate,
+            default_frequency,
         });
 
         Ok(())
@@ -44,63 +427,280 @@ pub mod confidential_payroll {
         employee_id: String,
         salary_amount: u64,
         deductions: u64,
-        payment_frequency: PaymentFrequency,
+        payment_frequency: Option<PaymentFrequency>, // None = payroll.default_frequency
+        department: String,
+        cost_center: String,
+        encryption_pubkey: [u8; 32],
+        classification: EmployeeClass,
     ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
         let payroll = &mut ctx.accounts.payroll;
         let employee = &mut ctx.accounts.employee;
 
         require!(payroll.is_active, ErrorCode::PayrollInactive);
-        require!(employee_id.len() <= 64, ErrorCode::InvalidEmployeeId);
-
-        employee.payroll = payroll.key();
-        employee.employee_id = employee_id.clone();
-        employee.wallet = ctx.accounts.employee_wallet.key();
-        employee.salary_amount = salary_amount;
-        employee.deductions = deductions;
-        employee.payment_frequency = payment_frequency;
-        // Set last_payment to 0 to allow immediate first payment
-        employee.last_payment = 0;
-        employee.is_active = true;
+        let params = EmployeeParams {
+            employee_id,
+            wallet: ctx.accounts.employee_wallet.key(),
+            salary_amount,
+            deductions,
+            payment_frequency: payment_frequency.unwrap_or(payroll.default_frequency),
+            department,
+            cost_center,
+            encryption_pubkey,
+            classification,
+        };
+        params.validate()?;
+        payroll.check_salary_bounds(params.salary_amount)?;
+        payroll.reserve_employee_slots(1)?;
 
-        payroll.employee_count += 1;
+        employee.set_inner(Employee::new(
+            payroll.key(),
+            params,
+            ctx.accounts.payment_token.decimals,
+        ));
+        employee.paused_seconds_applied = payroll.total_paused_seconds;
+        employee.created_at = Clock::get()?.unix_timestamp;
 
-        emit!(EmployeeAdded {
+        emit_verbose!(payroll, EmployeeAdded {
             payroll_id: payroll.payroll_id.clone(),
-            employee_id,
+            sequence,
+            employee_id: employee.employee_id.clone(),
             salary_amount,
             deductions,
+            payment_frequency: employee.payment_frequency,
+            decimals: employee.salary_decimals,
+            department: employee.department.clone(),
+            cost_center: employee.cost_center.clone(),
+            created_at: employee.created_at,
+            classification: employee.classification,
+        });
+
+        Ok(())
+    }
+
+    pub fn add_employees_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AddEmployeesBatch<'info>>,
+        employees: Vec<EmployeeParams>,
+    ) -> Result<()> {
+        require!(
+            !employees.is_empty() && employees.len() <= MAX_EMPLOYEES_PER_BATCH,
+            ErrorCode::BatchTooLarge
+        );
+        check_batch_size(employees.len(), COMPUTE_UNITS_PER_EMPLOYEE_INIT)?;
+        require!(
+            ctx.remaining_accounts.len() == employees.len(),
+            ErrorCode::InvalidEmployeeAccount
+        );
+
+        let payroll = &mut ctx.accounts.payroll;
+        require!(payroll.is_active, ErrorCode::PayrollInactive);
+        payroll.reserve_employee_slots(employees.len() as u64)?;
+
+        let payroll_key = payroll.key();
+        let decimals = ctx.accounts.payment_token.decimals;
+        let now = Clock::get()?.unix_timestamp;
+        let space = 8 + Employee::INIT_SPACE;
+        let lamports = Rent::get()?.minimum_balance(space);
+        let count = employees.len() as u64;
+
+        for (params, employee_info) in employees.into_iter().zip(ctx.remaining_accounts.iter()) {
+            params.validate()?;
+            payroll.check_salary_bounds(params.salary_amount)?;
+
+            let (expected, bump) = pdas::employee_pda(&payroll_key, &params.employee_id);
+            require_keys_eq!(
+                employee_info.key(),
+                expected,
+                ErrorCode::InvalidEmployeeAccount
+            );
+
+            system_program::create_account(
+                CpiContext::new_with_signer(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::CreateAccount {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: employee_info.clone(),
+                    },
+                    &[&[
+                        b"employee",
+                        payroll_key.as_ref(),
+                        params.employee_id.as_bytes(),
+                        &[bump],
+                    ]],
+                ),
+                lamports,
+                space as u64,
+                ctx.program_id,
+            )?;
+
+            let salary_amount = params.salary_amount;
+            let deductions = params.deductions;
+            let mut employee = Employee::new(payroll_key, params, decimals);
+            employee.paused_seconds_applied = payroll.total_paused_seconds;
+            employee.created_at = now;
+            employee.try_serialize(&mut &mut employee_info.try_borrow_mut_data()?[..])?;
+
+            // Each event gets its own sequence so indexers can tell them apart
+            let sequence = payroll.next_sequence()?;
+            emit_verbose!(payroll, EmployeeAdded {
+                payroll_id: payroll.payroll_id.clone(),
+                sequence,
+                employee_id: employee.employee_id,
+                salary_amount,
+                deductions,
+                payment_frequency: employee.payment_frequency,
+                decimals,
+                department: employee.department,
+                cost_center: employee.cost_center,
+                created_at: now,
+                classification: employee.classification,
+            });
+        }
+
+        let sequence = payroll.next_sequence()?;
+        emit_verbose!(payroll, EmployeesAdded {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            count,
+        });
+
+        Ok(())
+    }
+
+    /// Emits every persisted field of the employee so a migration tool can rebuild it
+    /// with `import_employee`. Read-only.
+    pub fn export_employee(ctx: Context<ReadEmployee>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.sequence;
+
+        emit!(EmployeeExported {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee: (*ctx.accounts.employee).clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Recreates an employee from an `EmployeeExported` snapshot under this payroll.
+    /// Idempotent: re-importing an identical snapshot is a no-op, while a snapshot that
+    /// differs from the existing account is rejected rather than overwriting it.
+    pub fn import_employee(
+        ctx: Context<ImportEmployee>,
+        employee_id: String,
+        snapshot: Employee,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        let employee = &mut ctx.accounts.employee;
+
+        require!(payroll.is_active, ErrorCode::PayrollInactive);
+        require!(
+            snapshot.employee_id == employee_id && is_valid_id(&employee_id),
+            ErrorCode::InvalidEmployeeId
+        );
+        validate_employee_metadata(&snapshot.department, &snapshot.cost_center)?;
+        require!(
+            snapshot.deduction_items.len() <= MAX_DEDUCTION_ITEMS,
+            ErrorCode::TooManyDeductions
+        );
+
+        // The snapshot belongs to the payroll it is imported into, not the one it came from
+        let mut snapshot = snapshot;
+        snapshot.payroll = payroll.key();
+
+        let created = employee.payroll == Pubkey::default();
+        if created {
+            payroll.check_salary_bounds(snapshot.salary_amount)?;
+            payroll.reserve_employee_slots(1)?;
+            // Pauses before the import say nothing about this employee's schedule
+            snapshot.paused_seconds_applied = payroll.total_paused_seconds;
+            // The pay history is keyed by the old employee account and doesn't come along
+            snapshot.pay_history_enabled = false;
+            employee.set_inner(snapshot);
+        } else {
+            // Pause and history bookkeeping stay local, so a retried import still matches
+            snapshot.paused_seconds_applied = employee.paused_seconds_applied;
+            snapshot.pay_history_enabled = employee.pay_history_enabled;
+            require!(
+                employee.try_to_vec()? == snapshot.try_to_vec()?,
+                ErrorCode::EmployeeImportConflict
+            );
+        }
+
+        emit_verbose!(payroll, EmployeeImported {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            employee_id,
+            created,
+        });
+
+        Ok(())
+    }
+
+    /// What queueing `calculate_net_pay` currently costs: the circuit's compute units at the
+    /// cluster's price. Reads the same Arcium accounts the queue call does and changes nothing,
+    /// so it can be simulated before funding the payer.
+    pub fn estimate_net_pay_fee(ctx: Context<EstimateNetPayFee>) -> Result<()> {
+        let cu_amount = ctx.accounts.comp_def_account.cu_amount;
+        let cu_price = ctx.accounts.cluster_account.cu_price;
+        let lamports = cu_amount
+            .checked_mul(cu_price)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(ComputationFeeEstimate {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence: ctx.accounts.payroll.sequence,
+            cu_amount,
+            cu_price,
+            lamports,
         });
 
         Ok(())
     }
 
+    /// Inputs must be encrypted with the employee's stored `encryption_pubkey`, which the
+    /// result is sealed to as well.
     pub fn calculate_net_pay(
         ctx: Context<CalculateNetPay>,
         computation_offset: u64,
         encrypted_salary: [u8; 32],
-        encrypted_tax_rate: [u8; 32],
-        encrypted_deductions: [u8; 32],
-        pub_key: [u8; 32],
+        encrypted_deductions: [[u8; 32]; MAX_CONFIDENTIAL_DEDUCTIONS],
         nonce: u128,
     ) -> Result<()> {
-        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
-        let args = vec![
-            Argument::ArcisPubkey(pub_key),
-            Argument::PlaintextU128(nonce),
-            Argument::EncryptedU64(encrypted_salary),
-            Argument::EncryptedU16(encrypted_tax_rate),
-            Argument::EncryptedU64(encrypted_deductions),
-        ];
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        // Keep the inputs so a failed computation can be re-queued without the ciphertexts
+        let employee = &mut ctx.accounts.employee;
+        require!(
+            employee.encryption_pubkey != [0u8; 32],
+            ErrorCode::EncryptionPubkeyNotSet
+        );
+        employee.net_pay_input_salary = encrypted_salary;
+        employee.net_pay_input_deductions = encrypted_deductions;
+        employee.net_pay_input_pub_key = employee.encryption_pubkey;
+        employee.net_pay_input_nonce = nonce;
 
-        queue_computation(
-            ctx.accounts,
+        queue_net_pay(ctx, computation_offset, sequence)
+    }
+
+    /// Re-queues `calculate_net_pay` with the inputs stored by the last request, against
+    /// the payroll's current encrypted tax rate.
+    pub fn retry_net_pay(ctx: Context<CalculateNetPay>, computation_offset: u64) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let employee = &ctx.accounts.employee;
+        require!(
+            employee.net_pay_input_pub_key != [0u8; 32],
+            ErrorCode::NoStoredNetPayInputs
+        );
+
+        emit_verbose!(ctx.accounts.payroll, NetPayRetried {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            previous_offset: employee.pending_computation_offset,
             computation_offset,
-            args,
-            None,
-            vec![CalculateNetPayCallback::callback_ix(&[])],
-        )?;
+        });
 
-        Ok(())
+        queue_net_pay(ctx, computation_offset, sequence)
     }
 
     #[arcium_callback(encrypted_ix = "calculate_net_pay")]
@@ -108,428 +708,7836 @@ pub mod confidential_payroll {
         ctx: Context<CalculateNetPayCallback>,
         output: ComputationOutputs<CalculateNetPayOutput>,
     ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let employee = &mut ctx.accounts.employee;
+        let computation_offset = employee.pending_computation_offset.take().unwrap_or_default();
+
         let net_pay = match output {
             ComputationOutputs::Success(CalculateNetPayOutput { field_0 }) => field_0,
-            _ => return Err(ErrorCode::AbortedComputation.into()),
+            _ => {
+                // Returning an error would roll back the cleared pending state and drop the
+                // event, so report the abort and let the client queue a fresh computation.
+                emit!(NetPayComputationFailed {
+                    payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+                    sequence,
+                    computation_offset,
+                    employee_id: employee.employee_id.clone(),
+                });
+                return Ok(());
+            }
         };
 
+        // The employee account is the callback's writable account, so the pending offset
+        // set when queuing identifies which request this result belongs to
+        employee.encrypted_net_pay = net_pay.ciphertexts[0];
+        employee.net_pay_nonce = net_pay.nonce;
+        employee.net_pay_result_fresh = true;
+        employee.net_pay_computed_at = Clock::get()?.unix_timestamp;
+        // A new result needs its own validation; one still in flight vouches for the old one
+        employee.net_pay_validated = false;
+        employee.pending_validation_offset = None;
+
         emit!(NetPayCalculated {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
             net_pay: net_pay.ciphertexts[0],
             nonce: net_pay.nonce.to_le_bytes(),
+            computation_offset,
+            employee_id: employee.employee_id.clone(),
         });
         Ok(())
     }
 
-    pub fn process_payment(ctx: Context<ProcessPayment>) -> Result<()> {
-        let payroll = &mut ctx.accounts.payroll;
-        let employee = &mut ctx.accounts.employee;
-
-        require!(payroll.is_active, ErrorCode::PayrollInactive);
-        require!(employee.is_active, ErrorCode::EmployeeInactive);
-
-        // Check if payment is due based on frequency
-        let current_time = Clock::get()?.unix_timestamp;
-        let time_since_last_payment = current_time - employee.last_payment;
-        let payment_interval = match employee.payment_frequency {
-            PaymentFrequency::Weekly => 7 * 24 * 60 * 60, // 7 days
-            PaymentFrequency::BiWeekly => 14 * 24 * 60 * 60, // 14 days
-            PaymentFrequency::Monthly => 30 * 24 * 60 * 60, // 30 days
-        };
+    pub fn calculate_tax_withholding(
+        ctx: Context<CalculateTaxWithholding>,
+        computation_offset: u64,
+        encrypted_salary: [u8; 32],
+        pub_key: [u8; 32],
+        nonce: u128,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &ctx.accounts.payroll;
+        require!(
+            payroll.encrypted_tax_rate != [0u8; 32],
+            ErrorCode::EncryptedTaxRateNotSet
+        );
+        let encrypted_tax_rate = payroll.encrypted_tax_rate;
+        let tax_rate_pubkey = payroll.tax_rate_pubkey;
+        let tax_rate_nonce = payroll.tax_rate_nonce;
+        let rounding_mode = payroll.rounding_mode;
+        let tax_free_allowance = payroll.tax_free_allowance;
 
-        require!(time_since_last_payment >= payment_interval, ErrorCode::PaymentTooSoon);
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
 
-        // For now, use plain calculation - in full confidential version, this would use the encrypted result
-        let tax_amount = (employee.salary_amount as u128 * payroll.tax_rate as u128) / 10000;
-        let net_pay = employee.salary_amount - tax_amount as u64 - employee.deductions;
+        // Tracked separately so a net pay and a withholding request can be in flight together
+        let employee = &mut ctx.accounts.employee;
+        employee.pending_tax_computation_offset = Some(computation_offset);
 
-        require!(payroll.total_funds >= net_pay, ErrorCode::InsufficientFunds);
+        emit_verbose!(payroll, TaxWithholdingComputationQueued {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            computation_offset,
+        });
 
-        // Transfer tokens using PDA signer
-        let payroll_id = payroll.payroll_id.clone();
-        let payroll_key = payroll.key();
-        let seeds = &[
-            b"payroll_vault",
-            payroll_key.as_ref(),
-            &[payroll.vault_bump],
+        let args = vec![
+            Argument::ArcisPubkey(pub_key),
+            Argument::PlaintextU128(nonce),
+            Argument::EncryptedU64(encrypted_salary),
+            Argument::ArcisPubkey(tax_rate_pubkey),
+            Argument::PlaintextU128(tax_rate_nonce),
+            Argument::EncryptedU16(encrypted_tax_rate),
+            Argument::PlaintextU8(rounding_mode as u8),
+            Argument::PlaintextU64(tax_free_allowance),
+            Argument::PlaintextBool(employee.classification.withholds_tax()),
         ];
-        let signer = &[&seeds[..]];
 
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.payroll_vault.to_account_info(),
-                    to: ctx.accounts.employee_wallet.to_account_info(),
-                    authority: ctx.accounts.payroll_vault.to_account_info(),
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![CalculateTaxWithholdingCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.employee.key(),
+                    is_writable: true,
                 },
-                signer,
-            ),
-            net_pay,
+                CallbackAccount {
+                    pubkey: ctx.accounts.payroll.key(),
+                    is_writable: true,
+                },
+            ])],
         )?;
 
-        payroll.total_funds -= net_pay;
-        employee.last_payment = current_time;
-
-        emit!(PaymentProcessed {
-            payroll_id,
-            employee_id: employee.employee_id.clone(),
-            gross_salary: employee.salary_amount,
-            tax_amount: tax_amount as u64,
-            deductions: employee.deductions,
-            net_pay,
-        });
-
         Ok(())
     }
 
-    pub fn deposit_funds(ctx: Context<DepositFunds>, amount: u64) -> Result<()> {
-        let payroll = &mut ctx.accounts.payroll;
-
-        require!(payroll.is_active, ErrorCode::PayrollInactive);
-
-        // Transfer tokens to vault
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.authority_token_account.to_account_info(),
-                    to: ctx.accounts.payroll_vault.to_account_info(),
-                    authority: ctx.accounts.authority.to_account_info(),
-                },
-            ),
-            amount,
-        )?;
+    #[arcium_callback(encrypted_ix = "calculate_tax_withholding")]
+    pub fn calculate_tax_withholding_callback(
+        ctx: Context<CalculateTaxWithholdingCallback>,
+        output: ComputationOutputs<CalculateTaxWithholdingOutput>,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let employee = &mut ctx.accounts.employee;
+        let computation_offset = employee
+            .pending_tax_computation_offset
+            .take()
+            .unwrap_or_default();
 
-        payroll.total_funds += amount;
+        let tax = match output {
+            ComputationOutputs::Success(CalculateTaxWithholdingOutput { field_0 }) => field_0,
+            _ => {
+                emit!(TaxWithholdingComputationFailed {
+                    payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+                    sequence,
+                    computation_offset,
+                    employee_id: employee.employee_id.clone(),
+                });
+                return Ok(());
+            }
+        };
 
-        emit!(FundsDeposited {
-            payroll_id: payroll.payroll_id.clone(),
-            amount,
-            total_funds: payroll.total_funds,
+        emit!(TaxWithholdingCalculated {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            tax: tax.ciphertexts[0],
+            nonce: tax.nonce.to_le_bytes(),
+            computation_offset,
+            employee_id: employee.employee_id.clone(),
         });
-
         Ok(())
     }
 
-    pub fn pause_payroll(ctx: Context<PausePayroll>) -> Result<()> {
-        let payroll = &mut ctx.accounts.payroll;
-        require!(payroll.is_active, ErrorCode::PayrollActive);
-
-        payroll.is_active = false;
+    /// Starts (or restarts, e.g. at a tax year boundary) the encrypted YTD withholding
+    /// from a client-encrypted zero; the circuit needs a prior ciphertext to add to.
+    pub fn reset_ytd_tax(
+        ctx: Context<UpdateEmployee>,
+        encrypted_zero: [u8; 32],
+        pub_key: [u8; 32],
+        nonce: u128,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let employee = &mut ctx.accounts.employee;
+        require!(
+            employee.pending_ytd_computation_offset.is_none(),
+            ErrorCode::YtdTaxComputationPending
+        );
+        employee.ytd_tax_encrypted = encrypted_zero;
+        employee.ytd_tax_pub_key = pub_key;
+        employee.ytd_tax_nonce = nonce;
 
-        emit!(PayrollPaused {
-            payroll_id: payroll.payroll_id.clone(),
+        emit_verbose!(ctx.accounts.payroll, YtdTaxReset {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
         });
 
         Ok(())
     }
 
-    pub fn resume_payroll(ctx: Context<ResumePayroll>) -> Result<()> {
-        let payroll = &mut ctx.accounts.payroll;
-        require!(!payroll.is_active, ErrorCode::PayrollInactive);
+    /// Adds an encrypted withholding (e.g. a `TaxWithholdingCalculated` result with its
+    /// nonce) to the employee's encrypted YTD total. One accumulation may be in flight at
+    /// a time, so no update is lost to a stale prior ciphertext.
+    pub fn accumulate_ytd_tax(
+        ctx: Context<AccumulateYtdTax>,
+        computation_offset: u64,
+        encrypted_tax: [u8; 32],
+        pub_key: [u8; 32],
+        nonce: u128,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
 
-        payroll.is_active = true;
+        let employee = &mut ctx.accounts.employee;
+        require!(
+            employee.ytd_tax_encrypted != [0u8; 32],
+            ErrorCode::YtdTaxNotInitialized
+        );
+        require!(
+            employee.pending_ytd_computation_offset.is_none(),
+            ErrorCode::YtdTaxComputationPending
+        );
+        employee.pending_ytd_computation_offset = Some(computation_offset);
 
-        emit!(PayrollResumed {
-            payroll_id: payroll.payroll_id.clone(),
-        });
+        emit_verbose!(ctx.accounts.payroll, YtdTaxComputationQueued {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            computation_offset,
+        });
+
+        let args = vec![
+            Argument::ArcisPubkey(employee.ytd_tax_pub_key),
+            Argument::PlaintextU128(employee.ytd_tax_nonce),
+            Argument::EncryptedU64(employee.ytd_tax_encrypted),
+            Argument::ArcisPubkey(pub_key),
+            Argument::PlaintextU128(nonce),
+            Argument::EncryptedU64(encrypted_tax),
+        ];
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![AccumulateYtdTaxCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.employee.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.payroll.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
 
         Ok(())
     }
-}
 
-#[queue_computation_accounts("calculate_net_pay", payer)]
-#[derive(Accounts)]
-#[instruction(computation_offset: u64)]
-pub struct CalculateNetPay<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(
-        init_if_needed,
-        space = 9,
-        payer = payer,
-        seeds = [&SIGN_PDA_SEED],
-        bump,
-        address = derive_sign_pda!(),
-    )]
-    pub sign_pda_account: Account<'info, SignerAccount>,
-    #[account(
-        address = derive_mxe_pda!()
-    )]
-    pub mxe_account: Account<'info, MXEAccount>,
-    #[account(
-        mut,
-        address = derive_mempool_pda!()
-    )]
-    /// CHECK: mempool_account, checked by the arcium program.
-    pub mempool_account: UncheckedAccount<'info>,
-    #[account(
-        mut,
-        address = derive_execpool_pda!()
-    )]
-    /// CHECK: executing_pool, checked by the arcium program.
-    pub executing_pool: UncheckedAccount<'info>,
-    #[account(
-        mut,
-        address = derive_comp_pda!(computation_offset)
-    )]
-    /// CHECK: computation_account, checked by the arcium program.
-    pub computation_account: UncheckedAccount<'info>,
-    #[account(
-        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CALCULATE_NET_PAY)
-    )]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
-    #[account(
-        mut,
-        address = derive_cluster_pda!(mxe_account)
-    )]
-    pub cluster_account: Account<'info, Cluster>,
-    #[account(
-        mut,
-        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
-    )]
-    pub pool_account: Account<'info, FeePool>,
-    #[account(
-        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS
-    )]
-    pub clock_account: Account<'info, ClockAccount>,
-    pub system_program: Program<'info, System>,
-    pub arcium_program: Program<'info, Arcium>,
+    #[arcium_callback(encrypted_ix = "accumulate_ytd_tax")]
+    pub fn accumulate_ytd_tax_callback(
+        ctx: Context<AccumulateYtdTaxCallback>,
+        output: ComputationOutputs<AccumulateYtdTaxOutput>,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let employee = &mut ctx.accounts.employee;
+        let computation_offset = employee
+            .pending_ytd_computation_offset
+            .take()
+            .unwrap_or_default();
+
+        let ytd_tax = match output {
+            ComputationOutputs::Success(AccumulateYtdTaxOutput { field_0 }) => field_0,
+            _ => {
+                // The stored total is untouched, so the same withholding can be re-submitted
+                emit!(YtdTaxComputationFailed {
+                    payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+                    sequence,
+                    computation_offset,
+                    employee_id: employee.employee_id.clone(),
+                });
+                return Ok(());
+            }
+        };
+
+        // Still owned by ytd_tax_pub_key; only the ciphertext and nonce move on
+        employee.ytd_tax_encrypted = ytd_tax.ciphertexts[0];
+        employee.ytd_tax_nonce = ytd_tax.nonce;
+
+        emit!(YtdTaxAccumulated {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            ytd_tax: ytd_tax.ciphertexts[0],
+            nonce: ytd_tax.nonce.to_le_bytes(),
+            computation_offset,
+            employee_id: employee.employee_id.clone(),
+        });
+        Ok(())
+    }
+
+    /// Checks the stored net pay result against the salary it was computed from, both
+    /// still encrypted, so a result inflated by bad inputs (e.g. deductions above the
+    /// salary wrapping the subtraction) is caught before it authorizes a payment. Only
+    /// whether the check held is revealed.
+    pub fn validate_net_pay(ctx: Context<ValidateNetPay>, computation_offset: u64) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let employee = &mut ctx.accounts.employee;
+        require!(
+            employee.encrypted_net_pay != [0u8; 32],
+            ErrorCode::NoNetPayResult
+        );
+        require!(
+            employee.pending_validation_offset.is_none(),
+            ErrorCode::NetPayValidationPending
+        );
+        employee.pending_validation_offset = Some(computation_offset);
+
+        emit_verbose!(ctx.accounts.payroll, NetPayValidationQueued {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            computation_offset,
+        });
+
+        // The salary is the first element of the calculate_net_pay input, so on its own it
+        // decrypts under the input nonce; the result is sealed to the same key
+        let args = vec![
+            Argument::ArcisPubkey(employee.net_pay_input_pub_key),
+            Argument::PlaintextU128(employee.net_pay_input_nonce),
+            Argument::EncryptedU64(employee.net_pay_input_salary),
+            Argument::ArcisPubkey(employee.net_pay_input_pub_key),
+            Argument::PlaintextU128(employee.net_pay_nonce),
+            Argument::EncryptedU64(employee.encrypted_net_pay),
+        ];
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![ValidateNetPayCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: ctx.accounts.employee.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.payroll.key(),
+                    is_writable: true,
+                },
+            ])],
+        )?;
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "validate_net_pay")]
+    pub fn validate_net_pay_callback(
+        ctx: Context<ValidateNetPayCallback>,
+        output: ComputationOutputs<ValidateNetPayOutput>,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let employee = &mut ctx.accounts.employee;
+        // Cleared when a newer net pay result lands, which this check didn't cover
+        let Some(computation_offset) = employee.pending_validation_offset.take() else {
+            return Ok(());
+        };
+
+        let valid = match output {
+            ComputationOutputs::Success(ValidateNetPayOutput { field_0 }) => field_0,
+            _ => {
+                emit!(NetPayValidationFailed {
+                    payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+                    sequence,
+                    computation_offset,
+                    employee_id: employee.employee_id.clone(),
+                });
+                return Ok(());
+            }
+        };
+
+        employee.net_pay_validated = valid;
+
+        emit!(NetPayValidated {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            computation_offset,
+            employee_id: employee.employee_id.clone(),
+            valid,
+        });
+        Ok(())
+    }
+
+    /// Encrypted total net pay for up to `MAX_TOTAL_NET_PAYROLL_INPUTS` employees, for
+    /// treasury planning without revealing any one salary. All inputs share `pub_key` and
+    /// `nonce`; unused slots are encrypted zeros. Larger payrolls are totalled in chunks.
+    pub fn calculate_total_net_payroll(
+        ctx: Context<CalculateTotalNetPayroll>,
+        computation_offset: u64,
+        encrypted_salaries: [[u8; 32]; MAX_TOTAL_NET_PAYROLL_INPUTS],
+        encrypted_deductions: [[[u8; 32]; MAX_CONFIDENTIAL_DEDUCTIONS];
+            MAX_TOTAL_NET_PAYROLL_INPUTS],
+        pub_key: [u8; 32],
+        nonce: u128,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        let payroll = &mut ctx.accounts.payroll;
+        require!(
+            payroll.encrypted_tax_rate != [0u8; 32],
+            ErrorCode::EncryptedTaxRateNotSet
+        );
+        require!(
+            payroll.pending_total_net_pay_offset.is_none(),
+            ErrorCode::TotalNetPayComputationPending
+        );
+        payroll.pending_total_net_pay_offset = Some(computation_offset);
+
+        emit_verbose!(payroll, TotalNetPayrollQueued {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            computation_offset,
+        });
+
+        let mut args = vec![
+            Argument::ArcisPubkey(pub_key),
+            Argument::PlaintextU128(nonce),
+        ];
+        // Flattened in PayrollInput field order, one employee after another
+        for (salary, deductions) in encrypted_salaries.iter().zip(encrypted_deductions.iter()) {
+            args.push(Argument::EncryptedU64(*salary));
+            args.extend(deductions.iter().map(|deduction| Argument::EncryptedU64(*deduction)));
+        }
+        args.extend([
+            Argument::ArcisPubkey(payroll.tax_rate_pubkey),
+            Argument::PlaintextU128(payroll.tax_rate_nonce),
+            Argument::EncryptedU16(payroll.encrypted_tax_rate),
+            Argument::PlaintextU8(payroll.rounding_mode as u8),
+            Argument::PlaintextU64(payroll.tax_free_allowance),
+        ]);
+        let payroll_key = payroll.key();
+
+        queue_computation(
+            ctx.accounts,
+            computation_offset,
+            args,
+            None,
+            vec![CalculateTotalNetPayrollCallback::callback_ix(&[
+                CallbackAccount {
+                    pubkey: payroll_key,
+                    is_writable: true,
+                },
+            ])],
+        )?;
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "calculate_total_net_payroll")]
+    pub fn calculate_total_net_payroll_callback(
+        ctx: Context<CalculateTotalNetPayrollCallback>,
+        output: ComputationOutputs<CalculateTotalNetPayrollOutput>,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        let computation_offset = payroll
+            .pending_total_net_pay_offset
+            .take()
+            .unwrap_or_default();
+
+        let total = match output {
+            ComputationOutputs::Success(CalculateTotalNetPayrollOutput { field_0 }) => field_0,
+            _ => {
+                emit!(TotalNetPayrollFailed {
+                    payroll_id: payroll.payroll_id.clone(),
+                    sequence,
+                    computation_offset,
+                });
+                return Ok(());
+            }
+        };
+
+        payroll.encrypted_total_net_pay = total.ciphertexts[0];
+        payroll.total_net_pay_nonce = total.nonce;
+
+        emit!(TotalNetPayrollCalculated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            total_net_pay: total.ciphertexts[0],
+            nonce: total.nonce.to_le_bytes(),
+            computation_offset,
+        });
+        Ok(())
+    }
+
+    /// `idempotency_key` should be unique per intended payment (e.g. random); retrying a
+    /// transaction with the same key is rejected instead of paying twice. A non-empty
+    /// `memo` is written with the Memo program next to the transfers, for bank reconciliation.
+    /// With `business_day_shift` on, `adjusted_pay_ts` is a client-computed business day up
+    /// to `max_business_day_shift` before the next due date; from then on the payment is
+    /// made as if that due date had arrived.
+    pub fn process_payment<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ProcessPayment<'info>>,
+        idempotency_key: [u8; 16],
+        memo: String,
+        adjusted_pay_ts: Option<i64>,
+    ) -> Result<()> {
+        pay_employee(ctx, idempotency_key, memo, adjusted_pay_ts, false)
+    }
+
+    /// Off-cycle payment for emergencies and corrections: the authority pays one period
+    /// even when none is due yet, subject to every other process_payment check. The
+    /// next due run is then skipped rather than paid, and ForcedPaymentProcessed marks
+    /// the run for audits.
+    pub fn force_payment<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ProcessPayment<'info>>,
+        idempotency_key: [u8; 16],
+        memo: String,
+    ) -> Result<()> {
+        let authority = ctx
+            .accounts
+            .authority
+            .as_ref()
+            .ok_or(ErrorCode::Unauthorized)?;
+        require_keys_eq!(authority.key(), ctx.accounts.payroll.authority, ErrorCode::Unauthorized);
+
+        pay_employee(ctx, idempotency_key, memo, None, true)
+    }
+
+    /// Failed process_payment calls roll back entirely, so operators record near-misses
+    /// here instead. Succeeds only when a payment attempted now would fail. Underfunded
+    /// attempts also build the streak behind payroll.funding_failure_threshold.
+    pub fn record_payment_attempt(ctx: Context<RecordPaymentAttempt>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &ctx.accounts.payroll;
+        let employee = &mut ctx.accounts.employee;
+        let current_time = Clock::get()?.unix_timestamp;
+        let shift_seconds = employee.apply_pause_adjustment(payroll.total_paused_seconds)?;
+        if shift_seconds > 0 {
+            emit_verbose!(payroll, ScheduleAdjustedForPause {
+                payroll_id: payroll.payroll_id.clone(),
+                sequence,
+                employee_id: employee.employee_id.clone(),
+                shift_seconds,
+                last_payment: employee.last_payment,
+            });
+        }
+        let periods_due =
+            employee.periods_due(payroll.schedule_time(current_time)?, payroll.pay_day_anchor)?;
+
+        let reason = if payroll.frozen {
+            PaymentFailureReason::PayrollFrozen
+        } else if !payroll.is_active {
+            PaymentFailureReason::PayrollInactive
+        } else if !employee.is_active {
+            PaymentFailureReason::EmployeeInactive
+        } else if periods_due < 1 {
+            PaymentFailureReason::TooSoon
+        } else {
+            let periods = periods_due.min(payroll.max_catchup_periods as i64) as u64;
+            let gross = resolve_gross_salary(
+                payroll,
+                employee,
+                ctx.accounts.price_update.as_ref(),
+                current_time,
+            )?;
+            // Accrued overtime is paid on top, as in process_payment
+            let overtime = employee.overtime_pay;
+            let overtime_tax = payroll.overtime_tax_for(employee)?;
+            let net_pay = PayBreakdown::compute(payroll, employee, gross)?
+                .net
+                .checked_mul(periods)
+                .and_then(|net| net.checked_add(overtime - overtime_tax))
+                .ok_or(ErrorCode::MathOverflow)?;
+            let clawback = payroll.clawback_for(employee.clawback_balance, net_pay, periods)?;
+            let payable = net_pay
+                .saturating_sub(clawback)
+                .checked_add(employee.outstanding_balance)
+                .ok_or(ErrorCode::MathOverflow)?;
+            let deferred = payable < payroll.min_payment;
+            let payout = if deferred { 0 } else { payable };
+            let platform_fee = payroll.platform_fee_for(payout)?;
+            let payout = if payroll.platform_fee_from_employee {
+                payout.saturating_sub(platform_fee)
+            } else {
+                payout
+            };
+            let payout = if deferred {
+                payout
+            } else {
+                payroll.round_net_pay(payout, employee.carry_forward)?.0
+            };
+            let employer_tax = payroll
+                .employer_tax_for(gross, periods)?
+                .checked_add(payroll.employer_tax_for(overtime, 1)?)
+                .ok_or(ErrorCode::MathOverflow)?;
+            let required = payout
+                .checked_add(employer_tax)
+                .and_then(|required| required.checked_add(platform_fee))
+                .ok_or(ErrorCode::MathOverflow)?;
+            require!(payroll.total_funds < required, ErrorCode::PaymentWouldSucceed);
+            PaymentFailureReason::InsufficientFunds
+        };
+
+        employee.failed_attempts = employee
+            .failed_attempts
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(PaymentAttemptFailed {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            reason,
+            failed_attempts: employee.failed_attempts,
+        });
+
+        // Underfunding counts once per missed due date, however often it is recorded;
+        // process_payment resets the streak
+        if reason == PaymentFailureReason::InsufficientFunds {
+            let due = if employee.last_payment == 0 {
+                0
+            } else {
+                employee.advance_schedule(periods_due as u64, payroll.pay_day_anchor)?
+            };
+            if employee.consecutive_funding_failures == 0
+                || employee.last_funding_failure_due != due
+            {
+                employee.consecutive_funding_failures = employee
+                    .consecutive_funding_failures
+                    .checked_add(1)
+                    .ok_or(ErrorCode::MathOverflow)?;
+                employee.last_funding_failure_due = due;
+            }
+            if payroll.funding_failure_threshold > 0
+                && employee.consecutive_funding_failures >= payroll.funding_failure_threshold
+            {
+                employee.is_active = false;
+                emit!(EmployeeAutoSuspended {
+                    payroll_id: payroll.payroll_id.clone(),
+                    sequence,
+                    employee_id: employee.employee_id.clone(),
+                    consecutive_funding_failures: employee.consecutive_funding_failures,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn close_pay_stub(ctx: Context<ClosePayStub>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        emit_verbose!(ctx.accounts.payroll, PayStubClosed {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee: ctx.accounts.pay_stub.employee,
+            payment_sequence: ctx.accounts.pay_stub.sequence,
+        });
+
+        Ok(())
+    }
+
+    /// Creates the employee's pay history. From then on every payment must pass it, so
+    /// the record can't be skipped.
+    pub fn init_pay_history(ctx: Context<InitPayHistory>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let employee = &mut ctx.accounts.employee;
+        employee.pay_history_enabled = true;
+        ctx.accounts.pay_history.set_inner(PayHistory {
+            employee: employee.key(),
+            next_index: 0,
+            len: 0,
+            records: [PaymentRecord::default(); PAY_HISTORY_LEN],
+        });
+
+        emit_verbose!(ctx.accounts.payroll, PayHistoryInitialized {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Emits the recorded payments, oldest first.
+    pub fn report_pay_history(ctx: Context<ReportPayHistory>) -> Result<()> {
+        emit!(PayHistoryReported {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence: ctx.accounts.payroll.sequence,
+            employee_id: ctx.accounts.employee.employee_id.clone(),
+            records: ctx.accounts.pay_history.ordered(),
+        });
+
+        Ok(())
+    }
+
+    pub fn preview_net_pay(ctx: Context<PreviewNetPay>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.sequence;
+        let payroll = &ctx.accounts.payroll;
+        let employee = &ctx.accounts.employee;
+
+        // Same math as process_payment, without moving funds or touching last_payment
+        let gross = resolve_gross_salary(
+            payroll,
+            employee,
+            ctx.accounts.price_update.as_ref(),
+            Clock::get()?.unix_timestamp,
+        )?;
+        let breakdown = PayBreakdown::compute(payroll, employee, gross)?;
+
+        emit!(NetPayPreview {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            gross: breakdown.gross,
+            tax: breakdown.tax,
+            deductions: breakdown.deductions,
+            percentage_deduction: breakdown.percentage_deduction,
+            garnishment: breakdown.garnishment,
+            net: breakdown.net,
+        });
+
+        Ok(())
+    }
+
+    pub fn next_payment_timestamp(ctx: Context<ReadEmployee>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.sequence;
+        // Report the schedule as the next payment will see it, paused time included
+        let mut employee = Employee::clone(&ctx.accounts.employee);
+        employee.apply_pause_adjustment(ctx.accounts.payroll.total_paused_seconds)?;
+
+        emit!(NextPayment {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            next_ts: employee.next_payment_ts(ctx.accounts.payroll.pay_day_anchor)?,
+        });
+
+        Ok(())
+    }
+
+    /// Seconds until process_payment stops failing with PaymentTooSoon, counting the
+    /// early-payment tolerance. A reverted payment discards its logs, so clients call
+    /// this instead of deriving the schedule themselves.
+    pub fn payment_due_in(ctx: Context<ReadEmployee>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.sequence;
+        let payroll = &ctx.accounts.payroll;
+        let mut employee = Employee::clone(&ctx.accounts.employee);
+        employee.apply_pause_adjustment(payroll.total_paused_seconds)?;
+        let now = Clock::get()?.unix_timestamp;
+
+        let due_ts = employee.next_payment_ts(payroll.pay_day_anchor)?;
+        let schedule_time = payroll.schedule_time(now)?;
+        let seconds_remaining = due_ts.saturating_sub(schedule_time).max(0);
+
+        emit!(PaymentDueIn {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            due_ts,
+            seconds_remaining,
+        });
+
+        Ok(())
+    }
+
+    /// Reports how long the employee has been on this payroll. Imported employees keep the
+    /// onboarding time of their original payroll.
+    pub fn employee_tenure(ctx: Context<ReadEmployee>) -> Result<()> {
+        let employee = &ctx.accounts.employee;
+        let now = Clock::get()?.unix_timestamp;
+        // Accounts migrated from before created_at existed have no onboarding time
+        let tenure_seconds = (employee.created_at > 0)
+            .then(|| now.saturating_sub(employee.created_at).max(0));
+
+        emit!(EmployeeTenure {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence: ctx.accounts.payroll.sequence,
+            employee_id: employee.employee_id.clone(),
+            created_at: employee.created_at,
+            tenure_seconds,
+        });
+
+        Ok(())
+    }
+
+    /// Sums one period of net pay plus employer tax for the active employees passed in
+    /// `remaining_accounts` and reports whether the vault covers it. Never moves funds.
+    pub fn check_solvency<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CheckSolvency<'info>>,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.sequence;
+        require!(
+            ctx.remaining_accounts.len() <= MAX_EMPLOYEES_PER_SOLVENCY_CHECK,
+            ErrorCode::BatchTooLarge
+        );
+        check_batch_size(ctx.remaining_accounts.len(), COMPUTE_UNITS_PER_EMPLOYEE_READ)?;
+
+        let payroll = &ctx.accounts.payroll;
+        let payroll_key = payroll.key();
+        let now = Clock::get()?.unix_timestamp;
+        let mut seen: Vec<Pubkey> = Vec::with_capacity(ctx.remaining_accounts.len());
+        let mut total_obligations: u64 = 0;
+
+        for employee_info in ctx.remaining_accounts.iter() {
+            require!(
+                !seen.contains(employee_info.key),
+                ErrorCode::InvalidEmployeeAccount
+            );
+            seen.push(*employee_info.key);
+
+            let employee = Account::<Employee>::try_from(employee_info)?;
+            require_keys_eq!(employee.payroll, payroll_key, ErrorCode::InvalidEmployeeAccount);
+            if !employee.is_active {
+                continue;
+            }
+
+            let gross = resolve_gross_salary(
+                payroll,
+                &employee,
+                ctx.accounts.price_update.as_ref(),
+                now,
+            )?;
+            let breakdown = PayBreakdown::compute(payroll, &employee, gross)?;
+            // Overtime accrued so far is owed with the next payment
+            let overtime = employee.overtime_pay;
+            let overtime_net = overtime - payroll.overtime_tax_for(&employee)?;
+            let employer_tax = payroll
+                .employer_tax_for(gross, 1)?
+                .checked_add(payroll.employer_tax_for(overtime, 1)?)
+                .ok_or(ErrorCode::MathOverflow)?;
+            total_obligations = total_obligations
+                .checked_add(breakdown.net)
+                .and_then(|total| total.checked_add(overtime_net))
+                .and_then(|total| total.checked_add(employer_tax))
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+
+        emit!(SolvencyReport {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            total_funds: payroll.total_funds,
+            total_obligations,
+            is_solvent: payroll.total_funds >= total_obligations,
+        });
+
+        Ok(())
+    }
+
+    /// Reports the payroll's running aggregates for dashboards. Never modifies state.
+    pub fn emit_payroll_stats(ctx: Context<EmitPayrollStats>) -> Result<()> {
+        let payroll = &ctx.accounts.payroll;
+
+        emit!(PayrollStats {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence: payroll.sequence,
+            employee_count: payroll.employee_count,
+            is_active: payroll.is_active,
+            total_funds: payroll.total_funds,
+            current_period: payroll.current_period,
+            total_paid_in_period: payroll.total_paid_in_period,
+            lifetime_paid: payroll.lifetime_paid,
+            lifetime_deposited: payroll.lifetime_deposited,
+        });
+
+        Ok(())
+    }
+
+    /// On-chain attestation for audits: fails unless `payroll_vault` is the payroll's vault
+    /// PDA, holds the payroll's mint and is owned by the vault PDA (or the external
+    /// custody key, when one is set). Never moves funds.
+    pub fn verify_vault(ctx: Context<VerifyVault>) -> Result<()> {
+        let payroll = &ctx.accounts.payroll;
+        require!(!payroll.native, ErrorCode::NativeUnsupported);
+
+        let vault_info = ctx.accounts.payroll_vault.to_account_info();
+        let (expected, bump) = pdas::payroll_vault_pda(&payroll.key());
+        require!(
+            vault_info.key() == expected && bump == payroll.vault_bump,
+            ErrorCode::VaultAddressMismatch
+        );
+        let vault = InterfaceAccount::<TokenAccount>::try_from(&vault_info)?;
+        require_keys_eq!(vault.mint, payroll.payment_token, ErrorCode::MintMismatch);
+        let expected_owner = payroll.vault_authority.unwrap_or(expected);
+        require_keys_eq!(vault.owner, expected_owner, ErrorCode::VaultOwnerMismatch);
+
+        emit!(VaultVerified {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence: payroll.sequence,
+            vault: expected,
+            mint: vault.mint,
+            authority: vault.owner,
+            balance: vault.amount,
+            total_funds: payroll.total_funds,
+        });
+
+        Ok(())
+    }
+
+    /// Repairs a drifted `employee_count`. Every counted employee must be passed in
+    /// `remaining_accounts`, so the new count is backed by accounts that exist on-chain.
+    /// Employee accounts are never closed, so a subset can't prove any are gone and the
+    /// count only ever moves up. Payrolls too large for one transaction can't be recounted.
+    pub fn recount_employees<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UpdatePayrollConfig<'info>>,
+        actual_count: u64,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        // Every employee has to fit in one call, which bounds the payrolls this can reconcile
+        check_batch_size(ctx.remaining_accounts.len(), COMPUTE_UNITS_PER_EMPLOYEE_READ)?;
+        require!(
+            ctx.remaining_accounts.len() as u64 == actual_count,
+            ErrorCode::EmployeeCountMismatch
+        );
+
+        let payroll_key = ctx.accounts.payroll.key();
+        let mut seen: Vec<Pubkey> = Vec::with_capacity(ctx.remaining_accounts.len());
+        for employee_info in ctx.remaining_accounts.iter() {
+            require!(
+                !seen.contains(employee_info.key),
+                ErrorCode::InvalidEmployeeAccount
+            );
+            seen.push(*employee_info.key);
+
+            let employee = Account::<Employee>::try_from(employee_info)?;
+            require_keys_eq!(employee.payroll, payroll_key, ErrorCode::InvalidEmployeeAccount);
+        }
+
+        let payroll = &mut ctx.accounts.payroll;
+        let old = payroll.employee_count;
+        // Lowering it would let reserve_employee_slots admit employees past max_employees
+        require!(actual_count >= old, ErrorCode::EmployeeCountDecrease);
+        payroll.employee_count = actual_count;
+
+        emit_verbose!(payroll, EmployeeCountReconciled {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            old,
+            new: actual_count,
+        });
+
+        Ok(())
+    }
+
+    pub fn schedule_salary_change(
+        ctx: Context<UpdateEmployee>,
+        new_salary: u64,
+        effective_ts: i64,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(effective_ts > current_time, ErrorCode::InvalidEffectiveTimestamp);
+        ctx.accounts.payroll.check_salary_bounds(new_salary)?;
+
+        let employee = &mut ctx.accounts.employee;
+        // Consented cuts go through update_employee_salary, which takes the employee's signature
+        require!(
+            !employee.employee_must_approve_cuts || new_salary >= employee.salary_amount,
+            ErrorCode::SalaryCutNotApproved
+        );
+        employee.pending_salary = Some(new_salary);
+        employee.pending_salary_effective = effective_ts;
+
+        emit_verbose!(ctx.accounts.payroll, SalaryChangeScheduled {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            new_salary,
+            effective_ts,
+        });
+
+        Ok(())
+    }
+
+    /// Changes the salary immediately. Cuts to an employee who opted into cut approval
+    /// also need the signature of their wallet's owner.
+    pub fn update_employee_salary(ctx: Context<UpdateEmployeeSalary>, new_salary: u64) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        ctx.accounts.payroll.check_salary_bounds(new_salary)?;
+
+        let employee = &mut ctx.accounts.employee;
+        let old_salary = employee.salary_amount;
+        if new_salary < old_salary && employee.employee_must_approve_cuts {
+            let wallet = ctx
+                .accounts
+                .employee_wallet
+                .as_ref()
+                .ok_or(ErrorCode::SalaryCutNotApproved)?;
+            let wallet_owner = ctx
+                .accounts
+                .wallet_owner
+                .as_ref()
+                .ok_or(ErrorCode::SalaryCutNotApproved)?;
+            require_keys_eq!(wallet_owner.key(), wallet.owner, ErrorCode::SalaryCutNotApproved);
+
+            emit_verbose!(ctx.accounts.payroll, SalaryCutApproved {
+                payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+                sequence,
+                employee_id: employee.employee_id.clone(),
+                old_salary,
+                new_salary,
+                approved_by: wallet_owner.key(),
+            });
+        }
+
+        employee.salary_amount = new_salary;
+
+        emit_verbose!(ctx.accounts.payroll, SalaryUpdated {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            old_salary,
+            new_salary,
+        });
+
+        Ok(())
+    }
+
+    /// Only the employee can opt in or out, so the authority cannot switch consent off before a cut.
+    pub fn set_cut_approval(ctx: Context<SetCutApproval>, required: bool) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let employee = &mut ctx.accounts.employee;
+        employee.employee_must_approve_cuts = required;
+
+        emit_verbose!(ctx.accounts.payroll, CutApprovalUpdated {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            required,
+        });
+
+        Ok(())
+    }
+
+    /// Rotates the key net pay is sealed to; stored results keep the old one until recomputed.
+    pub fn update_encryption_pubkey(
+        ctx: Context<UpdateEncryptionPubkey>,
+        encryption_pubkey: [u8; 32],
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(
+            encryption_pubkey != [0u8; 32],
+            ErrorCode::EncryptionPubkeyNotSet
+        );
+        let employee = &mut ctx.accounts.employee;
+        let old_pubkey = employee.encryption_pubkey;
+        employee.encryption_pubkey = encryption_pubkey;
+
+        emit_verbose!(ctx.accounts.payroll, EncryptionPubkeyUpdated {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            old_pubkey,
+            new_pubkey: encryption_pubkey,
+        });
+
+        Ok(())
+    }
+
+    /// Replaces the employee's direct-deposit splits; an empty list pays everything to
+    /// employee.wallet again. Signed by the wallet owner, since it redirects their pay.
+    /// The split token accounts must be passed in `remaining_accounts`, in order.
+    pub fn set_deposit_splits<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SetCutApproval<'info>>,
+        splits: Vec<DepositSplit>,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(!ctx.accounts.payroll.native, ErrorCode::NativeUnsupported);
+        require!(
+            splits.len() <= MAX_DEPOSIT_SPLITS
+                && (splits.is_empty()
+                    || splits.iter().map(|split| split.bps as u32).sum::<u32>() == 10000),
+            ErrorCode::InvalidDepositSplits
+        );
+        check_split_wallets(
+            &splits,
+            ctx.remaining_accounts,
+            &ctx.accounts.payroll.payment_token,
+        )?;
+
+        let employee = &mut ctx.accounts.employee;
+        employee.deposit_splits = splits.clone();
+
+        emit_verbose!(ctx.accounts.payroll, DepositSplitsUpdated {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            splits,
+        });
+
+        Ok(())
+    }
+
+    pub fn add_deduction(ctx: Context<UpdateEmployee>, label: String, amount: u64) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(
+            !label.is_empty() && label.len() <= MAX_DEDUCTION_LABEL_LEN,
+            ErrorCode::InvalidDeductionLabel
+        );
+
+        let employee = &mut ctx.accounts.employee;
+        require!(
+            employee.deduction_items.len() < MAX_DEDUCTION_ITEMS,
+            ErrorCode::TooManyDeductions
+        );
+        require!(
+            !employee.deduction_items.iter().any(|item| item.label == label),
+            ErrorCode::InvalidDeductionLabel
+        );
+
+        employee.deduction_items.push(DeductionItem {
+            label: label.clone(),
+            amount,
+        });
+
+        emit_verbose!(ctx.accounts.payroll, DeductionAdded {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            label,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    pub fn remove_deduction(ctx: Context<UpdateEmployee>, label: String) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let employee = &mut ctx.accounts.employee;
+        let index = employee
+            .deduction_items
+            .iter()
+            .position(|item| item.label == label)
+            .ok_or(ErrorCode::DeductionNotFound)?;
+        let removed = employee.deduction_items.remove(index);
+
+        emit_verbose!(ctx.accounts.payroll, DeductionRemoved {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            label: removed.label,
+            amount: removed.amount,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_garnishment(
+        ctx: Context<UpdateEmployee>,
+        garnishment_amount: u64,
+        garnishment_bps: u16,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(garnishment_bps <= 10000, ErrorCode::InvalidGarnishmentBps);
+
+        let employee = &mut ctx.accounts.employee;
+        employee.garnishment_amount = garnishment_amount;
+        employee.garnishment_bps = garnishment_bps;
+
+        emit_verbose!(ctx.accounts.payroll, GarnishmentUpdated {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            garnishment_amount,
+            garnishment_bps,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_deduction_bps(ctx: Context<UpdateEmployee>, deduction_bps: u16) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(deduction_bps <= 10000, ErrorCode::InvalidDeductionBps);
+
+        let employee = &mut ctx.accounts.employee;
+        employee.deduction_bps = deduction_bps;
+
+        emit_verbose!(ctx.accounts.payroll, DeductionBpsUpdated {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            deduction_bps,
+        });
+
+        Ok(())
+    }
+
+    /// Accrues gross overtime for a non-exempt employee, paid once with their next payment.
+    pub fn record_overtime(ctx: Context<UpdateEmployee>, amount: u64) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(amount > 0, ErrorCode::InvalidOvertimeAmount);
+
+        let employee = &mut ctx.accounts.employee;
+        require!(
+            employee.classification.earns_overtime(),
+            ErrorCode::OvertimeNotEligible
+        );
+        employee.overtime_pay = employee
+            .overtime_pay
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit_verbose!(ctx.accounts.payroll, OvertimeRecorded {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            amount,
+            overtime_pay: employee.overtime_pay,
+        });
+
+        Ok(())
+    }
+
+    pub fn add_clawback(ctx: Context<UpdateEmployee>, amount: u64) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(amount > 0, ErrorCode::InvalidClawbackAmount);
+
+        let employee = &mut ctx.accounts.employee;
+        employee.clawback_balance = employee
+            .clawback_balance
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit_verbose!(ctx.accounts.payroll, ClawbackAdded {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            amount,
+            clawback_balance: employee.clawback_balance,
+        });
+
+        Ok(())
+    }
+
+    /// Lets an employee voluntarily return pay into the vault, signed by the owner of
+    /// their wallet (or the wallet itself on native payrolls). Refunds settle any
+    /// outstanding clawback first. Accepted while frozen or paused, since they only add funds.
+    pub fn refund_payment(ctx: Context<RefundPayment>, amount: u64) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(amount > 0, ErrorCode::InvalidRefundAmount);
+        let payroll = &mut ctx.accounts.payroll;
+
+        let received = if payroll.native {
+            require_keys_eq!(
+                ctx.accounts.owner.key(),
+                ctx.accounts.employee.wallet,
+                ErrorCode::Unauthorized
+            );
+            let native_vault = ctx
+                .accounts
+                .native_vault
+                .as_ref()
+                .ok_or(ErrorCode::VaultMismatch)?;
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.owner.to_account_info(),
+                        to: native_vault.to_account_info(),
+                    },
+                ),
+                amount,
+            )?;
+            amount
+        } else {
+            let payroll_vault = ctx
+                .accounts
+                .payroll_vault
+                .as_mut()
+                .ok_or(ErrorCode::VaultMismatch)?;
+            let employee_wallet = ctx
+                .accounts
+                .employee_wallet
+                .as_ref()
+                .ok_or(ErrorCode::VaultMismatch)?;
+            require_keys_eq!(
+                employee_wallet.owner,
+                ctx.accounts.owner.key(),
+                ErrorCode::Unauthorized
+            );
+            require!(employee_wallet.amount >= amount, ErrorCode::InsufficientFunds);
+
+            let balance_before = payroll_vault.amount;
+            token_interface::transfer_checked(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: employee_wallet.to_account_info(),
+                        mint: ctx.accounts.payment_token.to_account_info(),
+                        to: payroll_vault.to_account_info(),
+                        authority: ctx.accounts.owner.to_account_info(),
+                    },
+                ),
+                amount,
+                ctx.accounts.payment_token.decimals,
+            )?;
+
+            // Same transfer-fee accounting as deposit_funds
+            payroll_vault.reload()?;
+            payroll_vault
+                .amount
+                .checked_sub(balance_before)
+                .ok_or(ErrorCode::MathOverflow)?
+        };
+
+        payroll.total_funds = payroll
+            .total_funds
+            .checked_add(received)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let employee = &mut ctx.accounts.employee;
+        employee.clawback_balance = employee.clawback_balance.saturating_sub(received);
+
+        emit!(PaymentRefunded {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            amount,
+            received,
+            clawback_balance: employee.clawback_balance,
+        });
+
+        Ok(())
+    }
+
+    /// Caps what process_payment and pay_reimbursement together may send the employee
+    /// per pay period (scaled by periods when catching up); 0 disables the cap.
+    pub fn set_period_pay_cap(ctx: Context<UpdateEmployee>, period_pay_cap: u64) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let employee = &mut ctx.accounts.employee;
+        employee.period_pay_cap = period_pay_cap;
+
+        emit_verbose!(ctx.accounts.payroll, PeriodPayCapUpdated {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            period_pay_cap,
+        });
+
+        Ok(())
+    }
+
+    /// Brings back an employee suspended by record_payment_attempt and clears the streak.
+    pub fn reactivate_employee(ctx: Context<UpdateEmployee>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let employee = &mut ctx.accounts.employee;
+        require!(!employee.is_active, ErrorCode::EmployeeAlreadyActive);
+        employee.is_active = true;
+        employee.consecutive_funding_failures = 0;
+
+        emit_verbose!(ctx.accounts.payroll, EmployeeReactivated {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+        });
+
+        Ok(())
+    }
+
+    pub fn skip_next_payment(ctx: Context<UpdateEmployee>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let employee = &mut ctx.accounts.employee;
+        employee.skip_next = true;
+
+        emit_verbose!(ctx.accounts.payroll, NextPaymentSkipScheduled {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+        });
+
+        Ok(())
+    }
+
+    pub fn update_employee_metadata(
+        ctx: Context<UpdateEmployee>,
+        department: String,
+        cost_center: String,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        validate_employee_metadata(&department, &cost_center)?;
+
+        let employee = &mut ctx.accounts.employee;
+        employee.department = department;
+        employee.cost_center = cost_center;
+
+        emit_verbose!(ctx.accounts.payroll, EmployeeMetadataUpdated {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            department: employee.department.clone(),
+            cost_center: employee.cost_center.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Grows an Employee account created before the latest fields were appended. New
+    /// trailing bytes are zeroed, which every appended field decodes as its default.
+    pub fn migrate_employee(ctx: Context<MigrateEmployee>, _employee_id: String) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let info = ctx.accounts.employee.to_account_info();
+        require_keys_eq!(*info.owner, crate::ID, ErrorCode::InvalidEmployeeAccount);
+        {
+            let data = info.try_borrow_data()?;
+            require!(
+                data.len() >= 8 && data[..8] == *Employee::DISCRIMINATOR,
+                ErrorCode::InvalidEmployeeAccount
+            );
+        }
+
+        let old_size = info.data_len();
+        let new_size = 8 + Employee::INIT_SPACE;
+        if old_size < new_size {
+            let top_up = Rent::get()?
+                .minimum_balance(new_size)
+                .saturating_sub(info.lamports());
+            if top_up > 0 {
+                system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        system_program::Transfer {
+                            from: ctx.accounts.authority.to_account_info(),
+                            to: info.clone(),
+                        },
+                    ),
+                    top_up,
+                )?;
+            }
+            info.resize(new_size)?;
+        }
+
+        // Fails if the account still doesn't decode with the current layout
+        let employee = Employee::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        require_keys_eq!(
+            employee.payroll,
+            ctx.accounts.payroll.key(),
+            ErrorCode::InvalidEmployeeAccount
+        );
+
+        emit_verbose!(ctx.accounts.payroll, EmployeeMigrated {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id,
+            old_size: old_size as u64,
+            new_size: info.data_len() as u64,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_payment_interval_override(
+        ctx: Context<UpdateEmployee>,
+        payment_interval_override: Option<i64>,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        if let Some(interval) = payment_interval_override {
+            require!(interval > 0, ErrorCode::InvalidPaymentInterval);
+        }
+
+        let employee = &mut ctx.accounts.employee;
+        employee.payment_interval_override = payment_interval_override;
+
+        emit_verbose!(ctx.accounts.payroll, PaymentIntervalOverrideUpdated {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            payment_interval_override,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_payment_frequency(
+        ctx: Context<UpdateEmployee>,
+        new_frequency: PaymentFrequency,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let employee = &mut ctx.accounts.employee;
+        let old_frequency = employee.payment_frequency;
+
+        // last_payment is kept, so the next payment is due one new interval after the last one
+        employee.payment_frequency = new_frequency;
+
+        emit_verbose!(ctx.accounts.payroll, PaymentFrequencyUpdated {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            old: old_frequency,
+            new: new_frequency,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_salary_denomination(ctx: Context<UpdateEmployee>, salary_is_usd: bool) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        if salary_is_usd {
+            require!(ctx.accounts.payroll.oracle.is_some(), ErrorCode::OracleNotConfigured);
+        }
+
+        let employee = &mut ctx.accounts.employee;
+        employee.salary_is_usd = salary_is_usd;
+
+        emit_verbose!(ctx.accounts.payroll, SalaryDenominationUpdated {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            salary_is_usd,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_employee_wallet(ctx: Context<UpdateEmployeeWallet>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &ctx.accounts.payroll;
+        let employee = &mut ctx.accounts.employee;
+        let new_wallet = ctx.accounts.new_wallet.key();
+        let current_time = Clock::get()?.unix_timestamp;
+
+        // A delay gives the other party time to react if either key was stolen
+        let effective_ts = current_time
+            .checked_add(payroll.wallet_change_delay)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if payroll.wallet_change_delay == 0 {
+            employee.wallet = new_wallet;
+            employee.pending_wallet = None;
+            employee.pending_wallet_effective = 0;
+        } else {
+            employee.pending_wallet = Some(new_wallet);
+            employee.pending_wallet_effective = effective_ts;
+        }
+
+        emit!(EmployeeWalletUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            old_wallet: ctx.accounts.current_wallet.key(),
+            new_wallet,
+            effective_ts,
+            updated_by: ctx.accounts.signer.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn approve_payment(ctx: Context<ApprovePayment>, amount: u64) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let approval = &mut ctx.accounts.approval;
+        approval.payroll = ctx.accounts.payroll.key();
+        approval.employee = ctx.accounts.employee.key();
+        approval.approver = ctx.accounts.approver.key();
+        approval.sequence = ctx.accounts.employee.payment_count;
+        approval.amount = amount;
+
+        emit!(PaymentApproved {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: ctx.accounts.employee.employee_id.clone(),
+            approver: approval.approver,
+            payment_sequence: approval.sequence,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_max_catchup_periods(
+        ctx: Context<UpdatePayrollConfig>,
+        max_catchup_periods: u8,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(max_catchup_periods > 0, ErrorCode::InvalidCatchupPeriods);
+
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.max_catchup_periods = max_catchup_periods;
+
+        emit_verbose!(payroll, CatchupPeriodsUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            max_catchup_periods,
+        });
+
+        Ok(())
+    }
+
+    pub fn disburse(ctx: Context<Disburse>, amount: u64) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+
+        require!(!payroll.frozen, ErrorCode::PayrollFrozen);
+        require!(payroll.is_active, ErrorCode::PayrollInactive);
+        // An immediate disbursement would bypass the time-lock
+        let now = Clock::get()?.unix_timestamp;
+        payroll.apply_pending_withdrawal_delay(now);
+        require!(payroll.withdrawal_delay_seconds == 0, ErrorCode::WithdrawalTimeLocked);
+        // Nor may it skip the withdrawal signers
+        require!(payroll.withdrawal_threshold == 0, ErrorCode::WithdrawalApprovalsNotMet);
+        require!(payroll.total_funds >= amount, ErrorCode::InsufficientFunds);
+        payroll.record_outflow(amount, now)?;
+
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.payroll_vault,
+            &ctx.accounts.payment_token,
+            ctx.accounts.recipient.to_account_info(),
+            payroll.key(),
+            payroll.vault_bump,
+            amount,
+        )?;
+
+        payroll.total_funds = payroll
+            .total_funds
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(Disbursed {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            recipient: ctx.accounts.recipient.key(),
+            amount,
+        });
+
+        payroll.warn_if_below_low_water();
+
+        Ok(())
+    }
+
+    /// Raising the delay applies immediately; shortening it only takes effect once the
+    /// current delay has elapsed, so a compromised authority cannot drop the lock and withdraw.
+    pub fn set_withdrawal_delay(
+        ctx: Context<UpdatePayrollConfig>,
+        withdrawal_delay_seconds: i64,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(withdrawal_delay_seconds >= 0, ErrorCode::InvalidWithdrawalDelay);
+
+        let now = Clock::get()?.unix_timestamp;
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.apply_pending_withdrawal_delay(now);
+
+        let effective_ts = if withdrawal_delay_seconds >= payroll.withdrawal_delay_seconds {
+            payroll.withdrawal_delay_seconds = withdrawal_delay_seconds;
+            payroll.pending_withdrawal_delay = None;
+            payroll.pending_withdrawal_delay_effective = 0;
+            now
+        } else {
+            let effective_ts = now
+                .checked_add(payroll.withdrawal_delay_seconds)
+                .ok_or(ErrorCode::MathOverflow)?;
+            payroll.pending_withdrawal_delay = Some(withdrawal_delay_seconds);
+            payroll.pending_withdrawal_delay_effective = effective_ts;
+            effective_ts
+        };
+
+        emit_verbose!(payroll, WithdrawalDelayUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            withdrawal_delay_seconds,
+            effective_ts,
+        });
+
+        Ok(())
+    }
+
+    /// M-of-N approval for execute_withdrawal (and no immediate `disburse`); an empty set
+    /// with a zero threshold turns it off. Fixed while a withdrawal is pending, so the
+    /// rules can't change under approvals already given.
+    pub fn set_withdrawal_signers(
+        ctx: Context<UpdatePayrollConfig>,
+        withdrawal_signers: Vec<Pubkey>,
+        withdrawal_threshold: u8,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        require!(payroll.pending_withdrawal.is_none(), ErrorCode::WithdrawalPending);
+        require!(
+            withdrawal_signers.len() <= MAX_WITHDRAWAL_SIGNERS,
+            ErrorCode::InvalidWithdrawalSigners
+        );
+        for (i, signer) in withdrawal_signers.iter().enumerate() {
+            require!(
+                !withdrawal_signers[..i].contains(signer),
+                ErrorCode::InvalidWithdrawalSigners
+            );
+        }
+        require!(
+            (withdrawal_signers.is_empty() && withdrawal_threshold == 0)
+                || (withdrawal_threshold >= 1
+                    && withdrawal_threshold as usize <= withdrawal_signers.len()),
+            ErrorCode::InvalidWithdrawalSigners
+        );
+        payroll.withdrawal_signers = withdrawal_signers;
+        payroll.withdrawal_threshold = withdrawal_threshold;
+
+        emit!(WithdrawalSignersUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            withdrawal_signers: payroll.withdrawal_signers.clone(),
+            withdrawal_threshold,
+        });
+
+        Ok(())
+    }
+
+    pub fn request_withdrawal(ctx: Context<RequestWithdrawal>, amount: u64) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let now = Clock::get()?.unix_timestamp;
+        let payroll = &mut ctx.accounts.payroll;
+
+        require!(!payroll.frozen, ErrorCode::PayrollFrozen);
+        require!(payroll.pending_withdrawal.is_none(), ErrorCode::WithdrawalPending);
+        require!(amount > 0, ErrorCode::InvalidWithdrawalAmount);
+        require!(payroll.total_funds >= amount, ErrorCode::InsufficientFunds);
+
+        payroll.apply_pending_withdrawal_delay(now);
+        let unlock_ts = now
+            .checked_add(payroll.withdrawal_delay_seconds)
+            .ok_or(ErrorCode::MathOverflow)?;
+        payroll.pending_withdrawal = Some(amount);
+        payroll.withdrawal_destination = ctx.accounts.destination.key();
+        payroll.withdrawal_unlock_ts = unlock_ts;
+        payroll.withdrawal_request_sequence = sequence;
+
+        emit!(WithdrawalRequested {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            amount,
+            destination: payroll.withdrawal_destination,
+            unlock_ts,
+        });
+
+        Ok(())
+    }
+
+    /// Approves the pending withdrawal as one of `withdrawal_signers`. Approvals left over
+    /// from an earlier, cancelled request are discarded on the first approval of a new one.
+    pub fn approve_withdrawal(ctx: Context<ApproveWithdrawal>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &ctx.accounts.payroll;
+        let amount = payroll
+            .pending_withdrawal
+            .ok_or(ErrorCode::NoPendingWithdrawal)?;
+
+        let approvals = &mut ctx.accounts.withdrawal_approvals;
+        if approvals.payroll != payroll.key()
+            || approvals.request_sequence != payroll.withdrawal_request_sequence
+        {
+            approvals.payroll = payroll.key();
+            approvals.request_sequence = payroll.withdrawal_request_sequence;
+            approvals.approvals.clear();
+        }
+        let signer = ctx.accounts.signer.key();
+        require!(
+            !approvals.approvals.contains(&signer),
+            ErrorCode::WithdrawalAlreadyApproved
+        );
+        approvals.approvals.push(signer);
+
+        emit!(WithdrawalApproved {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            signer,
+            amount,
+            approvals: approvals.approvals.len() as u8,
+            withdrawal_threshold: payroll.withdrawal_threshold,
+        });
+
+        Ok(())
+    }
+
+    pub fn execute_withdrawal(ctx: Context<ExecuteWithdrawal>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let now = Clock::get()?.unix_timestamp;
+        let payroll = &mut ctx.accounts.payroll;
+
+        let amount = payroll
+            .pending_withdrawal
+            .ok_or(ErrorCode::NoPendingWithdrawal)?;
+        require!(!payroll.frozen, ErrorCode::PayrollFrozen);
+        require!(now >= payroll.withdrawal_unlock_ts, ErrorCode::WithdrawalLocked);
+        require!(payroll.total_funds >= amount, ErrorCode::InsufficientFunds);
+        // Approvals are consumed here, so they can't be replayed on a later request
+        let approvers = if payroll.withdrawal_threshold > 0 {
+            let approvals = ctx
+                .accounts
+                .withdrawal_approvals
+                .as_mut()
+                .ok_or(ErrorCode::WithdrawalApprovalsNotMet)?;
+            require!(
+                approvals.request_sequence == payroll.withdrawal_request_sequence
+                    && approvals.approvals.len() >= payroll.withdrawal_threshold as usize,
+                ErrorCode::WithdrawalApprovalsNotMet
+            );
+            std::mem::take(&mut approvals.approvals)
+        } else {
+            Vec::new()
+        };
+        payroll.record_outflow(amount, now)?;
+
+        if payroll.native {
+            let native_vault = ctx
+                .accounts
+                .native_vault
+                .as_ref()
+                .ok_or(ErrorCode::VaultMismatch)?;
+            transfer_native_from_vault(
+                &ctx.accounts.system_program,
+                native_vault,
+                ctx.accounts.destination.to_account_info(),
+                payroll.key(),
+                payroll.vault_bump,
+                amount,
+            )?;
+        } else {
+            let payroll_vault = ctx
+                .accounts
+                .payroll_vault
+                .as_ref()
+                .ok_or(ErrorCode::VaultMismatch)?;
+            transfer_from_vault(
+                &ctx.accounts.token_program,
+                payroll_vault,
+                &ctx.accounts.payment_token,
+                ctx.accounts.destination.to_account_info(),
+                payroll.key(),
+                payroll.vault_bump,
+                amount,
+            )?;
+        }
+
+        payroll.total_funds = payroll
+            .total_funds
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        payroll.pending_withdrawal = None;
+        payroll.withdrawal_unlock_ts = 0;
+
+        emit!(WithdrawalExecuted {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            amount,
+            destination: ctx.accounts.destination.key(),
+            approvers,
+        });
+
+        payroll.warn_if_below_low_water();
+
+        Ok(())
+    }
+
+    /// The guardian may cancel as well as the authority, so a suspicious request can be
+    /// stopped without freezing the whole payroll.
+    pub fn cancel_withdrawal(ctx: Context<CancelWithdrawal>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        let amount = payroll
+            .pending_withdrawal
+            .take()
+            .ok_or(ErrorCode::NoPendingWithdrawal)?;
+        payroll.withdrawal_unlock_ts = 0;
+
+        emit!(WithdrawalCancelled {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            amount,
+            cancelled_by: ctx.accounts.signer.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn create_vesting(
+        ctx: Context<CreateVesting>,
+        total_amount: u64,
+        start_ts: i64,
+        cliff_ts: i64,
+        duration: i64,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let end_ts = start_ts
+            .checked_add(duration)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(
+            total_amount > 0 && duration > 0 && cliff_ts >= start_ts && cliff_ts <= end_ts,
+            ErrorCode::InvalidVestingSchedule
+        );
+
+        let vesting = &mut ctx.accounts.vesting;
+        vesting.payroll = ctx.accounts.payroll.key();
+        vesting.employee = ctx.accounts.employee.key();
+        vesting.total_amount = total_amount;
+        vesting.start_ts = start_ts;
+        vesting.cliff_ts = cliff_ts;
+        vesting.duration = duration;
+        vesting.claimed = 0;
+
+        emit_verbose!(ctx.accounts.payroll, VestingCreated {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            employee_id: ctx.accounts.employee.employee_id.clone(),
+            total_amount,
+            start_ts,
+            cliff_ts,
+            duration,
+        });
+
+        Ok(())
+    }
+
+    pub fn claim_vested(ctx: Context<ClaimVested>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        let employee = &mut ctx.accounts.employee;
+        let vesting = &mut ctx.accounts.vesting;
+
+        require!(!payroll.frozen, ErrorCode::PayrollFrozen);
+        require!(payroll.is_active, ErrorCode::PayrollInactive);
+
+        let current_time = Clock::get()?.unix_timestamp;
+        employee.apply_pending_wallet(current_time);
+        require_keys_eq!(
+            ctx.accounts.employee_wallet.key(),
+            employee.wallet,
+            ErrorCode::EmployeeWalletMismatch
+        );
+
+        let amount = vesting
+            .vested_amount(current_time)?
+            .checked_sub(vesting.claimed)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(amount > 0, ErrorCode::NothingToClaim);
+        require!(payroll.total_funds >= amount, ErrorCode::InsufficientFunds);
+        payroll.record_outflow(amount, current_time)?;
+
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.payroll_vault,
+            &ctx.accounts.payment_token,
+            ctx.accounts.employee_wallet.to_account_info(),
+            payroll.key(),
+            payroll.vault_bump,
+            amount,
+        )?;
+
+        payroll.total_funds = payroll
+            .total_funds
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        vesting.claimed = vesting
+            .claimed
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(VestedClaimed {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            amount,
+            claimed: vesting.claimed,
+            total_amount: vesting.total_amount,
+        });
+
+        payroll.warn_if_below_low_water();
+
+        Ok(())
+    }
+
+    /// Non-taxable expense reimbursement; skips the tax/deduction math and leaves the
+    /// salary schedule (`last_payment`) untouched.
+    pub fn pay_reimbursement(ctx: Context<PayReimbursement>, amount: u64) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        let employee = &mut ctx.accounts.employee;
+
+        require!(!payroll.frozen, ErrorCode::PayrollFrozen);
+        require!(payroll.is_active, ErrorCode::PayrollInactive);
+        require!(amount > 0, ErrorCode::InvalidReimbursementAmount);
+
+        let current_time = Clock::get()?.unix_timestamp;
+        employee.apply_pending_wallet(current_time);
+        require_keys_eq!(
+            ctx.accounts.employee_wallet.key(),
+            employee.wallet,
+            ErrorCode::EmployeeWalletMismatch
+        );
+
+        require!(payroll.total_funds >= amount, ErrorCode::InsufficientFunds);
+        payroll.record_outflow(amount, current_time)?;
+
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.payroll_vault,
+            &ctx.accounts.payment_token,
+            ctx.accounts.employee_wallet.to_account_info(),
+            payroll.key(),
+            payroll.vault_bump,
+            amount,
+        )?;
+
+        payroll.total_funds = payroll
+            .total_funds
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        employee.total_reimbursed = employee
+            .total_reimbursed
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        employee.record_period_pay(amount, 1)?;
+
+        emit!(ReimbursementPaid {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            amount,
+            total_reimbursed: employee.total_reimbursed,
+        });
+
+        payroll.warn_if_below_low_water();
+
+        Ok(())
+    }
+
+    pub fn close_period(ctx: Context<UpdatePayrollConfig>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        let period = payroll.current_period;
+        let total_paid_in_period = payroll.total_paid_in_period;
+
+        payroll.current_period = period.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        payroll.total_paid_in_period = 0;
+
+        emit_verbose!(payroll, PeriodClosed {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            period,
+            total_paid_in_period,
+        });
+
+        Ok(())
+    }
+
+    /// Applies `new_rate` to every payroll passed (writable) in `remaining_accounts`.
+    /// Any payroll not owned by the signer fails the whole transaction, so the batch is
+    /// all-or-nothing. Only the plaintext rate changes; confidential payrolls still need
+    /// `set_encrypted_tax_rate` for the MPC path.
+    pub fn update_tax_rate_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UpdateTaxRateBatch<'info>>,
+        new_rate: u16,
+    ) -> Result<()> {
+        require!(new_rate <= 10000, ErrorCode::InvalidTaxRate);
+        require!(
+            !ctx.remaining_accounts.is_empty()
+                && ctx.remaining_accounts.len() <= MAX_PAYROLLS_PER_TAX_BATCH,
+            ErrorCode::BatchTooLarge
+        );
+        check_batch_size(ctx.remaining_accounts.len(), COMPUTE_UNITS_PER_PAYROLL_UPDATE)?;
+
+        let authority = ctx.accounts.authority.key();
+        let mut seen: Vec<Pubkey> = Vec::with_capacity(ctx.remaining_accounts.len());
+
+        for payroll_info in ctx.remaining_accounts.iter() {
+            require!(
+                !seen.contains(payroll_info.key) && payroll_info.is_writable,
+                ErrorCode::InvalidPayrollAccount
+            );
+            seen.push(*payroll_info.key);
+
+            let mut payroll = Account::<Payroll>::try_from(payroll_info)?;
+            require_keys_eq!(payroll.authority, authority, ErrorCode::Unauthorized);
+            let sequence = payroll.next_sequence()?;
+            let old_rate = payroll.tax_rate;
+            payroll.tax_rate = new_rate;
+            payroll.exit(ctx.program_id)?;
+
+            emit_verbose!(payroll, TaxRateUpdated {
+                payroll_id: payroll.payroll_id.clone(),
+                sequence,
+                old_rate,
+                new_rate,
+            });
+        }
+
+        emit!(TaxRateBatchUpdated {
+            count: seen.len() as u64,
+            new_rate,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_encrypted_tax_rate(
+        ctx: Context<UpdatePayrollConfig>,
+        encrypted_tax_rate: [u8; 32],
+        pub_key: [u8; 32],
+        nonce: u128,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.encrypted_tax_rate = encrypted_tax_rate;
+        payroll.tax_rate_pubkey = pub_key;
+        payroll.tax_rate_nonce = nonce;
+
+        emit_verbose!(payroll, EncryptedTaxRateUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            encrypted_tax_rate,
+            nonce: nonce.to_le_bytes(),
+        });
+
+        Ok(())
+    }
+
+    pub fn set_outflow_limit(
+        ctx: Context<UpdatePayrollConfig>,
+        max_outflow_per_window: u64,
+        window_seconds: i64,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(
+            max_outflow_per_window == 0 || window_seconds > 0,
+            ErrorCode::InvalidOutflowWindow
+        );
+
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.max_outflow_per_window = max_outflow_per_window;
+        payroll.window_seconds = window_seconds;
+        payroll.window_start = Clock::get()?.unix_timestamp;
+        payroll.window_outflow = 0;
+
+        emit_verbose!(payroll, OutflowLimitUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            max_outflow_per_window,
+            window_seconds,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_oracle(
+        ctx: Context<UpdatePayrollConfig>,
+        oracle: Option<Pubkey>,
+        feed_id: [u8; 32],
+        max_price_age: u64,
+        max_conf_bps: u16,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(max_conf_bps <= 10000, ErrorCode::InvalidOracleConfig);
+        if oracle.is_some() {
+            require!(max_price_age > 0, ErrorCode::InvalidOracleConfig);
+        }
+
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.oracle = oracle;
+        payroll.oracle_feed_id = feed_id;
+        payroll.max_price_age = max_price_age;
+        payroll.max_conf_bps = max_conf_bps;
+
+        emit_verbose!(payroll, OracleUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            oracle,
+            feed_id,
+            max_price_age,
+            max_conf_bps,
+        });
+
+        Ok(())
+    }
+
+    /// `EVENT_LEVEL_ESSENTIAL` or `EVENT_LEVEL_ALL`; see those for which events each emits.
+    pub fn set_event_level(ctx: Context<UpdatePayrollConfig>, event_level: u8) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(event_level <= EVENT_LEVEL_ALL, ErrorCode::InvalidEventLevel);
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.event_level = event_level;
+
+        emit!(EventLevelUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            event_level,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_pay_stubs_enabled(ctx: Context<UpdatePayrollConfig>, enabled: bool) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.pay_stubs_enabled = enabled;
+
+        emit_verbose!(payroll, PayStubsToggled {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            enabled,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_rounding_mode(
+        ctx: Context<UpdatePayrollConfig>,
+        rounding_mode: RoundingMode,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.rounding_mode = rounding_mode;
+
+        emit_verbose!(payroll, RoundingModeUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            rounding_mode,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_wallet_change_delay(
+        ctx: Context<UpdatePayrollConfig>,
+        wallet_change_delay: i64,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(wallet_change_delay >= 0, ErrorCode::InvalidWalletChangeDelay);
+
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.wallet_change_delay = wallet_change_delay;
+
+        emit_verbose!(payroll, WalletChangeDelayUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            wallet_change_delay,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_approval_policy(
+        ctx: Context<UpdatePayrollConfig>,
+        approver: Option<Pubkey>,
+        approval_threshold: u64,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        // Dual control only means something if the approver is a different key
+        require!(
+            approver != Some(payroll.authority),
+            ErrorCode::InvalidApprovalPolicy
+        );
+        require!(
+            approval_threshold == 0 || approver.is_some(),
+            ErrorCode::InvalidApprovalPolicy
+        );
+
+        payroll.approver = approver;
+        payroll.approval_threshold = approval_threshold;
+
+        emit!(ApprovalPolicyUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            approver,
+            approval_threshold,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_clawback_policy(
+        ctx: Context<UpdatePayrollConfig>,
+        clawback_max_per_period: u64,
+        min_net_pay: u64,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.clawback_max_per_period = clawback_max_per_period;
+        payroll.min_net_pay = min_net_pay;
+
+        emit_verbose!(payroll, ClawbackPolicyUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            clawback_max_per_period,
+            min_net_pay,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_salary_bounds(
+        ctx: Context<UpdatePayrollConfig>,
+        min_salary: u64,
+        max_salary: u64,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(
+            max_salary == 0 || min_salary <= max_salary,
+            ErrorCode::InvalidSalaryBounds
+        );
+        require!(
+            max_salary == 0 || ctx.accounts.payroll.min_payment <= max_salary,
+            ErrorCode::InvalidMinPayment
+        );
+
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.min_salary = min_salary;
+        payroll.max_salary = max_salary;
+
+        emit_verbose!(payroll, SalaryBoundsUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            min_salary,
+            max_salary,
+        });
+
+        Ok(())
+    }
+
+    /// Probation before an employee's first payment, counted from onboarding; 0 disables it.
+    pub fn set_min_tenure(ctx: Context<UpdatePayrollConfig>, min_tenure_seconds: i64) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(min_tenure_seconds >= 0, ErrorCode::InvalidMinTenure);
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.min_tenure_seconds = min_tenure_seconds;
+
+        emit_verbose!(payroll, MinTenureUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            min_tenure_seconds,
+        });
+
+        Ok(())
+    }
+
+    /// A minimum above the salary ceiling could never be reached in one period.
+    pub fn set_min_payment(ctx: Context<UpdatePayrollConfig>, min_payment: u64) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        require!(
+            payroll.max_salary == 0 || min_payment <= payroll.max_salary,
+            ErrorCode::InvalidMinPayment
+        );
+
+        payroll.min_payment = min_payment;
+
+        emit_verbose!(payroll, MinPaymentUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            min_payment,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_employer_tax(
+        ctx: Context<UpdatePayrollConfig>,
+        employer_tax_bps: u16,
+        tax_account: Option<Pubkey>,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(
+            employer_tax_bps <= 10000 && (employer_tax_bps == 0 || tax_account.is_some()),
+            ErrorCode::InvalidEmployerTaxConfig
+        );
+
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.employer_tax_bps = employer_tax_bps;
+        payroll.tax_account = tax_account;
+
+        emit_verbose!(payroll, EmployerTaxUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            employer_tax_bps,
+            tax_account,
+        });
+
+        Ok(())
+    }
+
+    /// When set, process_payment only runs for employees with an unconsumed
+    /// calculate_net_pay result that has passed validate_net_pay, so every payment is
+    /// preceded by the confidential path.
+    pub fn set_require_confidential(
+        ctx: Context<UpdatePayrollConfig>,
+        require_confidential: bool,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.require_confidential = require_confidential;
+
+        emit_verbose!(payroll, ConfidentialRequirementUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            require_confidential,
+        });
+
+        Ok(())
+    }
+
+    /// Pays net pay in whole tokens of the payment mint, carrying each employee's
+    /// fractional remainder into their next payment.
+    pub fn set_round_net_to_whole(
+        ctx: Context<UpdatePayrollConfig>,
+        round_net_to_whole: bool,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.round_net_to_whole = round_net_to_whole;
+
+        emit_verbose!(payroll, NetRoundingUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            round_net_to_whole,
+            payment_decimals: payroll.payment_decimals,
+        });
+
+        Ok(())
+    }
+
+    /// Review window between a confidential net pay calculation and the payment it feeds.
+    /// Employees without a calculation are unaffected.
+    pub fn set_settlement_delay(
+        ctx: Context<UpdatePayrollConfig>,
+        settlement_delay_seconds: i64,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(settlement_delay_seconds >= 0, ErrorCode::InvalidSettlementDelay);
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.settlement_delay_seconds = settlement_delay_seconds;
+
+        emit_verbose!(payroll, SettlementDelayUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            settlement_delay_seconds,
+        });
+
+        Ok(())
+    }
+
+    /// Missed due dates in a row, recorded as underfunded, before an employee is
+    /// suspended. 0 disables auto-suspension.
+    pub fn set_funding_failure_threshold(
+        ctx: Context<UpdatePayrollConfig>,
+        funding_failure_threshold: u32,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.funding_failure_threshold = funding_failure_threshold;
+
+        emit_verbose!(payroll, FundingFailureThresholdUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            funding_failure_threshold,
+        });
+
+        Ok(())
+    }
+
+    /// Per-period gross pay, in payment token units, exempt from withholding. It may not
+    /// exceed max_salary when one is set, since no employee could then be taxed.
+    pub fn set_tax_free_allowance(
+        ctx: Context<UpdatePayrollConfig>,
+        tax_free_allowance: u64,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        require!(
+            payroll.max_salary == 0 || tax_free_allowance <= payroll.max_salary,
+            ErrorCode::InvalidTaxFreeAllowance
+        );
+        payroll.tax_free_allowance = tax_free_allowance;
+
+        emit_verbose!(payroll, TaxFreeAllowanceUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            tax_free_allowance,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_pay_day_anchor(ctx: Context<UpdatePayrollConfig>, pay_day_anchor: i64) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(pay_day_anchor >= 0, ErrorCode::InvalidPayDayAnchor);
+
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.pay_day_anchor = pay_day_anchor;
+
+        emit_verbose!(payroll, PayDayAnchorUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            pay_day_anchor,
+        });
+
+        Ok(())
+    }
+
+    /// Lets process_payment run up to `max_business_day_shift` seconds before a due date
+    /// that falls on a weekend or holiday, on a business day the client computes.
+    pub fn set_business_day_shift(
+        ctx: Context<UpdatePayrollConfig>,
+        business_day_shift: bool,
+        max_business_day_shift: i64,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(
+            (0..=MAX_BUSINESS_DAY_SHIFT_SECONDS).contains(&max_business_day_shift)
+                && (business_day_shift || max_business_day_shift == 0),
+            ErrorCode::InvalidBusinessDayShift
+        );
+
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.business_day_shift = business_day_shift;
+        payroll.max_business_day_shift = max_business_day_shift;
+
+        emit_verbose!(payroll, BusinessDayShiftUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            business_day_shift,
+            max_business_day_shift,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_schedule_policy(
+        ctx: Context<UpdatePayrollConfig>,
+        strict_schedule: bool,
+        schedule_tolerance: i64,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(
+            schedule_tolerance >= 0 && (strict_schedule || schedule_tolerance == 0),
+            ErrorCode::InvalidScheduleTolerance
+        );
+
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.strict_schedule = strict_schedule;
+        payroll.schedule_tolerance = schedule_tolerance;
+
+        emit_verbose!(payroll, SchedulePolicyUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            strict_schedule,
+            schedule_tolerance,
+        });
+
+        Ok(())
+    }
+
+    /// Hands ownership of the SPL vault to an external custody key, or back to the vault
+    /// PDA with `None`. While set, process_payment needs that key as a co-signer.
+    ///
+    /// Security tradeoffs: the external key owns the vault outright, so it can move funds
+    /// without going through this program, bypassing outflow limits, withdrawal
+    /// time-locks, approvals and total_funds accounting. Its compromise drains the vault.
+    /// Every other vault outflow (disburse, withdrawals, vesting claims, reimbursements,
+    /// dust sweeps) still signs as the PDA and fails until ownership is returned.
+    pub fn set_vault_authority(
+        ctx: Context<SetVaultAuthority>,
+        vault_authority: Option<Pubkey>,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        require!(!payroll.native, ErrorCode::NativeUnsupported);
+
+        let vault = &ctx.accounts.payroll_vault;
+        require!(
+            vault_authority != Some(vault.key()),
+            ErrorCode::InvalidVaultAuthority
+        );
+
+        // The vault PDA signs for itself; an external owner must sign this transaction
+        let current_authority = match payroll.vault_authority {
+            None => vault.to_account_info(),
+            Some(current) => {
+                let signer = ctx
+                    .accounts
+                    .current_vault_authority
+                    .as_ref()
+                    .ok_or(ErrorCode::InvalidVaultAuthority)?;
+                require_keys_eq!(signer.key(), current, ErrorCode::InvalidVaultAuthority);
+                signer.to_account_info()
+            }
+        };
+        let payroll_key = payroll.key();
+        let seeds = &[b"payroll_vault", payroll_key.as_ref(), &[payroll.vault_bump]];
+        token_interface::set_authority(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::SetAuthority {
+                    current_authority,
+                    account_or_mint: vault.to_account_info(),
+                },
+                &[&seeds[..]],
+            ),
+            token_interface::spl_token_2022::instruction::AuthorityType::AccountOwner,
+            Some(vault_authority.unwrap_or(vault.key())),
+        )?;
+        payroll.vault_authority = vault_authority;
+
+        emit!(VaultAuthorityUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            vault_authority,
+        });
+
+        Ok(())
+    }
+
+    /// Recovery for a payroll created with the wrong mint: closes the empty vault and
+    /// recreates it at the same address for `new_mint`. Only allowed before the payroll
+    /// holds funds or employees. Tax and platform accounts belong to the old mint, so they
+    /// are cleared; amount settings such as salary bounds keep their raw values.
+    pub fn change_payment_token(ctx: Context<ChangePaymentToken>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        require!(!payroll.native, ErrorCode::NativeUnsupported);
+        require!(
+            payroll.total_funds == 0 && payroll.employee_count == 0,
+            ErrorCode::PayrollNotEmpty
+        );
+        // An external custodian owns the vault, so the PDA can't close it
+        require!(
+            payroll.vault_authority.is_none(),
+            ErrorCode::InvalidVaultAuthority
+        );
+        let old_mint = payroll.payment_token;
+        let new_mint = &ctx.accounts.new_mint;
+        require_keys_neq!(new_mint.key(), old_mint, ErrorCode::PaymentTokenUnchanged);
+
+        let payroll_key = payroll.key();
+        let seeds = &[b"payroll_vault", payroll_key.as_ref(), &[payroll.vault_bump]];
+        let signer = &[&seeds[..]];
+        let vault_info = ctx.accounts.payroll_vault.to_account_info();
+
+        // Fails while the vault still holds tokens, e.g. dust sent to it directly
+        token_interface::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token_interface::CloseAccount {
+                account: vault_info.clone(),
+                destination: ctx.accounts.authority.to_account_info(),
+                authority: vault_info.clone(),
+            },
+            signer,
+        ))?;
+
+        // Closing hands the address back to the system program, so it can be recreated.
+        // Token-2022 mints may require account extensions, which change the size.
+        let space = {
+            let mint_info = new_mint.to_account_info();
+            let mint_data = mint_info.try_borrow_data()?;
+            let mint_state = StateWithExtensions::<MintState>::unpack(&mint_data)?;
+            let required = ExtensionType::get_required_init_account_extensions(
+                &mint_state.get_extension_types()?,
+            );
+            ExtensionType::try_calculate_account_len::<TokenAccountState>(&required)?
+        };
+        system_program::create_account(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::CreateAccount {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: vault_info.clone(),
+                },
+                signer,
+            ),
+            Rent::get()?.minimum_balance(space),
+            space as u64,
+            &ctx.accounts.new_token_program.key(),
+        )?;
+        token_interface::initialize_account3(CpiContext::new(
+            ctx.accounts.new_token_program.to_account_info(),
+            token_interface::InitializeAccount3 {
+                account: vault_info.clone(),
+                mint: new_mint.to_account_info(),
+                authority: vault_info,
+            },
+        ))?;
+
+        payroll.payment_token = new_mint.key();
+        payroll.payment_decimals = new_mint.decimals;
+        payroll.tax_account = None;
+        payroll.platform_account = None;
+
+        emit!(PaymentTokenChanged {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            old_mint,
+            new_mint: payroll.payment_token,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_platform_fee(
+        ctx: Context<UpdatePayrollConfig>,
+        platform_fee_bps: u16,
+        platform_account: Option<Pubkey>,
+        platform_fee_from_employee: bool,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(
+            platform_fee_bps <= MAX_PLATFORM_FEE_BPS
+                && (platform_fee_bps == 0 || platform_account.is_some()),
+            ErrorCode::InvalidPlatformFeeConfig
+        );
+
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.platform_fee_bps = platform_fee_bps;
+        payroll.platform_account = platform_account;
+        payroll.platform_fee_from_employee = platform_fee_from_employee;
+
+        emit_verbose!(payroll, PlatformFeeUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            platform_fee_bps,
+            platform_account,
+            platform_fee_from_employee,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_funder(ctx: Context<UpdatePayrollConfig>, funder: Option<Pubkey>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.funder = funder;
+
+        emit!(FunderUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            funder,
+        });
+
+        Ok(())
+    }
+
+    /// Token account process_payment tops the vault up from when it can't cover a payment.
+    /// Its owner approves the payroll vault PDA as delegate for the amount it may pull.
+    pub fn set_funding_source(
+        ctx: Context<UpdatePayrollConfig>,
+        funding_source: Option<Pubkey>,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        require!(
+            !payroll.native || funding_source.is_none(),
+            ErrorCode::InvalidFundingSource
+        );
+        payroll.funding_source = funding_source;
+
+        emit!(FundingSourceUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            funding_source,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_operator(ctx: Context<UpdatePayrollConfig>, operator: Option<Pubkey>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.operator = operator;
+
+        emit!(OperatorUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            operator,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_low_balance_threshold(
+        ctx: Context<UpdatePayrollConfig>,
+        low_balance_threshold: u64,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.low_balance_threshold = low_balance_threshold;
+
+        emit_verbose!(payroll, LowBalanceThresholdUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            low_balance_threshold,
+        });
+
+        Ok(())
+    }
+
+    pub fn deposit_funds(ctx: Context<DepositFunds>, amount: u64) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+
+        require!(!payroll.frozen, ErrorCode::PayrollFrozen);
+        require!(payroll.is_active, ErrorCode::PayrollInactive);
+
+        let received = if payroll.native {
+            let native_vault = ctx
+                .accounts
+                .native_vault
+                .as_ref()
+                .ok_or(ErrorCode::VaultMismatch)?;
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: native_vault.to_account_info(),
+                    },
+                ),
+                amount,
+            )?;
+            amount
+        } else {
+            let payroll_vault = ctx
+                .accounts
+                .payroll_vault
+                .as_mut()
+                .ok_or(ErrorCode::VaultMismatch)?;
+            let authority_token_account = ctx
+                .accounts
+                .authority_token_account
+                .as_ref()
+                .ok_or(ErrorCode::VaultMismatch)?;
+
+            deposit_to_vault(
+                &ctx.accounts.token_program,
+                authority_token_account,
+                &ctx.accounts.payment_token,
+                payroll_vault,
+                &ctx.accounts.authority,
+                amount,
+            )?
+        };
+
+        payroll.total_funds = payroll
+            .total_funds
+            .checked_add(received)
+            .ok_or(ErrorCode::MathOverflow)?;
+        payroll.lifetime_deposited = payroll
+            .lifetime_deposited
+            .checked_add(received)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(FundsDeposited {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            amount,
+            received,
+            total_funds: payroll.total_funds,
+        });
+
+        payroll.warn_if_below_low_water();
+
+        Ok(())
+    }
+
+    /// Moves tokens stranded in a token account owned by an employee PDA (e.g. sent there
+    /// by mistake after offboarding) back into the vault.
+    pub fn sweep_dust(ctx: Context<SweepDust>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let amount = ctx.accounts.dust_account.amount;
+        require!(amount > 0, ErrorCode::NothingToSweep);
+
+        let payroll_key = ctx.accounts.payroll.key();
+        let employee_id = ctx.accounts.employee.employee_id.clone();
+        let balance_before = ctx.accounts.payroll_vault.amount;
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.dust_account.to_account_info(),
+                    mint: ctx.accounts.payment_token.to_account_info(),
+                    to: ctx.accounts.payroll_vault.to_account_info(),
+                    authority: ctx.accounts.employee.to_account_info(),
+                },
+                &[&[
+                    b"employee",
+                    payroll_key.as_ref(),
+                    employee_id.as_bytes(),
+                    &[ctx.bumps.employee],
+                ]],
+            ),
+            amount,
+            ctx.accounts.payment_token.decimals,
+        )?;
+
+        // Credit only what landed, as deposits do for transfer-fee mints
+        ctx.accounts.payroll_vault.reload()?;
+        let received = ctx
+            .accounts
+            .payroll_vault
+            .amount
+            .checked_sub(balance_before)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.total_funds = payroll
+            .total_funds
+            .checked_add(received)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(DustSwept {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            employee_id,
+            amount: received,
+        });
+
+        Ok(())
+    }
+
+    /// Guardians who can, `recovery_threshold` of them together, hand the payroll to a new
+    /// authority if the current key is lost. Unrelated to the freeze `guardian`. A recovery
+    /// only executes `recovery_delay_seconds` after it is proposed, and the authority can
+    /// cancel it until then, so a live key always wins. An empty list disables recovery.
+    pub fn set_recovery_guardians(
+        ctx: Context<UpdatePayrollConfig>,
+        guardians: Vec<Pubkey>,
+        recovery_threshold: u8,
+        recovery_delay_seconds: i64,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        // Changing the set mid-recovery would let approvals from removed guardians count
+        require!(!payroll.recovery_pending, ErrorCode::RecoveryPending);
+        require!(
+            guardians.len() <= MAX_RECOVERY_GUARDIANS,
+            ErrorCode::InvalidRecoveryConfig
+        );
+        for (i, guardian) in guardians.iter().enumerate() {
+            require!(
+                *guardian != payroll.authority && !guardians[..i].contains(guardian),
+                ErrorCode::InvalidRecoveryConfig
+            );
+        }
+        if guardians.is_empty() {
+            require!(recovery_threshold == 0, ErrorCode::InvalidRecoveryConfig);
+        } else {
+            require!(
+                recovery_threshold >= 1
+                    && recovery_threshold as usize <= guardians.len()
+                    && recovery_delay_seconds > 0,
+                ErrorCode::InvalidRecoveryConfig
+            );
+        }
+        payroll.guardians = guardians;
+        payroll.recovery_threshold = recovery_threshold;
+        payroll.recovery_delay_seconds = recovery_delay_seconds;
+
+        emit!(RecoveryGuardiansUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            guardians: payroll.guardians.clone(),
+            recovery_threshold,
+            recovery_delay_seconds,
+        });
+
+        Ok(())
+    }
+
+    /// Opens a recovery towards `new_authority`, counting the proposer's approval.
+    pub fn propose_recovery(ctx: Context<ProposeRecovery>, new_authority: Pubkey) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        // A guardian as authority would turn one recovery vote into full control
+        require!(
+            new_authority != payroll.authority && !payroll.guardians.contains(&new_authority),
+            ErrorCode::InvalidRecoveryAuthority
+        );
+        let now = Clock::get()?.unix_timestamp;
+        let executable_at = now
+            .checked_add(payroll.recovery_delay_seconds)
+            .ok_or(ErrorCode::MathOverflow)?;
+        payroll.recovery_pending = true;
+
+        let guardian = ctx.accounts.guardian.key();
+        ctx.accounts.recovery.set_inner(RecoveryProposal {
+            payroll: payroll.key(),
+            proposer: guardian,
+            new_authority,
+            approvals: vec![guardian],
+            executable_at,
+        });
+
+        emit!(RecoveryProposed {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            proposer: guardian,
+            new_authority,
+            executable_at,
+        });
+
+        Ok(())
+    }
+
+    pub fn approve_recovery(ctx: Context<ApproveRecovery>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let recovery = &mut ctx.accounts.recovery;
+        let guardian = ctx.accounts.guardian.key();
+        require!(
+            !recovery.approvals.contains(&guardian),
+            ErrorCode::RecoveryAlreadyApproved
+        );
+        recovery.approvals.push(guardian);
+
+        emit!(RecoveryApproved {
+            payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+            sequence,
+            guardian,
+            approvals: recovery.approvals.len() as u8,
+        });
+
+        Ok(())
+    }
+
+    /// Any guardian may execute once the threshold is met and the delay has passed.
+    pub fn execute_recovery(ctx: Context<ExecuteRecovery>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        let recovery = &ctx.accounts.recovery;
+        require!(
+            recovery.approvals.len() >= payroll.recovery_threshold as usize,
+            ErrorCode::RecoveryThresholdNotMet
+        );
+        require!(
+            Clock::get()?.unix_timestamp >= recovery.executable_at,
+            ErrorCode::RecoveryDelayActive
+        );
+        let old_authority = payroll.authority;
+        payroll.authority = recovery.new_authority;
+        payroll.recovery_pending = false;
+
+        emit!(RecoveryExecuted {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            old_authority,
+            new_authority: payroll.authority,
+        });
+
+        Ok(())
+    }
+
+    /// The authority's veto; the proposer may also withdraw their own proposal.
+    pub fn cancel_recovery(ctx: Context<CancelRecovery>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.recovery_pending = false;
+
+        emit!(RecoveryCancelled {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            cancelled_by: ctx.accounts.canceller.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn set_guardian(ctx: Context<UpdatePayrollConfig>, guardian: Option<Pubkey>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.guardian = guardian;
+
+        emit!(GuardianUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            guardian,
+        });
+
+        Ok(())
+    }
+
+    pub fn freeze_payroll(ctx: Context<FreezePayroll>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        require!(!payroll.frozen, ErrorCode::PayrollFrozen);
+
+        payroll.frozen = true;
+
+        emit!(PayrollFrozen {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            frozen_by: ctx.accounts.guardian.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn unfreeze_payroll(ctx: Context<UpdatePayrollConfig>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        require!(payroll.frozen, ErrorCode::PayrollNotFrozen);
+
+        payroll.frozen = false;
+
+        emit!(PayrollUnfrozen {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+        });
+
+        Ok(())
+    }
+
+    /// Minimum seconds between a pause and the following resume, and between a resume and
+    /// the next pause. Emergency stops that must not wait go through freeze_payroll.
+    pub fn set_pause_cooldown(
+        ctx: Context<UpdatePayrollConfig>,
+        pause_cooldown_seconds: i64,
+    ) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        require!(pause_cooldown_seconds >= 0, ErrorCode::InvalidPauseCooldown);
+
+        let payroll = &mut ctx.accounts.payroll;
+        payroll.pause_cooldown_seconds = pause_cooldown_seconds;
+
+        emit_verbose!(payroll, PauseCooldownUpdated {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            pause_cooldown_seconds,
+        });
+
+        Ok(())
+    }
+
+    pub fn pause_payroll(ctx: Context<PausePayroll>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        require!(payroll.is_active, ErrorCode::PayrollAlreadyPaused);
+
+        let now = Clock::get()?.unix_timestamp;
+        payroll.check_pause_cooldown(payroll.last_resume_ts, now)?;
+        payroll.is_active = false;
+        payroll.last_pause_ts = now;
+
+        emit!(PayrollPaused {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+        });
+
+        Ok(())
+    }
+
+    pub fn resume_payroll(ctx: Context<ResumePayroll>) -> Result<()> {
+        let sequence = ctx.accounts.payroll.next_sequence()?;
+        let payroll = &mut ctx.accounts.payroll;
+        require!(!payroll.is_active, ErrorCode::PayrollAlreadyActive);
+
+        let now = Clock::get()?.unix_timestamp;
+        payroll.check_pause_cooldown(payroll.last_pause_ts, now)?;
+        // Employees pick the paused time up lazily, at their next payment
+        let paused_seconds = if payroll.last_pause_ts > 0 {
+            now.saturating_sub(payroll.last_pause_ts)
+        } else {
+            0
+        };
+        payroll.total_paused_seconds = payroll
+            .total_paused_seconds
+            .checked_add(paused_seconds)
+            .ok_or(ErrorCode::MathOverflow)?;
+        payroll.is_active = true;
+        payroll.last_resume_ts = now;
+
+        emit!(PayrollResumed {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            paused_seconds,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct EstimateNetPayFee<'info> {
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CALCULATE_NET_PAY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Box<Account<'info, Payroll>>,
+}
+
+#[queue_computation_accounts("calculate_net_pay", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct CalculateNetPay<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// `init_if_needed` is safe here: this is a single program-wide PDA whose only state is
+    /// its bump, rewritten on every queue. Whoever creates it first only pays its rent, and
+    /// since only this program can sign for or write to it, an early creator cannot change
+    /// how it is used. Arcium checks the signer seeds, not who funded the account.
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CALCULATE_NET_PAY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        constraint = payroll.can_operate(&payer.key()) @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Box<Account<'info, Payroll>>,
+    #[account(
+        mut,
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Box<Account<'info, Employee>>,
+}
+
+#[callback_accounts("calculate_net_pay")]
+#[derive(Accounts)]
+pub struct CalculateNetPayCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CALCULATE_NET_PAY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub employee: Account<'info, Employee>,
+    #[account(mut, address = employee.payroll)]
+    pub payroll: Account<'info, Payroll>,
+}
+
+#[queue_computation_accounts("calculate_tax_withholding", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct CalculateTaxWithholding<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// See `CalculateNetPay::sign_pda_account` for why `init_if_needed` is safe.
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CALCULATE_TAX_WITHHOLDING)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        constraint = payroll.can_operate(&payer.key()) @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Box<Account<'info, Payroll>>,
+    #[account(
+        mut,
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Box<Account<'info, Employee>>,
+}
+
+#[callback_accounts("calculate_tax_withholding")]
+#[derive(Accounts)]
+pub struct CalculateTaxWithholdingCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CALCULATE_TAX_WITHHOLDING)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub employee: Account<'info, Employee>,
+    #[account(mut, address = employee.payroll)]
+    pub payroll: Account<'info, Payroll>,
+}
+
+#[queue_computation_accounts("accumulate_ytd_tax", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct AccumulateYtdTax<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// See `CalculateNetPay::sign_pda_account` for why `init_if_needed` is safe.
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_ACCUMULATE_YTD_TAX)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        constraint = payroll.can_operate(&payer.key()) @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Box<Account<'info, Payroll>>,
+    #[account(
+        mut,
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Box<Account<'info, Employee>>,
+}
+
+#[callback_accounts("accumulate_ytd_tax")]
+#[derive(Accounts)]
+pub struct AccumulateYtdTaxCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_ACCUMULATE_YTD_TAX)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub employee: Account<'info, Employee>,
+    #[account(mut, address = employee.payroll)]
+    pub payroll: Account<'info, Payroll>,
+}
+
+#[queue_computation_accounts("validate_net_pay", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ValidateNetPay<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// See `CalculateNetPay::sign_pda_account` for why `init_if_needed` is safe.
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_VALIDATE_NET_PAY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        constraint = payroll.can_operate(&payer.key()) @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Box<Account<'info, Payroll>>,
+    #[account(
+        mut,
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Box<Account<'info, Employee>>,
+}
+
+#[callback_accounts("validate_net_pay")]
+#[derive(Accounts)]
+pub struct ValidateNetPayCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_VALIDATE_NET_PAY)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub employee: Account<'info, Employee>,
+    #[account(mut, address = employee.payroll)]
+    pub payroll: Account<'info, Payroll>,
+}
+
+#[queue_computation_accounts("calculate_total_net_payroll", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct CalculateTotalNetPayroll<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// See `CalculateNetPay::sign_pda_account` for why `init_if_needed` is safe.
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account, checked by the arcium program.
+    pub mempool_account: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool, checked by the arcium program.
+    pub executing_pool: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account, checked by the arcium program.
+    pub computation_account: UncheckedAccount<'info>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CALCULATE_TOTAL_NET_PAYROLL)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(
+        mut,
+        address = derive_cluster_pda!(mxe_account)
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        constraint = payroll.can_operate(&payer.key()) @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Box<Account<'info, Payroll>>,
+}
+
+#[callback_accounts("calculate_total_net_payroll")]
+#[derive(Accounts)]
+pub struct CalculateTotalNetPayrollCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CALCULATE_TOTAL_NET_PAYROLL)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar, checked by the account constraint
+    pub instructions_sysvar: AccountInfo<'info>,
+    #[account(mut)]
+    pub payroll: Account<'info, Payroll>,
+}
+
+#[init_computation_definition_accounts("calculate_net_pay", payer)]
+#[derive(Accounts)]
+pub struct InitCalculateNetPayCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("calculate_tax_withholding", payer)]
+#[derive(Accounts)]
+pub struct InitCalculateTaxWithholdingCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("accumulate_ytd_tax", payer)]
+#[derive(Accounts)]
+pub struct InitAccumulateYtdTaxCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("validate_net_pay", payer)]
+#[derive(Accounts)]
+pub struct InitValidateNetPayCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("calculate_total_net_payroll", payer)]
+#[derive(Accounts)]
+pub struct InitCalculateTotalNetPayrollCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    #[account(mut)]
+    /// CHECK: comp_def_account, checked by arcium program.
+    /// Can't check it here as it's not initialized yet.
+    pub comp_def_account: UncheckedAccount<'info>,
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(payroll_id: String)]
+pub struct InitializeNativePayroll<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Payroll::INIT_SPACE,
+        seeds = [b"payroll", payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        mut,
+        seeds = [b"native_vault", payroll.key().as_ref()],
+        bump
+    )]
+    pub native_vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeProgramConfig<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ProgramConfig::INIT_SPACE,
+        seeds = [b"program_config"],
+        bump
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    /// Only the program's upgrade authority may create the singleton config
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key()) @ ErrorCode::Unauthorized
+    )]
+    pub program: Program<'info, crate::program::ConfidentialPayroll>,
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(payer.key()) @ ErrorCode::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetGlobalPause<'info> {
+    #[account(
+        mut,
+        seeds = [b"program_config"],
+        bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(payroll_id: String)]
+pub struct InitializePayroll<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Payroll::INIT_SPACE,
+        seeds = [b"payroll", payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"payroll_vault", payroll.key().as_ref()],
+        bump,
+        token::mint = payment_token,
+        token::authority = payroll_vault,
+        token::token_program = token_program,
+    )]
+    pub payroll_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub payment_token: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    /// Required when initial_deposit is non-zero
+    #[account(
+        mut,
+        constraint = authority_token_account.mint == payment_token.key() @ ErrorCode::MintMismatch
+    )]
+    pub authority_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+#[instruction(employee_id: String)]
+pub struct AddEmployee<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Employee::INIT_SPACE,
+        seeds = [b"employee", payroll.key().as_ref(), employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Account<'info, Employee>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: employee_wallet is checked by token program
+    pub employee_wallet: AccountInfo<'info>,
+    #[account(address = payroll.payment_token)]
+    pub payment_token: InterfaceAccount<'info, Mint>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(employee_id: String)]
+pub struct ImportEmployee<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Account<'info, Payroll>,
+    // An existing account is compared against the snapshot, never overwritten
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + Employee::INIT_SPACE,
+        seeds = [b"employee", payroll.key().as_ref(), employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Account<'info, Employee>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddEmployeesBatch<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(address = payroll.payment_token)]
+    pub payment_token: InterfaceAccount<'info, Mint>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProcessPayment<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        mut,
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Account<'info, Employee>,
+    /// SPL payrolls only
+    #[account(
+        mut,
+        seeds = [b"payroll_vault", payroll.key().as_ref()],
+        bump
+    )]
+    pub payroll_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// Native payrolls only
+    #[account(
+        mut,
+        seeds = [b"native_vault", payroll.key().as_ref()],
+        bump
+    )]
+    pub native_vault: Option<SystemAccount<'info>>,
+    /// SPL payrolls only: the employee's wallet, counting a change whose delay has passed
+    #[account(
+        mut,
+        address = employee.wallet_at(Clock::get()?.unix_timestamp) @ ErrorCode::EmployeeWalletMismatch,
+        token::mint = payment_token,
+        token::token_program = token_program
+    )]
+    pub employee_wallet: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// Native payrolls only: the lamport recipient at the employee's wallet
+    #[account(
+        mut,
+        address = employee.wallet_at(Clock::get()?.unix_timestamp) @ ErrorCode::EmployeeWalletMismatch
+    )]
+    pub native_wallet: Option<SystemAccount<'info>>,
+    #[account(address = payroll.payment_token)]
+    pub payment_token: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    /// CHECK: Pyth price update, validated against payroll.oracle in resolve_gross_salary
+    pub price_update: Option<UncheckedAccount<'info>>,
+    /// Required when payroll.pay_stubs_enabled; seeded by the employee's payment sequence
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PayStub::INIT_SPACE,
+        seeds = [b"paystub", employee.key().as_ref(), &employee.payment_count.to_le_bytes()],
+        bump
+    )]
+    pub pay_stub: Option<Account<'info, PayStub>>,
+    #[account(mut)]
+    pub payer: Option<Signer<'info>>,
+    pub system_program: Option<Program<'info, System>>,
+    /// Required when net pay exceeds payroll.approval_threshold; closed on payment
+    #[account(
+        mut,
+        seeds = [b"approval", employee.key().as_ref(), &employee.payment_count.to_le_bytes()],
+        bump
+    )]
+    pub approval: Option<Account<'info, Approval>>,
+    /// CHECK: receives the approval's rent, must match approval.approver
+    #[account(mut)]
+    pub approver: Option<UncheckedAccount<'info>>,
+    /// Required when payroll.employer_tax_bps is non-zero
+    #[account(
+        mut,
+        constraint = payroll.tax_account == Some(tax_account.key()) @ ErrorCode::InvalidTaxAccount,
+        constraint = tax_account.mint == payroll.payment_token @ ErrorCode::MintMismatch
+    )]
+    pub tax_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// Required when payroll.platform_fee_bps is non-zero
+    #[account(
+        mut,
+        constraint = payroll.platform_account == Some(platform_account.key()) @ ErrorCode::InvalidPlatformAccount,
+        constraint = platform_account.mint == payroll.payment_token @ ErrorCode::MintMismatch
+    )]
+    pub platform_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        seeds = [b"program_config"],
+        bump,
+        constraint = !program_config.global_paused @ ErrorCode::GloballyPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    /// Required when payroll.vault_authority is set
+    pub vault_authority: Option<Signer<'info>>,
+    /// CHECK: the Memo program, required when a memo is passed
+    #[account(address = MEMO_PROGRAM_ID)]
+    pub memo_program: Option<UncheckedAccount<'info>>,
+    /// Required once init_pay_history has run for the employee
+    #[account(
+        mut,
+        seeds = [b"history", employee.key().as_ref()],
+        bump
+    )]
+    pub pay_history: Option<Box<Account<'info, PayHistory>>>,
+    /// Required when payroll.funding_source is set and the vault is short
+    #[account(
+        mut,
+        constraint = payroll.funding_source == Some(funding_source.key()) @ ErrorCode::InvalidFundingSource,
+        constraint = funding_source.mint == payroll.payment_token @ ErrorCode::MintMismatch
+    )]
+    pub funding_source: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// Required by force_payment, must be payroll.authority
+    pub authority: Option<Signer<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct RecordPaymentAttempt<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        mut,
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Account<'info, Employee>,
+    #[account(constraint = payroll.can_operate(&caller.key()) @ ErrorCode::Unauthorized)]
+    pub caller: Signer<'info>,
+    /// CHECK: Pyth price update, validated against payroll.oracle in resolve_gross_salary
+    pub price_update: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct InitPayHistory<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        mut,
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Account<'info, Employee>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + PayHistory::INIT_SPACE,
+        seeds = [b"history", employee.key().as_ref()],
+        bump
+    )]
+    pub pay_history: Box<Account<'info, PayHistory>>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReportPayHistory<'info> {
+    #[account(
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Account<'info, Employee>,
+    #[account(
+        seeds = [b"history", employee.key().as_ref()],
+        bump
+    )]
+    pub pay_history: Box<Account<'info, PayHistory>>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePayStub<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        mut,
+        close = authority,
+        constraint = pay_stub.payroll == payroll.key() @ ErrorCode::Unauthorized
+    )]
+    pub pay_stub: Account<'info, PayStub>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PreviewNetPay<'info> {
+    #[account(
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Account<'info, Employee>,
+    #[account(constraint = payroll.can_operate(&caller.key()) @ ErrorCode::Unauthorized)]
+    pub caller: Signer<'info>,
+    /// CHECK: Pyth price update, validated against payroll.oracle in resolve_gross_salary
+    pub price_update: Option<UncheckedAccount<'info>>,
+}
+
+/// Shared by the read-only employee views, which emit an event and change nothing
+#[derive(Accounts)]
+pub struct ReadEmployee<'info> {
+    #[account(
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Account<'info, Employee>,
+}
+
+#[derive(Accounts)]
+pub struct EmitPayrollStats<'info> {
+    #[account(
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+}
+
+#[derive(Accounts)]
+pub struct CheckSolvency<'info> {
+    #[account(
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    /// CHECK: Pyth price update, validated against payroll.oracle in resolve_gross_salary
+    pub price_update: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyVault<'info> {
+    #[account(
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    /// CHECK: deliberately unconstrained so each broken invariant gets its own error;
+    /// address, mint and owner are checked in the handler
+    pub payroll_vault: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DepositFunds<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    /// SPL payrolls only
+    #[account(
+        mut,
+        seeds = [b"payroll_vault", payroll.key().as_ref()],
+        bump
+    )]
+    pub payroll_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// Native payrolls only
+    #[account(
+        mut,
+        seeds = [b"native_vault", payroll.key().as_ref()],
+        bump
+    )]
+    pub native_vault: Option<SystemAccount<'info>>,
+    /// Either the payroll authority or its designated funder
+    #[account(
+        mut,
+        constraint = payroll.can_fund(&authority.key()) @ ErrorCode::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+    /// SPL payrolls only; must hold the payroll's own mint, not a wrapped or bridged variant
+    #[account(
+        mut,
+        constraint = authority_token_account.mint == payroll.payment_token @ ErrorCode::MintMismatch
+    )]
+    pub authority_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = payroll.payment_token)]
+    pub payment_token: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        seeds = [b"program_config"],
+        bump,
+        constraint = !program_config.global_paused @ ErrorCode::GloballyPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct Disburse<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        mut,
+        seeds = [b"payroll_vault", payroll.key().as_ref()],
+        bump
+    )]
+    pub payroll_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = recipient.mint == payroll.payment_token @ ErrorCode::MintMismatch
+    )]
+    pub recipient: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = payroll.payment_token)]
+    pub payment_token: InterfaceAccount<'info, Mint>,
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    #[account(
+        seeds = [b"program_config"],
+        bump,
+        constraint = !program_config.global_paused @ ErrorCode::GloballyPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct RequestWithdrawal<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Account<'info, Payroll>,
+    /// CHECK: only recorded here; the token or system program validates it on execute
+    pub destination: UncheckedAccount<'info>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteWithdrawal<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Account<'info, Payroll>,
+    /// SPL payrolls only
+    #[account(
+        mut,
+        seeds = [b"payroll_vault", payroll.key().as_ref()],
+        bump
+    )]
+    pub payroll_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// Native payrolls only
+    #[account(
+        mut,
+        seeds = [b"native_vault", payroll.key().as_ref()],
+        bump
+    )]
+    pub native_vault: Option<SystemAccount<'info>>,
+    /// CHECK: must be the destination fixed by request_withdrawal
+    #[account(
+        mut,
+        address = payroll.withdrawal_destination @ ErrorCode::InvalidWithdrawalDestination
+    )]
+    pub destination: UncheckedAccount<'info>,
+    #[account(address = payroll.payment_token)]
+    pub payment_token: InterfaceAccount<'info, Mint>,
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        seeds = [b"program_config"],
+        bump,
+        constraint = !program_config.global_paused @ ErrorCode::GloballyPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    /// Required when payroll.withdrawal_threshold is non-zero
+    #[account(
+        mut,
+        seeds = [b"withdrawal_approvals", payroll.key().as_ref()],
+        bump,
+        has_one = payroll
+    )]
+    pub withdrawal_approvals: Option<Account<'info, WithdrawalApprovals>>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveWithdrawal<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    /// Created by the first approval and kept for later requests
+    #[account(
+        init_if_needed,
+        payer = signer,
+        space = 8 + WithdrawalApprovals::INIT_SPACE,
+        seeds = [b"withdrawal_approvals", payroll.key().as_ref()],
+        bump
+    )]
+    pub withdrawal_approvals: Account<'info, WithdrawalApprovals>,
+    #[account(
+        mut,
+        constraint = payroll.withdrawal_signers.contains(&signer.key()) @ ErrorCode::Unauthorized
+    )]
+    pub signer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct WithdrawalApprovals {
+    pub payroll: Pubkey,
+    pub request_sequence: u64, // Payroll::withdrawal_request_sequence the approvals belong to
+    #[max_len(5)]
+    pub approvals: Vec<Pubkey>,
+}
+
+#[derive(Accounts)]
+pub struct CancelWithdrawal<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    /// The payroll authority or its guardian
+    #[account(constraint = payroll.can_freeze(&signer.key()) @ ErrorCode::Unauthorized)]
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateVesting<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Account<'info, Employee>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + VestingSchedule::INIT_SPACE,
+        seeds = [b"vesting", employee.key().as_ref()],
+        bump
+    )]
+    pub vesting: Account<'info, VestingSchedule>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimVested<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        mut,
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Account<'info, Employee>,
+    #[account(
+        mut,
+        seeds = [b"vesting", employee.key().as_ref()],
+        bump
+    )]
+    pub vesting: Account<'info, VestingSchedule>,
+    #[account(
+        mut,
+        seeds = [b"payroll_vault", payroll.key().as_ref()],
+        bump
+    )]
+    pub payroll_vault: InterfaceAccount<'info, TokenAccount>,
+    /// Checked against employee.wallet after any pending wallet change is applied
+    #[account(mut)]
+    pub employee_wallet: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = payroll.payment_token)]
+    pub payment_token: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    #[account(
+        seeds = [b"program_config"],
+        bump,
+        constraint = !program_config.global_paused @ ErrorCode::GloballyPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct PayReimbursement<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        mut,
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Account<'info, Employee>,
+    #[account(
+        mut,
+        seeds = [b"payroll_vault", payroll.key().as_ref()],
+        bump
+    )]
+    pub payroll_vault: InterfaceAccount<'info, TokenAccount>,
+    /// Checked against employee.wallet after any pending wallet change is applied
+    #[account(mut)]
+    pub employee_wallet: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = payroll.payment_token)]
+    pub payment_token: InterfaceAccount<'info, Mint>,
+    #[account(constraint = payroll.can_operate(&caller.key()) @ ErrorCode::Unauthorized)]
+    pub caller: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    #[account(
+        seeds = [b"program_config"],
+        bump,
+        constraint = !program_config.global_paused @ ErrorCode::GloballyPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SweepDust<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Account<'info, Employee>,
+    #[account(
+        mut,
+        token::mint = payment_token,
+        token::authority = employee,
+        token::token_program = token_program
+    )]
+    pub dust_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"payroll_vault", payroll.key().as_ref()],
+        bump
+    )]
+    pub payroll_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = payroll.payment_token)]
+    pub payment_token: InterfaceAccount<'info, Mint>,
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    #[account(
+        seeds = [b"program_config"],
+        bump,
+        constraint = !program_config.global_paused @ ErrorCode::GloballyPaused
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct FreezePayroll<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    /// The registered guardian, or the authority itself
+    #[account(constraint = payroll.can_freeze(&guardian.key()) @ ErrorCode::Unauthorized)]
+    pub guardian: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PausePayroll<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Account<'info, Payroll>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ResumePayroll<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Account<'info, Payroll>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateEmployee<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        mut,
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Account<'info, Employee>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(employee_id: String)]
+pub struct MigrateEmployee<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Account<'info, Payroll>,
+    /// CHECK: may predate the current Employee layout; owner and discriminator are checked
+    /// in the handler before it is resized
+    #[account(
+        mut,
+        seeds = [b"employee", payroll.key().as_ref(), employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateEmployeeWallet<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        mut,
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Account<'info, Employee>,
+    #[account(address = employee.wallet)]
+    pub current_wallet: InterfaceAccount<'info, TokenAccount>,
+    #[account(constraint = new_wallet.mint == payroll.payment_token @ ErrorCode::MintMismatch)]
+    pub new_wallet: InterfaceAccount<'info, TokenAccount>,
+    /// The payroll authority, or the owner of the employee's current wallet
+    #[account(
+        constraint = signer.key() == payroll.authority
+            || signer.key() == current_wallet.owner @ ErrorCode::Unauthorized
+    )]
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateEmployeeSalary<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        mut,
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Account<'info, Employee>,
+    pub authority: Signer<'info>,
+    /// Required for cuts when employee.employee_must_approve_cuts is set
+    #[account(address = employee.wallet)]
+    pub employee_wallet: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// Owner of employee_wallet, consenting to the cut
+    pub wallet_owner: Option<Signer<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct RefundPayment<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        mut,
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Account<'info, Employee>,
+    /// SPL payrolls only
+    #[account(
+        mut,
+        seeds = [b"payroll_vault", payroll.key().as_ref()],
+        bump
+    )]
+    pub payroll_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// Native payrolls only
+    #[account(
+        mut,
+        seeds = [b"native_vault", payroll.key().as_ref()],
+        bump
+    )]
+    pub native_vault: Option<SystemAccount<'info>>,
+    /// SPL payrolls only; the refund is drawn from here
+    #[account(
+        mut,
+        address = employee.wallet,
+        constraint = employee_wallet.mint == payroll.payment_token @ ErrorCode::MintMismatch
+    )]
+    pub employee_wallet: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// Owner of employee_wallet, or employee.wallet itself on native payrolls
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(address = payroll.payment_token)]
+    pub payment_token: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetCutApproval<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        mut,
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Account<'info, Employee>,
+    #[account(address = employee.wallet)]
+    pub employee_wallet: InterfaceAccount<'info, TokenAccount>,
+    #[account(constraint = wallet_owner.key() == employee_wallet.owner @ ErrorCode::Unauthorized)]
+    pub wallet_owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateEncryptionPubkey<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        mut,
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Account<'info, Employee>,
+    #[account(address = employee.wallet)]
+    pub employee_wallet: InterfaceAccount<'info, TokenAccount>,
+    /// Owner of employee_wallet; results are sealed to a key only they should choose
+    #[account(constraint = wallet_owner.key() == employee_wallet.owner @ ErrorCode::Unauthorized)]
+    pub wallet_owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ApprovePayment<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
+        bump
+    )]
+    pub employee: Account<'info, Employee>,
+    #[account(
+        init,
+        payer = approver,
+        space = 8 + Approval::INIT_SPACE,
+        seeds = [b"approval", employee.key().as_ref(), &employee.payment_count.to_le_bytes()],
+        bump
+    )]
+    pub approval: Account<'info, Approval>,
+    #[account(
+        mut,
+        constraint = payroll.approver == Some(approver.key()) @ ErrorCode::Unauthorized
+    )]
+    pub approver: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePayrollConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Account<'info, Payroll>,
+    pub authority: Signer<'info>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ProgramConfig {
+    pub admin: Pubkey,
+    pub global_paused: bool, // halts every fund-moving instruction across all payrolls
+}
+
+#[derive(Accounts)]
+pub struct SetVaultAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Account<'info, Payroll>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"payroll_vault", payroll.key().as_ref()],
+        bump
+    )]
+    pub payroll_vault: InterfaceAccount<'info, TokenAccount>,
+    /// Required when ownership currently sits with an external vault authority
+    pub current_vault_authority: Option<Signer<'info>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ChangePaymentToken<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub payroll: Account<'info, Payroll>,
+    /// Receives the old vault's rent and pays for the new one
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// Closed and recreated at the same address for new_mint
+    #[account(
+        mut,
+        seeds = [b"payroll_vault", payroll.key().as_ref()],
+        bump
+    )]
+    pub payroll_vault: InterfaceAccount<'info, TokenAccount>,
+    /// Token program that owns the current vault
+    pub token_program: Interface<'info, TokenInterface>,
+    #[account(
+        constraint = *new_mint.to_account_info().owner == new_token_program.key() @ ErrorCode::MintMismatch
+    )]
+    pub new_mint: InterfaceAccount<'info, Mint>,
+    pub new_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeRecovery<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    /// Fails to initialize while another proposal is open
+    #[account(
+        init,
+        payer = guardian,
+        space = 8 + RecoveryProposal::INIT_SPACE,
+        seeds = [b"recovery", payroll.key().as_ref()],
+        bump
+    )]
+    pub recovery: Account<'info, RecoveryProposal>,
+    #[account(
+        mut,
+        constraint = payroll.guardians.contains(&guardian.key()) @ ErrorCode::Unauthorized
+    )]
+    pub guardian: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveRecovery<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        mut,
+        seeds = [b"recovery", payroll.key().as_ref()],
+        bump,
+        has_one = payroll
+    )]
+    pub recovery: Account<'info, RecoveryProposal>,
+    #[account(constraint = payroll.guardians.contains(&guardian.key()) @ ErrorCode::Unauthorized)]
+    pub guardian: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteRecovery<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        mut,
+        seeds = [b"recovery", payroll.key().as_ref()],
+        bump,
+        has_one = payroll,
+        has_one = proposer,
+        close = proposer
+    )]
+    pub recovery: Account<'info, RecoveryProposal>,
+    /// CHECK: receives the proposal's rent; matched by has_one
+    #[account(mut)]
+    pub proposer: UncheckedAccount<'info>,
+    #[account(constraint = payroll.guardians.contains(&guardian.key()) @ ErrorCode::Unauthorized)]
+    pub guardian: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelRecovery<'info> {
+    #[account(
+        mut,
+        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
+        bump
+    )]
+    pub payroll: Account<'info, Payroll>,
+    #[account(
+        mut,
+        seeds = [b"recovery", payroll.key().as_ref()],
+        bump,
+        has_one = payroll,
+        has_one = proposer,
+        close = proposer
+    )]
+    pub recovery: Account<'info, RecoveryProposal>,
+    /// CHECK: receives the proposal's rent; matched by has_one
+    #[account(mut)]
+    pub proposer: UncheckedAccount<'info>,
+    /// The payroll authority or the proposing guardian
+    #[account(
+        constraint = canceller.key() == payroll.authority
+            || canceller.key() == recovery.proposer @ ErrorCode::Unauthorized
+    )]
+    pub canceller: Signer<'info>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct RecoveryProposal {
+    pub payroll: Pubkey,
+    pub proposer: Pubkey, // guardian who paid for the account and gets its rent back
+    pub new_authority: Pubkey,
+    #[max_len(5)]
+    pub approvals: Vec<Pubkey>, // guardians who approved, the proposer included
+    pub executable_at: i64, // end of the authority's window to cancel
+}
+
+#[derive(Accounts)]
+pub struct UpdateTaxRateBatch<'info> {
+    // Payroll accounts arrive in remaining_accounts and are checked against this signer
+    pub authority: Signer<'info>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Payroll {
+    pub authority: Pubkey,
+    #[max_len(32)]
+    pub payroll_id: String,
+    pub payment_token: Pubkey,
+    pub employee_count: u64,
+    pub is_active: bool,
+    pub tax_rate: u16, // basis points
+    pub total_funds: u64,
+    pub vault_bump: u8,
+    pub max_catchup_periods: u8,
+    pub funder: Option<Pubkey>,
+    pub low_balance_threshold: u64, // 0 disables the warning
+    pub operator: Option<Pubkey>,
+    pub max_employees: u64, // 0 = unlimited
+    pub frozen: bool,
+    pub guardian: Option<Pubkey>,
+    pub current_period: u64,
+    pub total_paid_in_period: u64,
+    pub encrypted_tax_rate: [u8; 32], // confidential counterpart of tax_rate for the MPC path
+    pub tax_rate_pubkey: [u8; 32],
+    pub tax_rate_nonce: u128,
+    pub max_outflow_per_window: u64, // 0 disables the rate limit
+    pub window_seconds: i64,
+    pub window_start: i64,
+    pub window_outflow: u64,
+    pub oracle: Option<Pubkey>, // Pyth PriceUpdateV2 account for USD-denominated salaries
+    pub oracle_feed_id: [u8; 32],
+    pub max_price_age: u64, // seconds
+    pub max_conf_bps: u16, // max confidence interval relative to price
+    pub pay_stubs_enabled: bool,
+    pub rounding_mode: RoundingMode, // applied to tax withholding
+    pub wallet_change_delay: i64, // seconds before a wallet update takes effect
+    pub approver: Option<Pubkey>, // second signer for payments above approval_threshold
+    pub approval_threshold: u64, // 0 disables dual control
+    pub clawback_max_per_period: u64, // 0 = limited only by min_net_pay
+    pub min_net_pay: u64, // per-period net pay that clawbacks may not touch
+    pub min_salary: u64,
+    pub max_salary: u64, // 0 = no ceiling
+    pub employer_tax_bps: u16, // employer-paid tax on top of gross
+    pub tax_account: Option<Pubkey>, // token account receiving employer tax
+    pub native: bool, // pays lamports from the native_vault PDA instead of SPL tokens
+    pub withdrawal_delay_seconds: i64, // 0 disables the withdrawal time-lock
+    pub pending_withdrawal_delay: Option<i64>, // a shortened delay waiting out the current one
+    pub pending_withdrawal_delay_effective: i64,
+    pub pending_withdrawal: Option<u64>,
+    pub withdrawal_destination: Pubkey,
+    pub withdrawal_unlock_ts: i64,
+    pub min_payment: u64, // smaller net payouts accumulate in Employee::outstanding_balance
+    pub platform_fee_bps: u16, // service fee on net pay, routed to platform_account
+    pub platform_account: Option<Pubkey>,
+    pub platform_fee_from_employee: bool, // deduct the fee from net pay instead of charging the employer
+    pub strict_schedule: bool, // advance last_payment by whole intervals rather than to the payment time
+    pub schedule_tolerance: i64, // strict mode only: seconds before a boundary a payment may run
+    pub sequence: u64, // bumped by every state-changing instruction and stamped on its events
+    pub pay_day_anchor: i64, // reference timestamp pay boundaries align to; 0 = from last_payment
+    pub vault_authority: Option<Pubkey>, // external owner of the SPL vault; None = the vault PDA
+    pub last_pause_ts: i64,
+    pub last_resume_ts: i64,
+    pub pause_cooldown_seconds: i64, // min gap between pause and resume transitions; 0 = none
+    pub require_confidential: bool, // payments need a fresh calculate_net_pay result
+    pub total_paused_seconds: i64, // cumulative time spent paused, applied lazily to employees
+    pub funding_failure_threshold: u32, // underfunded due dates before auto-suspension; 0 = never
+    pub tax_free_allowance: u64, // per-period gross exempt from withholding
+    pub settlement_delay_seconds: i64, // wait after calculate_net_pay before paying
+    pub round_net_to_whole: bool, // floor net pay to whole tokens, carrying the fraction forward
+    pub payment_decimals: u8, // payment_token decimals, snapshotted when the mint is set
+    #[max_len(5)]
+    pub guardians: Vec<Pubkey>, // social recovery signers, distinct from the freeze guardian
+    pub recovery_threshold: u8, // guardian approvals needed to reassign authority
+    pub recovery_delay_seconds: i64, // time the authority has to cancel a proposed recovery
+    pub recovery_pending: bool, // a RecoveryProposal is open
+    pub encrypted_total_net_pay: [u8; 32], // latest calculate_total_net_payroll result
+    pub total_net_pay_nonce: u128,
+    pub pending_total_net_pay_offset: Option<u64>,
+    pub default_frequency: PaymentFrequency, // used by add_employee when none is given
+    pub event_level: u8, // EVENT_LEVEL_ESSENTIAL or EVENT_LEVEL_ALL
+    pub lifetime_paid: u64, // net pay sent to employees since creation
+    pub lifetime_deposited: u64, // funds credited by deposits since creation
+    pub business_day_shift: bool, // process_payment accepts a client-supplied adjusted_pay_ts
+    pub max_business_day_shift: i64, // seconds an adjusted_pay_ts may precede the due date
+    pub funding_source: Option<Pubkey>, // token account delegated to the vault PDA for top-ups
+    pub min_tenure_seconds: i64, // probation before a first payment, from Employee::created_at
+    #[max_len(5)]
+    pub withdrawal_signers: Vec<Pubkey>, // M-of-N approvers for execute_withdrawal
+    pub withdrawal_threshold: u8, // approvals needed; 0 disables
+    pub withdrawal_request_sequence: u64, // sequence of the request_withdrawal that opened the pending one
+}
+
+impl Payroll {
+    pub fn new(
+        authority: Pubkey,
+        payroll_id: String,
+        payment_token: Pubkey,
+        payment_decimals: u8,
+        tax_rate: u16,
+        max_employees: u64,
+        default_frequency: PaymentFrequency,
+        vault_bump: u8,
+        native: bool,
+    ) -> Self {
+        Self {
+            authority,
+            payroll_id,
+            payment_token,
+            employee_count: 0,
+            is_active: true,
+            tax_rate,
+            total_funds: 0,
+            vault_bump,
+            max_catchup_periods: DEFAULT_MAX_CATCHUP_PERIODS,
+            funder: None,
+            low_balance_threshold: 0,
+            operator: None,
+            max_employees,
+            frozen: false,
+            guardian: None,
+            current_period: 0,
+            total_paid_in_period: 0,
+            encrypted_tax_rate: [0u8; 32],
+            tax_rate_pubkey: [0u8; 32],
+            tax_rate_nonce: 0,
+            max_outflow_per_window: 0,
+            window_seconds: 0,
+            window_start: 0,
+            window_outflow: 0,
+            oracle: None,
+            oracle_feed_id: [0u8; 32],
+            max_price_age: 0,
+            max_conf_bps: 0,
+            pay_stubs_enabled: false,
+            rounding_mode: RoundingMode::Floor,
+            wallet_change_delay: 0,
+            approver: None,
+            approval_threshold: 0,
+            clawback_max_per_period: 0,
+            min_net_pay: 0,
+            min_salary: 0,
+            max_salary: 0,
+            employer_tax_bps: 0,
+            tax_account: None,
+            native,
+            withdrawal_delay_seconds: 0,
+            pending_withdrawal_delay: None,
+            pending_withdrawal_delay_effective: 0,
+            pending_withdrawal: None,
+            withdrawal_destination: Pubkey::default(),
+            withdrawal_unlock_ts: 0,
+            min_payment: 0,
+            platform_fee_bps: 0,
+            platform_account: None,
+            platform_fee_from_employee: false,
+            strict_schedule: true,
+            schedule_tolerance: 0,
+            sequence: 0,
+            pay_day_anchor: 0,
+            vault_authority: None,
+            last_pause_ts: 0,
+            last_resume_ts: 0,
+            pause_cooldown_seconds: 0,
+            require_confidential: false,
+            total_paused_seconds: 0,
+            funding_failure_threshold: 0,
+            tax_free_allowance: 0,
+            settlement_delay_seconds: 0,
+            round_net_to_whole: false,
+            payment_decimals,
+            guardians: Vec::new(),
+            recovery_threshold: 0,
+            recovery_delay_seconds: 0,
+            recovery_pending: false,
+            encrypted_total_net_pay: [0u8; 32],
+            total_net_pay_nonce: 0,
+            pending_total_net_pay_offset: None,
+            default_frequency,
+            event_level: EVENT_LEVEL_ALL,
+            lifetime_paid: 0,
+            lifetime_deposited: 0,
+            business_day_shift: false,
+            max_business_day_shift: 0,
+            funding_source: None,
+            min_tenure_seconds: 0,
+            withdrawal_signers: Vec::new(),
+            withdrawal_threshold: 0,
+            withdrawal_request_sequence: 0,
+        }
+    }
+
+    /// Splits `net_pay` plus the employee's carried remainder into the whole-token amount
+    /// paid now and the fraction carried to the next payment. Without rounding nothing is
+    /// held back, so a remainder left from before rounding was disabled is paid out.
+    pub fn round_net_pay(&self, net_pay: u64, carry_forward: u64) -> Result<(u64, u64)> {
+        let total = net_pay
+            .checked_add(carry_forward)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if !self.round_net_to_whole {
+            return Ok((total, 0));
+        }
+        let unit = 10u64
+            .checked_pow(self.payment_decimals as u32)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let remainder = total % unit;
+        Ok((total - remainder, remainder))
+    }
+
+    pub fn apply_pending_withdrawal_delay(&mut self, now: i64) {
+        if let Some(delay) = self.pending_withdrawal_delay {
+            if now >= self.pending_withdrawal_delay_effective {
+                self.withdrawal_delay_seconds = delay;
+                self.pending_withdrawal_delay = None;
+                self.pending_withdrawal_delay_effective = 0;
+            }
+        }
+    }
+
+    pub fn can_fund(&self, signer: &Pubkey) -> bool {
+        *signer == self.authority || self.funder == Some(*signer)
+    }
+
+    pub fn can_operate(&self, signer: &Pubkey) -> bool {
+        *signer == self.authority || self.operator == Some(*signer)
+    }
+
+    pub fn can_freeze(&self, signer: &Pubkey) -> bool {
+        *signer == self.authority || self.guardian == Some(*signer)
+    }
+
+    pub fn is_below_low_water(&self) -> bool {
+        self.total_funds < self.low_balance_threshold
+    }
+
+    /// Counts `amount` against the outflow window, rolling the window over once it has elapsed.
+    pub fn record_outflow(&mut self, amount: u64, now: i64) -> Result<()> {
+        if self.max_outflow_per_window == 0 {
+            return Ok(());
+        }
+
+        if now.saturating_sub(self.window_start) >= self.window_seconds {
+            self.window_start = now;
+            self.window_outflow = 0;
+        }
+
+        let window_outflow = self
+            .window_outflow
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(
+            window_outflow <= self.max_outflow_per_window,
+            ErrorCode::RateLimitExceeded
+        );
+        self.window_outflow = window_outflow;
+
+        Ok(())
+    }
+
+    /// Fails while `pause_cooldown_seconds` have not elapsed since `last_transition`.
+    pub fn check_pause_cooldown(&self, last_transition: i64, now: i64) -> Result<()> {
+        let ready_ts = last_transition
+            .checked_add(self.pause_cooldown_seconds)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(now >= ready_ts, ErrorCode::PauseCooldownActive);
+        Ok(())
+    }
+
+    /// Bumps `employee_count` by `count`, respecting `max_employees`.
+    pub fn reserve_employee_slots(&mut self, count: u64) -> Result<()> {
+        let employee_count = self
+            .employee_count
+            .checked_add(count)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(
+            self.max_employees == 0 || employee_count <= self.max_employees,
+            ErrorCode::EmployeeLimitReached
+        );
+        self.employee_count = employee_count;
+
+        Ok(())
+    }
+
+    /// Guards against data-entry mistakes; bounds are in the employee's salary units.
+    pub fn check_salary_bounds(&self, salary: u64) -> Result<()> {
+        require!(
+            salary >= self.min_salary && (self.max_salary == 0 || salary <= self.max_salary),
+            ErrorCode::SalaryOutOfBounds
+        );
+        Ok(())
+    }
+
+    /// Advances the audit sequence; events emitted by the calling instruction carry the result.
+    pub fn next_sequence(&mut self) -> Result<u64> {
+        self.sequence = self
+            .sequence
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(self.sequence)
+    }
+
+    /// The time schedules are evaluated at, shifted by the early-payment tolerance.
+    pub fn schedule_time(&self, now: i64) -> Result<i64> {
+        if !self.strict_schedule {
+            return Ok(now);
+        }
+        now.checked_add(self.schedule_tolerance)
+            .ok_or_else(|| ErrorCode::MathOverflow.into())
+    }
+
+    pub fn platform_fee_for(&self, net_pay: u64) -> Result<u64> {
+        u64::try_from(
+            self.rounding_mode.apply(
+                (net_pay as u128)
+                    .checked_mul(self.platform_fee_bps as u128)
+                    .ok_or(ErrorCode::MathOverflow)?,
+                10000,
+            ),
+        )
+        .map_err(|_| ErrorCode::MathOverflow.into())
+    }
+
+    /// Withholding on the employee's accrued overtime, at the payroll rate without a
+    /// second allowance.
+    pub fn overtime_tax_for(&self, employee: &Employee) -> Result<u64> {
+        let overtime = employee.overtime_pay;
+        let tax = if employee.classification.withholds_tax() {
+            self.rounding_mode
+                .apply(overtime as u128 * self.tax_rate as u128, 10000)
+        } else {
+            0
+        };
+        require!(tax <= overtime as u128, ErrorCode::TaxExceedsSalary);
+        Ok(tax as u64)
+    }
+
+    /// Employer-paid tax owed on `gross` for each of `periods` periods.
+    pub fn employer_tax_for(&self, gross: u64, periods: u64) -> Result<u64> {
+        self.rounding_mode
+            .apply(
+                (gross as u128)
+                    .checked_mul(self.employer_tax_bps as u128)
+                    .ok_or(ErrorCode::MathOverflow)?,
+                10000,
+            )
+            .checked_mul(periods as u128)
+            .and_then(|tax| u64::try_from(tax).ok())
+            .ok_or_else(|| ErrorCode::MathOverflow.into())
+    }
+
+    /// Portion of `balance` to recover from a payment of `net_pay` covering `periods` periods.
+    pub fn clawback_for(&self, balance: u64, net_pay: u64, periods: u64) -> Result<u64> {
+        let protected = self
+            .min_net_pay
+            .checked_mul(periods)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let mut clawback = balance.min(net_pay.saturating_sub(protected));
+        if self.clawback_max_per_period > 0 {
+            let cap = self
+                .clawback_max_per_period
+                .checked_mul(periods)
+                .ok_or(ErrorCode::MathOverflow)?;
+            clawback = clawback.min(cap);
+        }
+        Ok(clawback)
+    }
+
+    pub fn warn_if_below_low_water(&self) {
+        if self.is_below_low_water() {
+            emit!(LowBalanceWarning {
+                payroll_id: self.payroll_id.clone(),
+                sequence: self.sequence,
+                total_funds: self.total_funds,
+                threshold: self.low_balance_threshold,
+            });
+        }
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Employee {
+    pub payroll: Pubkey,
+    #[max_len(32)]
+    pub employee_id: String,
+    pub wallet: Pubkey,
+    pub salary_amount: u64,
+    pub deductions: u64,
+    pub payment_frequency: PaymentFrequency,
+    pub last_payment: i64,
+    pub is_active: bool,
+    pub salary_decimals: u8, // payment_token decimals when the employee was added
+    pub pending_salary: Option<u64>,
+    pub pending_salary_effective: i64,
+    #[max_len(8)]
+    pub deduction_items: Vec<DeductionItem>,
+    pub pending_computation_offset: Option<u64>,
+    pub garnishment_amount: u64,
+    pub garnishment_bps: u16, // max share of disposable pay that can be garnished
+    pub payment_interval_override: Option<i64>, // seconds, replaces the frequency-derived interval
+    pub salary_is_usd: bool, // salary_amount is in micro-USD, converted via payroll.oracle
+    pub payment_count: u64,
+    pub pending_wallet: Option<Pubkey>,
+    pub pending_wallet_effective: i64,
+    pub last_idempotency_key: [u8; 16], // key of the most recent process_payment
+    pub clawback_balance: u64, // overpayment still to be recovered from future pay
+    #[max_len(32)]
+    pub department: String,
+    #[max_len(32)]
+    pub cost_center: String,
+    pub failed_attempts: u32, // near-misses logged via record_payment_attempt
+    pub total_reimbursed: u64,
+    pub pending_tax_computation_offset: Option<u64>,
+    pub encrypted_net_pay: [u8; 32], // latest calculate_net_pay result, decryptable by the requester
+    pub net_pay_nonce: u128,
+    pub outstanding_balance: u64, // deferred net pay still below payroll.min_payment
+    pub employee_must_approve_cuts: bool, // salary reductions need the wallet owner's signature
+    pub net_pay_input_salary: [u8; 32], // last calculate_net_pay inputs, reused by retry_net_pay
+    pub net_pay_input_deductions: [[u8; 32]; MAX_CONFIDENTIAL_DEDUCTIONS],
+    pub net_pay_input_pub_key: [u8; 32],
+    pub net_pay_input_nonce: u128,
+    pub skip_next: bool, // the next due pay run advances the schedule without paying
+    pub deduction_bps: u16, // percentage-of-gross deduction on top of the fixed amounts
+    pub ytd_tax_encrypted: [u8; 32], // encrypted year-to-date withholding; zeroed until reset_ytd_tax
+    pub ytd_tax_pub_key: [u8; 32],
+    pub ytd_tax_nonce: u128,
+    pub pending_ytd_computation_offset: Option<u64>,
+    #[max_len(4)]
+    pub deposit_splits: Vec<DepositSplit>, // empty = all net pay to wallet
+    pub net_pay_result_fresh: bool, // set by calculate_net_pay_callback, consumed by a payment
+    pub period_pay_cap: u64, // max paid per period across salary and reimbursements; 0 = none
+    pub paid_this_period: u64,
+    pub paid_period_start: i64, // last_payment value the running total belongs to
+    pub paused_seconds_applied: i64, // payroll.total_paused_seconds already added to last_payment
+    pub created_at: i64, // onboarding time; 0 for accounts migrated from before it was tracked
+    pub consecutive_funding_failures: u32, // underfunded due dates since the last payment
+    pub last_funding_failure_due: i64, // due date the latest funding failure was counted for
+    pub encryption_pubkey: [u8; 32], // x25519 key calculate_net_pay seals results to
+    pub net_pay_computed_at: i64, // when the latest confidential net pay result landed
+    pub carry_forward: u64, // sub-token remainder held back by whole-token rounding
+    pub pay_history_enabled: bool, // payments must append to the employee's PayHistory
+    pub net_pay_validated: bool, // validate_net_pay confirmed the latest result, consumed by a payment
+    pub pending_validation_offset: Option<u64>,
+    pub classification: EmployeeClass, // set at add time; migrated accounts read as Exempt
+    pub overtime_pay: u64, // gross overtime accrued by record_overtime, paid with the next payment
+}
+
+impl Employee {
+    pub fn new(payroll: Pubkey, params: EmployeeParams, salary_decimals: u8) -> Self {
+        Self {
+            payroll,
+            employee_id: params.employee_id,
+            wallet: params.wallet,
+            salary_amount: params.salary_amount,
+            deductions: params.deductions,
+            payment_frequency: params.payment_frequency,
+            // Set last_payment to 0 to allow immediate first payment
+            last_payment: 0,
+            is_active: true,
+            salary_decimals,
+            pending_salary: None,
+            pending_salary_effective: 0,
+            deduction_items: Vec::new(),
+            pending_computation_offset: None,
+            garnishment_amount: 0,
+            garnishment_bps: 0,
+            payment_interval_override: None,
+            salary_is_usd: false,
+            payment_count: 0,
+            pending_wallet: None,
+            pending_wallet_effective: 0,
+            last_idempotency_key: [0; 16],
+            clawback_balance: 0,
+            department: params.department,
+            cost_center: params.cost_center,
+            failed_attempts: 0,
+            total_reimbursed: 0,
+            pending_tax_computation_offset: None,
+            encrypted_net_pay: [0u8; 32],
+            net_pay_nonce: 0,
+            outstanding_balance: 0,
+            employee_must_approve_cuts: false,
+            net_pay_input_salary: [0u8; 32],
+            net_pay_input_deductions: [[0u8; 32]; MAX_CONFIDENTIAL_DEDUCTIONS],
+            net_pay_input_pub_key: [0u8; 32],
+            net_pay_input_nonce: 0,
+            skip_next: false,
+            deduction_bps: 0,
+            ytd_tax_encrypted: [0u8; 32],
+            ytd_tax_pub_key: [0u8; 32],
+            ytd_tax_nonce: 0,
+            pending_ytd_computation_offset: None,
+            deposit_splits: Vec::new(),
+            net_pay_result_fresh: false,
+            period_pay_cap: 0,
+            paid_this_period: 0,
+            paid_period_start: 0,
+            paused_seconds_applied: 0,
+            created_at: 0,
+            consecutive_funding_failures: 0,
+            last_funding_failure_due: 0,
+            encryption_pubkey: params.encryption_pubkey,
+            net_pay_computed_at: 0,
+            carry_forward: 0,
+            pay_history_enabled: false,
+            net_pay_validated: false,
+            pending_validation_offset: None,
+            classification: params.classification,
+            overtime_pay: 0,
+        }
+    }
+
+    /// The wallet payments go to at `now`, once any time-locked change has been promoted.
+    pub fn wallet_at(&self, now: i64) -> Pubkey {
+        match self.pending_wallet {
+            Some(wallet) if now >= self.pending_wallet_effective => wallet,
+            _ => self.wallet,
+        }
+    }
+
+    pub fn apply_pending_wallet(&mut self, now: i64) {
+        if let Some(wallet) = self.pending_wallet {
+            if now >= self.pending_wallet_effective {
+                self.wallet = wallet;
+                self.pending_wallet = None;
+                self.pending_wallet_effective = 0;
+            }
+        }
+    }
+
+    pub fn payment_interval(&self) -> i64 {
+        self.payment_interval_override
+            .unwrap_or_else(|| self.payment_frequency.interval_seconds())
+    }
+
+    /// First pay boundary strictly after last_payment. Without an anchor that is
+    /// `last_payment + interval`; with one, boundaries sit at `anchor + k * interval`, so
+    /// the next is `anchor + (floor((last_payment - anchor) / interval) + 1) * interval`
+    /// (euclidean division, so anchors after last_payment work too).
+    pub fn next_boundary(&self, pay_day_anchor: i64) -> Result<i64> {
+        let interval = self.payment_interval();
+        if pay_day_anchor == 0 {
+            return self
+                .last_payment
+                .checked_add(interval)
+                .ok_or_else(|| ErrorCode::MathOverflow.into());
+        }
+        self.last_payment
+            .checked_sub(pay_day_anchor)
+            .map(|offset| offset.div_euclid(interval))
+            .and_then(|k| k.checked_add(1))
+            .and_then(|k| k.checked_mul(interval))
+            .and_then(|elapsed| pay_day_anchor.checked_add(elapsed))
+            .ok_or_else(|| ErrorCode::MathOverflow.into())
+    }
+
+    /// Pay boundaries crossed since last_payment; the first payment is always due.
+    pub fn periods_due(&self, now: i64, pay_day_anchor: i64) -> Result<i64> {
+        if self.last_payment == 0 {
+            // First payment anchors the schedule
+            return Ok(1);
+        }
+        let next = self.next_boundary(pay_day_anchor)?;
+        if now < next {
+            return Ok(0);
+        }
+        Ok(1 + (now - next) / self.payment_interval())
+    }
+
+    /// last_payment after paying `periods` boundaries on a strict schedule.
+    pub fn advance_schedule(&self, periods: u64, pay_day_anchor: i64) -> Result<i64> {
+        let next = self.next_boundary(pay_day_anchor)?;
+        (periods as i64 - 1)
+            .checked_mul(self.payment_interval())
+            .and_then(|elapsed| next.checked_add(elapsed))
+            .ok_or_else(|| ErrorCode::MathOverflow.into())
+    }
+
+    /// Earliest time process_payment will accept; 0 means the first payment is due now.
+    pub fn next_payment_ts(&self, pay_day_anchor: i64) -> Result<i64> {
+        if self.last_payment == 0 {
+            return Ok(0);
+        }
+        self.next_boundary(pay_day_anchor)
+    }
+
+    /// Moves `last_payment` forward by payroll pause time accrued since the last call and
+    /// returns the shift. Never-paid employees only sync, since their first payment is
+    /// due immediately anyway. Anchored schedules rejoin the anchor grid at the next boundary.
+    pub fn apply_pause_adjustment(&mut self, total_paused_seconds: i64) -> Result<i64> {
+        let shift = total_paused_seconds.saturating_sub(self.paused_seconds_applied);
+        self.paused_seconds_applied = total_paused_seconds;
+        if shift <= 0 || self.last_payment == 0 {
+            return Ok(0);
+        }
+
+        let shifted = self
+            .last_payment
+            .checked_add(shift)
+            .ok_or(ErrorCode::MathOverflow)?;
+        // Keep the per-period pay total attached to the period it belongs to
+        if self.paid_period_start == self.last_payment {
+            self.paid_period_start = shifted;
+        }
+        self.last_payment = shifted;
+        Ok(shift)
+    }
+
+    /// Adds `amount` to the running total for the period that began at `last_payment`,
+    /// starting a fresh total whenever a pay run has moved `last_payment` on. The total is
+    /// tracked even without a cap so enabling one mid-period counts what was already paid.
+    pub fn record_period_pay(&mut self, amount: u64, periods: u64) -> Result<()> {
+        if self.paid_period_start != self.last_payment {
+            self.paid_period_start = self.last_payment;
+            self.paid_this_period = 0;
+        }
+        self.paid_this_period = self
+            .paid_this_period
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if self.period_pay_cap > 0 {
+            let cap = self
+                .period_pay_cap
+                .checked_mul(periods.max(1))
+                .ok_or(ErrorCode::MathOverflow)?;
+            require!(self.paid_this_period <= cap, ErrorCode::PeriodCapExceeded);
+        }
+        Ok(())
+    }
+
+    /// Flat deductions plus every itemized line.
+    pub fn total_deductions(&self) -> Result<u64> {
+        self.deduction_items
+            .iter()
+            .try_fold(self.deductions, |total, item| total.checked_add(item.amount))
+            .ok_or_else(|| ErrorCode::MathOverflow.into())
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PayStub {
+    pub payroll: Pubkey,
+    pub employee: Pubkey,
+    pub sequence: u64, // employee.payment_count at the time of payment
+    pub period: u64, // payroll.current_period at the time of payment
+    pub gross: u64,
+    pub tax: u64,
+    pub deductions: u64,
+    pub garnishment: u64,
+    pub net: u64, // total transferred, across all periods_paid
+    pub periods_paid: u64,
+    pub timestamp: i64,
+    pub clawback: u64,
+}
+
+#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct PaymentRecord {
+    pub ts: i64,
+    pub gross: u64, // across all periods paid
+    pub tax: u64,
+    pub net: u64, // amount transferred
+}
+
+/// Ring buffer of an employee's latest payments; the oldest record is overwritten once full.
+#[account]
+#[derive(InitSpace)]
+pub struct PayHistory {
+    pub employee: Pubkey,
+    pub next_index: u8, // slot the next payment is written to
+    pub len: u8, // filled slots, up to PAY_HISTORY_LEN
+    pub records: [PaymentRecord; PAY_HISTORY_LEN],
+}
+
+impl PayHistory {
+    pub fn push(&mut self, record: PaymentRecord) {
+        self.records[self.next_index as usize] = record;
+        self.next_index = ((self.next_index as usize + 1) % PAY_HISTORY_LEN) as u8;
+        self.len = (self.len as usize + 1).min(PAY_HISTORY_LEN) as u8;
+    }
+
+    /// Filled records, oldest first.
+    pub fn ordered(&self) -> Vec<PaymentRecord> {
+        let len = self.len as usize;
+        let start = (self.next_index as usize + PAY_HISTORY_LEN - len) % PAY_HISTORY_LEN;
+        (0..len)
+            .map(|i| self.records[(start + i) % PAY_HISTORY_LEN])
+            .collect()
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Approval {
+    pub payroll: Pubkey,
+    pub employee: Pubkey,
+    pub approver: Pubkey,
+    pub sequence: u64, // employee.payment_count of the payment being approved
+    pub amount: u64, // max net pay this approval covers
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct VestingSchedule {
+    pub payroll: Pubkey,
+    pub employee: Pubkey,
+    pub total_amount: u64,
+    pub start_ts: i64,
+    pub cliff_ts: i64, // nothing is claimable before this
+    pub duration: i64, // seconds from start_ts until fully vested
+    pub claimed: u64,
+}
+
+impl VestingSchedule {
+    /// Linearly vested amount at `now`, zero before the cliff and capped at total_amount.
+    pub fn vested_amount(&self, now: i64) -> Result<u64> {
+        if now < self.cliff_ts {
+            return Ok(0);
+        }
+        let elapsed = now.saturating_sub(self.start_ts).min(self.duration);
+        let vested = (self.total_amount as u128)
+            .checked_mul(elapsed as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            / self.duration as u128;
+        Ok(vested as u64)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct EmployeeParams {
+    pub employee_id: String,
+    pub wallet: Pubkey,
+    pub salary_amount: u64,
+    pub deductions: u64,
+    pub payment_frequency: PaymentFrequency,
+    pub department: String,
+    pub cost_center: String,
+    pub encryption_pubkey: [u8; 32], // x25519 key net pay is sealed to; zeros until known
+    pub classification: EmployeeClass,
+}
+
+impl EmployeeParams {
+    pub fn validate(&self) -> Result<()> {
+        require!(is_valid_id(&self.employee_id), ErrorCode::InvalidEmployeeId);
+        validate_employee_metadata(&self.department, &self.cost_center)
+    }
+}
+
+/// Rejects a batch before any work starts when `count` accounts at `units_per_item` would
+/// overrun `BATCH_COMPUTE_BUDGET`, instead of hitting the compute limit part-way through.
+pub fn check_batch_size(count: usize, units_per_item: u64) -> Result<()> {
+    let estimated = (count as u64)
+        .checked_mul(units_per_item)
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(
+        count <= MAX_BATCH_SIZE && estimated <= BATCH_COMPUTE_BUDGET,
+        ErrorCode::BatchTooLarge
+    );
+    Ok(())
+}
+
+/// Ids seed PDAs verbatim, so they are limited to lowercase ASCII letters, digits, `_` and
+/// `-`. That rules out empty or whitespace ids and case variants like "Alice" vs "alice".
+pub fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_ID_LEN
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_' || b == b'-')
+}
+
+pub fn validate_employee_metadata(department: &str, cost_center: &str) -> Result<()> {
+    require!(
+        department.len() <= MAX_EMPLOYEE_METADATA_LEN
+            && cost_center.len() <= MAX_EMPLOYEE_METADATA_LEN,
+        ErrorCode::InvalidEmployeeMetadata
+    );
+    Ok(())
+}
+
+#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct DeductionItem {
+    #[max_len(32)]
+    pub label: String,
+    pub amount: u64,
+}
+
+#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct DepositSplit {
+    pub wallet: Pubkey, // token account of the payroll mint
+    pub bps: u16, // share of net pay; all splits sum to 10000
+}
+
+/// Per-split share of `net_pay`, floored, with the rounding remainder going to the last
+/// split so the amounts always add up to `net_pay`.
+pub fn split_amounts(splits: &[DepositSplit], net_pay: u64) -> Result<Vec<u64>> {
+    let mut amounts = Vec::with_capacity(splits.len());
+    let mut remaining = net_pay;
+    for (i, split) in splits.iter().enumerate() {
+        let amount = if i + 1 == splits.len() {
+            remaining
+        } else {
+            ((net_pay as u128 * split.bps as u128) / 10000) as u64
+        };
+        remaining = remaining.checked_sub(amount).ok_or(ErrorCode::MathOverflow)?;
+        amounts.push(amount);
+    }
+    Ok(amounts)
+}
+
+/// Checks that `wallets` are exactly the split token accounts, in order, for `mint`.
+pub fn check_split_wallets<'info>(
+    splits: &[DepositSplit],
+    wallets: &'info [AccountInfo<'info>],
+    mint: &Pubkey,
+) -> Result<()> {
+    require!(wallets.len() == splits.len(), ErrorCode::InvalidDepositSplits);
+    for (split, wallet) in splits.iter().zip(wallets.iter()) {
+        require_keys_eq!(wallet.key(), split.wallet, ErrorCode::InvalidDepositSplits);
+        let token_account = InterfaceAccount::<TokenAccount>::try_from(wallet)?;
+        require_keys_eq!(token_account.mint, *mint, ErrorCode::MintMismatch);
+    }
+    Ok(())
+}
+
+/// Plaintext pay figures for a single period, shared by payment and preview paths.
+pub struct PayBreakdown {
+    pub gross: u64,
+    pub tax: u64,
+    pub deductions: u64, // fixed plus percentage
+    pub percentage_deduction: u64,
+    pub garnishment: u64,
+    pub net: u64,
+}
+
+impl PayBreakdown {
+    pub fn compute(payroll: &Payroll, employee: &Employee, gross: u64) -> Result<Self> {
+        // Only pay above the allowance is taxed, matching the circuits. Contractors
+        // handle their own taxes, so nothing is withheld from them
+        let taxable = if employee.classification.withholds_tax() {
+            gross.saturating_sub(payroll.tax_free_allowance)
+        } else {
+            0
+        };
+        let tax = payroll
+            .rounding_mode
+            .apply(taxable as u128 * payroll.tax_rate as u128, 10000);
+        // tax_rate is not range-checked at initialization, so never let a cast truncate it
+        require!(tax <= gross as u128, ErrorCode::TaxExceedsSalary);
+        let tax = tax as u64;
+        // Floored like the circuit, so both paths deduct the same amount
+        let percentage_deduction = ((gross as u128 * employee.deduction_bps as u128) / 10000) as u64;
+        let deductions = employee
+            .total_deductions()?
+            .checked_add(percentage_deduction)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(deductions <= gross, ErrorCode::DeductionsExceedGross);
+        let net_before_garnishment = gross
+            .checked_sub(tax)
+            .and_then(|net| net.checked_sub(deductions))
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        // Garnishment is limited to a share of disposable (post-tax, post-deduction) pay
+        let garnishment_cap = ((net_before_garnishment as u128
+            * employee.garnishment_bps as u128)
+            / 10000) as u64;
+        let garnishment = employee.garnishment_amount.min(garnishment_cap);
+        let net = net_before_garnishment - garnishment; // garnishment <= net_before_garnishment
+
+        Ok(Self {
+            gross,
+            tax,
+            deductions,
+            percentage_deduction,
+            garnishment,
+            net,
+        })
+    }
+}
+
+/// Gross pay for one period in token units, converting USD salaries at the oracle price.
+pub fn resolve_gross_salary(
+    payroll: &Payroll,
+    employee: &Employee,
+    price_update: Option<&UncheckedAccount>,
+    now: i64,
+) -> Result<u64> {
+    if !employee.salary_is_usd {
+        return Ok(employee.salary_amount);
+    }
+
+    let oracle = payroll.oracle.ok_or(ErrorCode::OracleNotConfigured)?;
+    let price_update = price_update.ok_or(ErrorCode::OracleNotConfigured)?;
+    require_keys_eq!(price_update.key(), oracle, ErrorCode::InvalidPriceAccount);
+
+    let update = PythPriceUpdate::load(&price_update.to_account_info())?;
+    let message = update.price_message;
+    require!(
+        message.feed_id == payroll.oracle_feed_id,
+        ErrorCode::InvalidPriceAccount
+    );
+    require!(message.price > 0, ErrorCode::InvalidPrice);
+    require!(
+        now.saturating_sub(message.publish_time) <= payroll.max_price_age as i64,
+        ErrorCode::StalePrice
+    );
+    require!(
+        message.conf as u128 * 10000 <= message.price as u128 * payroll.max_conf_bps as u128,
+        ErrorCode::PriceConfidenceTooWide
+    );
+
+    usd_to_token_amount(
+        employee.salary_amount,
+        message.price,
+        message.exponent,
+        employee.salary_decimals,
+    )
+}
+
+/// Converts micro-USD to raw token units at `price * 10^exponent` USD per whole token:
+/// `tokens = usd * 10^decimals / (price * 10^(USD_DECIMALS + exponent))`.
+pub fn usd_to_token_amount(
+    usd_amount: u64,
+    price: i64,
+    exponent: i32,
+    token_decimals: u8,
+) -> Result<u64> {
+    require!(price > 0, ErrorCode::InvalidPrice);
+
+    let pow10 = |exp: u32| 10u128.checked_pow(exp).ok_or(ErrorCode::MathOverflow);
+    let mut numerator = (usd_amount as u128)
+        .checked_mul(pow10(token_decimals as u32)?)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let mut denominator = price as u128;
+
+    let scale = USD_DECIMALS as i32 + exponent;
+    if scale >= 0 {
+        denominator = denominator
+            .checked_mul(pow10(scale as u32)?)
+            .ok_or(ErrorCode::MathOverflow)?;
+    } else {
+        numerator = numerator
+            .checked_mul(pow10(scale.unsigned_abs())?)
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+
+    u64::try_from(numerator / denominator).map_err(|_| ErrorCode::MathOverflow.into())
+}
+
+/// Mirror of the Pyth receiver `PriceUpdateV2` account layout, so the program
+/// can read price updates without depending on the full SDK.
+#[derive(AnchorDeserialize)]
+pub struct PythPriceUpdate {
+    pub write_authority: Pubkey,
+    pub verification_level: PythVerificationLevel,
+    pub price_message: PythPriceFeedMessage,
+    pub posted_slot: u64,
+}
+
+#[derive(AnchorDeserialize)]
+pub enum PythVerificationLevel {
+    Partial { num_signatures: u8 },
+    Full,
+}
+
+#[derive(AnchorDeserialize)]
+pub struct PythPriceFeedMessage {
+    pub feed_id: [u8; 32],
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+    pub prev_publish_time: i64,
+    pub ema_price: i64,
+    pub ema_conf: u64,
+}
+
+impl PythPriceUpdate {
+    /// sha256("account:PriceUpdateV2")[..8]
+    pub const DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+
+    pub fn load(info: &AccountInfo) -> Result<Self> {
+        require_keys_eq!(
+            *info.owner,
+            PYTH_RECEIVER_PROGRAM_ID,
+            ErrorCode::InvalidPriceAccount
+        );
+
+        let data = info.try_borrow_data()?;
+        require!(
+            data.len() > 8 && data[..8] == Self::DISCRIMINATOR,
+            ErrorCode::InvalidPriceAccount
+        );
+
+        let mut payload = &data[8..];
+        Self::deserialize(&mut payload).map_err(|_| error!(ErrorCode::InvalidPriceAccount))
+    }
+}
+
+/// Moves `amount` from `from` into the payroll vault and returns what the vault
+/// actually received.
+pub fn deposit_to_vault<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    vault: &mut InterfaceAccount<'info, TokenAccount>,
+    authority: &Signer<'info>,
+    amount: u64,
+) -> Result<u64> {
+    let balance_before = vault.amount;
+    token_interface::transfer_checked(
+        CpiContext::new(
+            token_program.to_account_info(),
+            TransferChecked {
+                from: from.to_account_info(),
+                mint: mint.to_account_info(),
+                to: vault.to_account_info(),
+                authority: authority.to_account_info(),
+            },
+        ),
+        amount,
+        mint.decimals,
+    )?;
+
+    // Token-2022 transfer-fee mints withhold part of the amount in transit,
+    // so only credit what actually landed in the vault
+    vault.reload()?;
+    let received = vault
+        .amount
+        .checked_sub(balance_before)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(received)
+}
+
+/// Pulls `amount` from a token account that delegated to the vault PDA, returning what
+/// landed in the vault.
+pub fn pull_from_funding_source<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    vault: &mut InterfaceAccount<'info, TokenAccount>,
+    payroll_key: Pubkey,
+    vault_bump: u8,
+    amount: u64,
+) -> Result<u64> {
+    let seeds = &[b"payroll_vault", payroll_key.as_ref(), &[vault_bump]];
+    let signer = &[&seeds[..]];
+    let balance_before = vault.amount;
+
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            TransferChecked {
+                from: from.to_account_info(),
+                mint: mint.to_account_info(),
+                to: vault.to_account_info(),
+                authority: vault.to_account_info(),
+            },
+            signer,
+        ),
+        amount,
+        mint.decimals,
+    )?;
+
+    vault.reload()?;
+    let received = vault
+        .amount
+        .checked_sub(balance_before)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(received)
+}
+
+/// Moves `amount` out of the payroll vault, signed by the vault PDA.
+/// Works for both SPL Token and Token-2022 mints; with a transfer-fee mint the
+/// recipient receives `amount` minus the fee while the vault is debited `amount`.
+pub fn transfer_from_vault<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    to: AccountInfo<'info>,
+    payroll_key: Pubkey,
+    vault_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    let seeds = &[b"payroll_vault", payroll_key.as_ref(), &[vault_bump]];
+    let signer = &[&seeds[..]];
+
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            TransferChecked {
+                from: vault.to_account_info(),
+                mint: mint.to_account_info(),
+                to,
+                authority: vault.to_account_info(),
+            },
+            signer,
+        ),
+        amount,
+        mint.decimals,
+    )
+}
+
+/// Like `transfer_from_vault`, but signed by `vault_authority` when the vault is owned
+/// by an external custody key.
+pub fn pay_from_vault<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    to: AccountInfo<'info>,
+    payroll: &Account<'info, Payroll>,
+    vault_authority: Option<&Signer<'info>>,
+    amount: u64,
+) -> Result<()> {
+    let Some(vault_authority) = vault_authority else {
+        return transfer_from_vault(
+            token_program,
+            vault,
+            mint,
+            to,
+            payroll.key(),
+            payroll.vault_bump,
+            amount,
+        );
+    };
+
+    token_interface::transfer_checked(
+        CpiContext::new(
+            token_program.to_account_info(),
+            TransferChecked {
+                from: vault.to_account_info(),
+                mint: mint.to_account_info(),
+                to,
+                authority: vault_authority.to_account_info(),
+            },
+        ),
+        amount,
+        mint.decimals,
+    )
+}
+
+/// Body of process_payment and force_payment. `forced` pays one period when none is due,
+/// leaving `last_payment` alone and a pending skip for the next scheduled run.
+pub fn pay_employee<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ProcessPayment<'info>>,
+    idempotency_key: [u8; 16],
+    memo: String,
+    adjusted_pay_ts: Option<i64>,
+    forced: bool,
+) -> Result<()> {
+    let sequence = ctx.accounts.payroll.next_sequence()?;
+    let payroll = &mut ctx.accounts.payroll;
+    let employee = &mut ctx.accounts.employee;
+
+    require!(memo.len() <= MAX_PAYMENT_MEMO_LEN, ErrorCode::InvalidMemo);
+    require!(!payroll.frozen, ErrorCode::PayrollFrozen);
+    require!(payroll.is_active, ErrorCode::PayrollInactive);
+    require!(employee.is_active, ErrorCode::EmployeeInactive);
+    require!(
+        employee.last_idempotency_key != idempotency_key,
+        ErrorCode::DuplicatePayment
+    );
+
+    // Check if payment is due based on frequency
+    let current_time = Clock::get()?.unix_timestamp;
+
+    // Promote a time-locked wallet change once its delay has passed, then pay only to it
+    employee.apply_pending_wallet(current_time);
+    let payout_wallet = if payroll.native {
+        ctx.accounts.native_wallet.as_ref().map(|wallet| wallet.key())
+    } else {
+        ctx.accounts.employee_wallet.as_ref().map(|wallet| wallet.key())
+    };
+    require_keys_eq!(
+        payout_wallet.ok_or(ErrorCode::EmployeeWalletMismatch)?,
+        employee.wallet,
+        ErrorCode::EmployeeWalletMismatch
+    );
+
+    // Paused time does not count toward the next payment
+    let shift_seconds = employee.apply_pause_adjustment(payroll.total_paused_seconds)?;
+    if shift_seconds > 0 {
+        emit_verbose!(payroll, ScheduleAdjustedForPause {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            shift_seconds,
+            last_payment: employee.last_payment,
+        });
+    }
+
+    // Nothing is paid during probation. Afterwards the schedule runs from onboarding, so
+    // the first run back-pays the probation periods like any missed ones. Accounts
+    // without an onboarding time are treated as past probation.
+    if employee.last_payment == 0 && payroll.min_tenure_seconds > 0 && employee.created_at > 0 {
+        require!(
+            current_time.saturating_sub(employee.created_at) >= payroll.min_tenure_seconds,
+            ErrorCode::TenureNotMet
+        );
+        employee.last_payment = employee.created_at;
+    }
+
+    // An unset clock would record this payment as "never paid", and one behind
+    // last_payment would read as merely not due yet. A first payment always passes.
+    // A business-day shift records the due date it paid against, up to the shift ahead.
+    let schedule_time = payroll.schedule_time(current_time)?;
+    let clock_slack = if payroll.business_day_shift {
+        payroll.max_business_day_shift
+    } else {
+        0
+    };
+    require!(
+        current_time > 0 && schedule_time.saturating_add(clock_slack) >= employee.last_payment,
+        ErrorCode::ClockRegression
+    );
+
+    // A shifted payment is evaluated at, and records, the due date it stands in for
+    let (schedule_time, paid_at) = match adjusted_pay_ts {
+        Some(adjusted_pay_ts) => {
+            require!(payroll.business_day_shift, ErrorCode::BusinessDayShiftDisabled);
+            let due_ts = employee.next_payment_ts(payroll.pay_day_anchor)?;
+            require!(
+                adjusted_pay_ts <= due_ts
+                    && due_ts - adjusted_pay_ts <= payroll.max_business_day_shift,
+                ErrorCode::InvalidAdjustedPayTs
+            );
+            require!(current_time >= adjusted_pay_ts, ErrorCode::PaymentTooSoon);
+            (schedule_time.max(due_ts), current_time.max(due_ts))
+        }
+        None => (schedule_time, current_time),
+    };
+
+    // Apply a scheduled raise before computing this run's pay
+    if let Some(new_salary) = employee.pending_salary {
+        if current_time >= employee.pending_salary_effective {
+            let old_salary = employee.salary_amount;
+            employee.salary_amount = new_salary;
+            employee.pending_salary = None;
+            employee.pending_salary_effective = 0;
+
+            emit_verbose!(payroll, ScheduledSalaryApplied {
+                payroll_id: payroll.payroll_id.clone(),
+                sequence,
+                employee_id: employee.employee_id.clone(),
+                old_salary,
+                new_salary,
+            });
+        }
+    }
+
+    let periods_due = employee.periods_due(schedule_time, payroll.pay_day_anchor)?;
+    // Paying ahead is limited to one period, so a pending skip blocks another advance
+    let paid_ahead = periods_due < 1;
+    require!(
+        !paid_ahead || (forced && !employee.skip_next),
+        ErrorCode::PaymentTooSoon
+    );
+    let periods_due = periods_due.max(1);
+
+    // A skipped run consumes exactly one period and pays nothing
+    if employee.skip_next && !forced {
+        employee.skip_next = false;
+        employee.last_payment = if employee.last_payment == 0
+            || (!payroll.strict_schedule && periods_due == 1)
+        {
+            paid_at
+        } else {
+            employee.advance_schedule(1, payroll.pay_day_anchor)?
+        };
+        employee.last_idempotency_key = idempotency_key;
+
+        emit!(PaymentSkipped {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            last_payment: employee.last_payment,
+        });
+
+        return Ok(());
+    }
+
+    // Each confidential result authorizes exactly one payment
+    if payroll.require_confidential {
+        require!(employee.net_pay_result_fresh, ErrorCode::ConfidentialRequired);
+        require!(employee.net_pay_validated, ErrorCode::NetPayNotValidated);
+        employee.net_pay_result_fresh = false;
+        employee.net_pay_validated = false;
+    }
+    // A fresh calculation opens a review window before anything is disbursed
+    let settles_at = employee
+        .net_pay_computed_at
+        .checked_add(payroll.settlement_delay_seconds)
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(current_time >= settles_at, ErrorCode::SettlementPending);
+
+    // Catch up on missed periods, but never more than the configured cap per call
+    let periods_paid = periods_due.min(payroll.max_catchup_periods as i64) as u64;
+
+    // For now, use plain calculation - in full confidential version, this would use the encrypted result
+    let gross = resolve_gross_salary(
+        payroll,
+        employee,
+        ctx.accounts.price_update.as_ref(),
+        current_time,
+    )?;
+    let breakdown = PayBreakdown::compute(payroll, employee, gross)?;
+    let tax_amount = breakdown.tax;
+    let net_pay = breakdown
+        .net
+        .checked_mul(periods_paid)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    // Accrued overtime is paid once on top of the periods, withheld at the payroll rate
+    // without a second allowance
+    let overtime = employee.overtime_pay;
+    let overtime_tax = payroll.overtime_tax_for(employee)?;
+    let net_pay = net_pay
+        .checked_add(overtime - overtime_tax)
+        .ok_or(ErrorCode::MathOverflow)?;
+    employee.overtime_pay = 0;
+
+    // Recover outstanding overpayments, never dipping below the protected minimum
+    let clawback = payroll.clawback_for(employee.clawback_balance, net_pay, periods_paid)?;
+    let net_pay = net_pay
+        .checked_sub(clawback)
+        .ok_or(ErrorCode::MathOverflow)?;
+    if clawback > 0 {
+        employee.clawback_balance = employee
+            .clawback_balance
+            .checked_sub(clawback)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit_verbose!(payroll, ClawbackApplied {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            amount: clawback,
+            remaining: employee.clawback_balance,
+        });
+    }
+
+    // Hold back dust until the accumulated balance reaches the payroll minimum
+    let payable = net_pay
+        .checked_add(employee.outstanding_balance)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let deferred = payable < payroll.min_payment;
+    let net_pay = if deferred { 0 } else { payable };
+    employee.outstanding_balance = if deferred { payable } else { 0 };
+    if deferred {
+        emit_verbose!(payroll, PaymentDeferred {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            accumulated: payable,
+        });
+    }
+
+    // The platform fee either comes out of net pay or is charged to the employer on top
+    let platform_fee = payroll.platform_fee_for(net_pay)?;
+    let net_pay = if payroll.platform_fee_from_employee {
+        net_pay
+            .checked_sub(platform_fee)
+            .ok_or(ErrorCode::MathOverflow)?
+    } else {
+        net_pay
+    };
+
+    // Whole-token rounding; a deferred payment leaves the carried remainder untouched
+    let net_pay = if deferred {
+        net_pay
+    } else {
+        let (paid, carried) = payroll.round_net_pay(net_pay, employee.carry_forward)?;
+        employee.carry_forward = carried;
+        paid
+    };
+
+    // Employer-side payroll tax is paid on top of gross, so it never reduces net pay
+    let employer_tax = payroll
+        .employer_tax_for(breakdown.gross, periods_paid)?
+        .checked_add(payroll.employer_tax_for(overtime, 1)?)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let total_outflow = net_pay
+        .checked_add(employer_tax)
+        .and_then(|total| total.checked_add(platform_fee))
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    // Cover a shortfall from the funding source, which must have delegated at least
+    // that much to the vault PDA. Nothing is pulled unless it covers the whole gap,
+    // since a partial top-up would still fail the payment
+    if payroll.total_funds < total_outflow && payroll.funding_source.is_some() {
+        let funding_source = ctx
+            .accounts
+            .funding_source
+            .as_ref()
+            .ok_or(ErrorCode::InvalidFundingSource)?;
+        let payroll_vault = ctx
+            .accounts
+            .payroll_vault
+            .as_mut()
+            .ok_or(ErrorCode::VaultMismatch)?;
+        let shortfall = total_outflow - payroll.total_funds;
+        let available = if Option::<Pubkey>::from(funding_source.delegate) == Some(payroll_vault.key()) {
+            funding_source.amount.min(funding_source.delegated_amount)
+        } else {
+            0
+        };
+        require!(available >= shortfall, ErrorCode::FundingSourceInsufficient);
+
+        let received = pull_from_funding_source(
+            &ctx.accounts.token_program,
+            funding_source,
+            &ctx.accounts.payment_token,
+            payroll_vault,
+            payroll.key(),
+            payroll.vault_bump,
+            shortfall,
+        )?;
+        payroll.total_funds = payroll
+            .total_funds
+            .checked_add(received)
+            .ok_or(ErrorCode::MathOverflow)?;
+        payroll.lifetime_deposited = payroll
+            .lifetime_deposited
+            .checked_add(received)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(AutoTopUp {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            funding_source: funding_source.key(),
+            amount: received,
+            total_funds: payroll.total_funds,
+        });
+    }
+
+    require!(payroll.total_funds >= total_outflow, ErrorCode::InsufficientFunds);
+
+    // Large payments need a matching approval from the second signer; any approval
+    // passed in is consumed since it is bound to this payment's sequence number.
+    if let Some(approval) = ctx.accounts.approval.as_ref() {
+        require!(approval.amount >= net_pay, ErrorCode::ApprovalAmountTooLow);
+        let approver = ctx
+            .accounts
+            .approver
+            .as_ref()
+            .ok_or(ErrorCode::ApprovalRequired)?;
+        require_keys_eq!(approver.key(), approval.approver, ErrorCode::Unauthorized);
+        approval.close(approver.to_account_info())?;
+    } else {
+        require!(
+            payroll.approval_threshold == 0 || net_pay <= payroll.approval_threshold,
+            ErrorCode::ApprovalRequired
+        );
+    }
+
+    payroll.record_outflow(total_outflow, current_time)?;
+
+    // Transfer using the vault PDA (or the external vault authority) as signer
+    let payroll_id = payroll.payroll_id.clone();
+    if payroll.native {
+        require!(
+            employer_tax == 0 && platform_fee == 0 && employee.deposit_splits.is_empty(),
+            ErrorCode::NativeUnsupported
+        );
+        let native_vault = ctx
+            .accounts
+            .native_vault
+            .as_ref()
+            .ok_or(ErrorCode::VaultMismatch)?;
+        let system_program = ctx
+            .accounts
+            .system_program
+            .as_ref()
+            .ok_or(ErrorCode::VaultMismatch)?;
+        if net_pay > 0 {
+            transfer_native_from_vault(
+                system_program,
+                native_vault,
+                ctx.accounts
+                    .native_wallet
+                    .as_ref()
+                    .ok_or(ErrorCode::EmployeeWalletMismatch)?
+                    .to_account_info(),
+                payroll.key(),
+                payroll.vault_bump,
+                net_pay,
+            )?;
+        }
+    } else {
+        let payroll_vault = ctx
+            .accounts
+            .payroll_vault
+            .as_ref()
+            .ok_or(ErrorCode::VaultMismatch)?;
+        let employee_wallet = ctx
+            .accounts
+            .employee_wallet
+            .as_ref()
+            .ok_or(ErrorCode::EmployeeWalletMismatch)?;
+        // An external custody key must co-sign; otherwise the vault PDA signs
+        let vault_authority = match payroll.vault_authority {
+            Some(expected) => {
+                let signer = ctx
+                    .accounts
+                    .vault_authority
+                    .as_ref()
+                    .ok_or(ErrorCode::InvalidVaultAuthority)?;
+                require_keys_eq!(signer.key(), expected, ErrorCode::InvalidVaultAuthority);
+                Some(signer)
+            }
+            None => None,
+        };
+        if net_pay > 0 && employee.deposit_splits.is_empty() {
+            pay_from_vault(
+                &ctx.accounts.token_program,
+                payroll_vault,
+                &ctx.accounts.payment_token,
+                employee_wallet.to_account_info(),
+                payroll,
+                vault_authority,
+                net_pay,
+            )?;
+        } else if net_pay > 0 {
+            // Split wallets arrive in remaining_accounts, in the order they are stored
+            check_split_wallets(
+                &employee.deposit_splits,
+                ctx.remaining_accounts,
+                &payroll.payment_token,
+            )?;
+            let amounts = split_amounts(&employee.deposit_splits, net_pay)?;
+            for ((split, wallet), amount) in employee
+                .deposit_splits
+                .iter()
+                .zip(ctx.remaining_accounts.iter())
+                .zip(amounts)
+            {
+                if amount > 0 {
+                    pay_from_vault(
+                        &ctx.accounts.token_program,
+                        payroll_vault,
+                        &ctx.accounts.payment_token,
+                        wallet.clone(),
+                        payroll,
+                        vault_authority,
+                        amount,
+                    )?;
+                }
+                emit_verbose!(payroll, DepositSplitPaid {
+                    payroll_id: payroll_id.clone(),
+                    sequence,
+                    employee_id: employee.employee_id.clone(),
+                    wallet: split.wallet,
+                    bps: split.bps,
+                    amount,
+                });
+            }
+        }
+        if employer_tax > 0 {
+            let tax_account = ctx
+                .accounts
+                .tax_account
+                .as_ref()
+                .ok_or(ErrorCode::InvalidTaxAccount)?;
+            pay_from_vault(
+                &ctx.accounts.token_program,
+                payroll_vault,
+                &ctx.accounts.payment_token,
+                tax_account.to_account_info(),
+                payroll,
+                vault_authority,
+                employer_tax,
+            )?;
+        }
+        if platform_fee > 0 {
+            let platform_account = ctx
+                .accounts
+                .platform_account
+                .as_ref()
+                .ok_or(ErrorCode::InvalidPlatformAccount)?;
+            pay_from_vault(
+                &ctx.accounts.token_program,
+                payroll_vault,
+                &ctx.accounts.payment_token,
+                platform_account.to_account_info(),
+                payroll,
+                vault_authority,
+                platform_fee,
+            )?;
+        }
+    }
+    if !memo.is_empty() {
+        let memo_program = ctx
+            .accounts
+            .memo_program
+            .as_ref()
+            .ok_or(ErrorCode::InvalidMemo)?;
+        write_memo(memo_program, &memo)?;
+    }
+
+    payroll.total_funds = payroll
+        .total_funds
+        .checked_sub(total_outflow)
+        .ok_or(ErrorCode::MathOverflow)?;
+    payroll.total_paid_in_period = payroll
+        .total_paid_in_period
+        .checked_add(net_pay)
+        .ok_or(ErrorCode::MathOverflow)?;
+    payroll.lifetime_paid = payroll
+        .lifetime_paid
+        .checked_add(net_pay)
+        .ok_or(ErrorCode::MathOverflow)?;
+    // Strict schedules advance by whole intervals so payments never drift later and
+    // remaining missed periods stay payable; otherwise the cadence restarts from now,
+    // unless the catch-up cap left periods owed. A run paid ahead keeps the schedule
+    // where it is and skips the next due run in its place
+    if paid_ahead {
+        employee.skip_next = true;
+    } else {
+        employee.last_payment = if employee.last_payment == 0
+            || (!payroll.strict_schedule && periods_paid as i64 == periods_due)
+        {
+            paid_at
+        } else {
+            employee.advance_schedule(periods_paid, payroll.pay_day_anchor)?
+        };
+    }
+    // This run opens a new period; exceeding the cap reverts the transfers above too
+    employee.record_period_pay(net_pay, periods_paid)?;
+    employee.consecutive_funding_failures = 0;
+    let garnishment = breakdown
+        .garnishment
+        .checked_mul(periods_paid)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    emit!(PaymentProcessed {
+        payroll_id,
+        sequence,
+        employee_id: employee.employee_id.clone(),
+        gross_salary: breakdown.gross,
+        tax_amount,
+        deductions: breakdown.deductions,
+        percentage_deduction: breakdown.percentage_deduction,
+        net_pay,
+        periods_paid,
+        deduction_items: employee.deduction_items.clone(),
+        garnishment,
+        clawback,
+        department: employee.department.clone(),
+        cost_center: employee.cost_center.clone(),
+        employer_tax,
+        platform_fee,
+        memo,
+        carry_forward: employee.carry_forward,
+        overtime,
+        overtime_tax,
+    });
+    if forced {
+        emit!(ForcedPaymentProcessed {
+            payroll_id: payroll.payroll_id.clone(),
+            sequence,
+            employee_id: employee.employee_id.clone(),
+            authority: payroll.authority,
+            net_pay,
+            periods_paid,
+            last_payment: employee.last_payment,
+        });
+    }
+
+    if let Some(pay_stub) = ctx.accounts.pay_stub.as_mut() {
+        pay_stub.payroll = payroll.key();
+        pay_stub.employee = employee.key();
+        pay_stub.sequence = employee.payment_count;
+        pay_stub.period = payroll.current_period;
+        pay_stub.gross = breakdown.gross;
+        pay_stub.tax = breakdown.tax;
+        pay_stub.deductions = breakdown.deductions;
+        pay_stub.garnishment = breakdown.garnishment;
+        pay_stub.net = net_pay;
+        pay_stub.periods_paid = periods_paid;
+        pay_stub.timestamp = current_time;
+        pay_stub.clawback = clawback;
+    } else {
+        require!(!payroll.pay_stubs_enabled, ErrorCode::PayStubRequired);
+    }
+    if let Some(pay_history) = ctx.accounts.pay_history.as_mut() {
+        pay_history.push(PaymentRecord {
+            ts: current_time,
+            gross: breakdown
+                .gross
+                .checked_mul(periods_paid)
+                .and_then(|gross| gross.checked_add(overtime))
+                .ok_or(ErrorCode::MathOverflow)?,
+            tax: tax_amount
+                .checked_mul(periods_paid)
+                .and_then(|tax| tax.checked_add(overtime_tax))
+                .ok_or(ErrorCode::MathOverflow)?,
+            net: net_pay,
+        });
+    } else {
+        require!(!employee.pay_history_enabled, ErrorCode::PayHistoryRequired);
+    }
+    employee.payment_count = employee
+        .payment_count
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    employee.last_idempotency_key = idempotency_key;
+
+    payroll.warn_if_below_low_water();
+
+    Ok(())
+}
+
+/// Queues `calculate_net_pay` from the inputs stored on the employee.
+pub fn queue_net_pay<'info>(
+    ctx: Context<'_, '_, '_, 'info, CalculateNetPay<'info>>,
+    computation_offset: u64,
+    sequence: u64,
+) -> Result<()> {
+    let payroll = &ctx.accounts.payroll;
+    require!(
+        payroll.encrypted_tax_rate != [0u8; 32],
+        ErrorCode::EncryptedTaxRateNotSet
+    );
+    let encrypted_tax_rate = payroll.encrypted_tax_rate;
+    let tax_rate_pubkey = payroll.tax_rate_pubkey;
+    let tax_rate_nonce = payroll.tax_rate_nonce;
+    let rounding_mode = payroll.rounding_mode;
+    let tax_free_allowance = payroll.tax_free_allowance;
+
+    ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+    // Remember which request is in flight so the callback can report it
+    let employee = &mut ctx.accounts.employee;
+    employee.pending_computation_offset = Some(computation_offset);
+
+    emit_verbose!(payroll, NetPayComputationQueued {
+        payroll_id: ctx.accounts.payroll.payroll_id.clone(),
+        sequence,
+        employee_id: employee.employee_id.clone(),
+        computation_offset,
+    });
+
+    let mut args = vec![
+        Argument::ArcisPubkey(employee.net_pay_input_pub_key),
+        Argument::PlaintextU128(employee.net_pay_input_nonce),
+        Argument::EncryptedU64(employee.net_pay_input_salary),
+    ];
+    args.extend(
+        employee
+            .net_pay_input_deductions
+            .iter()
+            .map(|deduction| Argument::EncryptedU64(*deduction)),
+    );
+    args.extend([
+        Argument::ArcisPubkey(tax_rate_pubkey),
+        Argument::PlaintextU128(tax_rate_nonce),
+        Argument::EncryptedU16(encrypted_tax_rate),
+        Argument::PlaintextU8(rounding_mode as u8),
+        Argument::PlaintextU16(employee.deduction_bps),
+        Argument::PlaintextU64(tax_free_allowance),
+        Argument::PlaintextBool(employee.classification.withholds_tax()),
+    ]);
+
+    queue_computation(
+        ctx.accounts,
+        computation_offset,
+        args,
+        None,
+        vec![CalculateNetPayCallback::callback_ix(&[
+            CallbackAccount {
+                pubkey: ctx.accounts.employee.key(),
+                is_writable: true,
+            },
+            CallbackAccount {
+                pubkey: ctx.accounts.payroll.key(),
+                is_writable: true,
+            },
+        ])],
+    )?;
+
+    Ok(())
+}
+
+/// Lamport counterpart of `transfer_from_vault` for native payrolls.
+pub fn transfer_native_from_vault<'info>(
+    system_program: &Program<'info, System>,
+    vault: &SystemAccount<'info>,
+    to: AccountInfo<'info>,
+    payroll_key: Pubkey,
+    vault_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    let seeds = &[b"native_vault", payroll_key.as_ref(), &[vault_bump]];
+    let signer = &[&seeds[..]];
+
+    system_program::transfer(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::Transfer {
+                from: vault.to_account_info(),
+                to,
+            },
+            signer,
+        ),
+        amount,
+    )
+}
+
+/// Records `memo` in the transaction through the Memo program; it takes no accounts.
+pub fn write_memo<'info>(memo_program: &UncheckedAccount<'info>, memo: &str) -> Result<()> {
+    let ix = anchor_lang::solana_program::instruction::Instruction {
+        program_id: MEMO_PROGRAM_ID,
+        accounts: vec![],
+        data: memo.as_bytes().to_vec(),
+    };
+    anchor_lang::solana_program::program::invoke(&ix, &[memo_program.to_account_info()])?;
+    Ok(())
+}
+
+#[repr(u8)]
+#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PaymentFrequency {
+    Weekly,
+    BiWeekly,
+    Monthly,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PaymentFailureReason {
+    PayrollFrozen,
+    PayrollInactive,
+    EmployeeInactive,
+    TooSoon,
+    InsufficientFunds,
+}
+
+/// How `numerator / denominator` is rounded when computing withholding:
+/// - `Floor`: `n / d`
+/// - `Ceil`: `(n + d - 1) / d`
+/// - `Round`: `(n + d / 2) / d` (half-up)
+///
+/// The discriminants are passed to the `calculate_net_pay` circuit as a plaintext `u8`.
+#[repr(u8)]
+#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RoundingMode {
+    Floor,
+    Ceil,
+    Round,
+}
+
+impl RoundingMode {
+    pub fn apply(&self, numerator: u128, denominator: u128) -> u128 {
+        match self {
+            RoundingMode::Floor => numerator / denominator,
+            RoundingMode::Ceil => numerator.div_ceil(denominator),
+            RoundingMode::Round => (numerator + denominator / 2) / denominator,
+        }
+    }
+}
+
+/// Worker class, which decides withholding and overtime. `Exempt` is first so employees
+/// added before classification existed keep being taxed without overtime.
+#[repr(u8)]
+#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EmployeeClass {
+    Exempt,
+    NonExempt,
+    Contractor,
+}
+
+impl EmployeeClass {
+    pub fn withholds_tax(&self) -> bool {
+        !matches!(self, EmployeeClass::Contractor)
+    }
+
+    pub fn earns_overtime(&self) -> bool {
+        matches!(self, EmployeeClass::NonExempt)
+    }
+}
+
+impl PaymentFrequency {
+    pub fn interval_seconds(&self) -> i64 {
+        match self {
+            PaymentFrequency::Weekly => 7 * 24 * 60 * 60,    // 7 days
+            PaymentFrequency::BiWeekly => 14 * 24 * 60 * 60, // 14 days
+            PaymentFrequency::Monthly => 30 * 24 * 60 * 60,  // 30 days
+        }
+    }
+}
+
+#[event]
+pub struct ProgramConfigInitialized {
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct GlobalPauseUpdated {
+    pub admin: Pubkey,
+    pub paused: bool,
+}
+
+#[event]
+pub struct PayrollInitialized {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub authority: Pubkey,
+    pub tax_rate: u16,
+    pub default_frequency: PaymentFrequency,
+}
+
+#[event]
+pub struct EmployeeExported {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee: Employee,
+}
+
+#[event]
+pub struct EmployeeImported {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub created: bool, // false when an identical employee already existed
+}
+
+#[event]
+pub struct EmployeeAdded {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub salary_amount: u64,
+    pub deductions: u64,
+    pub payment_frequency: PaymentFrequency, // resolved against payroll.default_frequency
+    pub decimals: u8,
+    pub department: String,
+    pub cost_center: String,
+    pub created_at: i64,
+    pub classification: EmployeeClass,
+}
+
+#[event]
+pub struct EmployeesAdded {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub count: u64,
+}
+
+#[event]
+pub struct NetPayComputationQueued {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub computation_offset: u64,
+}
+
+#[event]
+pub struct NetPayRetried {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub previous_offset: Option<u64>,
+    pub computation_offset: u64,
+}
+
+#[event]
+pub struct NetPayCalculated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub net_pay: [u8; 32],
+    pub nonce: [u8; 16],
+    pub computation_offset: u64,
+    pub employee_id: String,
+}
+
+#[event]
+pub struct NetPayComputationFailed {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub computation_offset: u64,
+    pub employee_id: String,
+}
+
+#[event]
+pub struct TaxWithholdingComputationQueued {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub computation_offset: u64,
+}
+
+#[event]
+pub struct TaxWithholdingCalculated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub tax: [u8; 32],
+    pub nonce: [u8; 16],
+    pub computation_offset: u64,
+    pub employee_id: String,
+}
+
+#[event]
+pub struct TaxWithholdingComputationFailed {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub computation_offset: u64,
+    pub employee_id: String,
+}
+
+#[event]
+pub struct YtdTaxReset {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+}
+
+#[event]
+pub struct YtdTaxComputationQueued {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub computation_offset: u64,
+}
+
+#[event]
+pub struct YtdTaxAccumulated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub ytd_tax: [u8; 32],
+    pub nonce: [u8; 16],
+    pub computation_offset: u64,
+    pub employee_id: String,
+}
+
+#[event]
+pub struct YtdTaxComputationFailed {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub computation_offset: u64,
+    pub employee_id: String,
+}
+
+#[event]
+pub struct NetPayValidationQueued {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub computation_offset: u64,
+}
+
+#[event]
+pub struct NetPayValidated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub computation_offset: u64,
+    pub employee_id: String,
+    pub valid: bool, // net pay <= salary
+}
+
+#[event]
+pub struct NetPayValidationFailed {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub computation_offset: u64,
+    pub employee_id: String,
+}
+
+#[event]
+pub struct TotalNetPayrollQueued {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub computation_offset: u64,
+}
+
+#[event]
+pub struct TotalNetPayrollCalculated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub total_net_pay: [u8; 32],
+    pub nonce: [u8; 16],
+    pub computation_offset: u64,
+}
+
+#[event]
+pub struct TotalNetPayrollFailed {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub computation_offset: u64,
+}
+
+#[event]
+pub struct PaymentProcessed {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub gross_salary: u64,
+    pub tax_amount: u64,
+    pub deductions: u64,
+    pub percentage_deduction: u64, // included in deductions
+    pub net_pay: u64,
+    pub periods_paid: u64,
+    pub deduction_items: Vec<DeductionItem>,
+    pub garnishment: u64,
+    pub clawback: u64,
+    pub department: String,
+    pub cost_center: String,
+    pub employer_tax: u64,
+    pub platform_fee: u64,
+    pub memo: String, // empty when none was attached
+    pub carry_forward: u64, // remainder held back for the next payment by whole-token rounding
+    pub overtime: u64, // gross overtime paid once on top of the periods
+    pub overtime_tax: u64, // withholding on overtime, not included in tax_amount
+}
+
+#[event]
+pub struct SalaryUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub old_salary: u64,
+    pub new_salary: u64,
+}
+
+#[event]
+pub struct SalaryCutApproved {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub old_salary: u64,
+    pub new_salary: u64,
+    pub approved_by: Pubkey,
+}
+
+#[event]
+pub struct CutApprovalUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub required: bool,
+}
+
+#[event]
+pub struct NextPaymentSkipScheduled {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+}
+
+#[event]
+pub struct ForcedPaymentProcessed {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub authority: Pubkey,
+    pub net_pay: u64,
+    pub periods_paid: u64,
+    pub last_payment: i64,
+}
+
+#[event]
+pub struct PaymentSkipped {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub last_payment: i64,
+}
+
+#[event]
+pub struct PaymentDeferred {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub accumulated: u64,
+}
+
+#[event]
+pub struct MinTenureUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub min_tenure_seconds: i64,
+}
+
+#[event]
+pub struct MinPaymentUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub min_payment: u64,
+}
+
+#[event]
+pub struct PaymentAttemptFailed {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub reason: PaymentFailureReason,
+    pub failed_attempts: u32,
+}
+
+#[event]
+pub struct NetPayPreview {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub gross: u64,
+    pub tax: u64,
+    pub deductions: u64,
+    pub percentage_deduction: u64,
+    pub garnishment: u64,
+    pub net: u64,
+}
+
+#[event]
+pub struct NextPayment {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub next_ts: i64,
+}
+
+#[event]
+pub struct PayHistoryInitialized {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+}
+
+#[event]
+pub struct PayHistoryReported {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub records: Vec<PaymentRecord>, // oldest first
+}
+
+#[event]
+pub struct EmployeeTenure {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub created_at: i64,
+    pub tenure_seconds: Option<i64>, // None when created_at is unknown
+}
+
+#[event]
+pub struct PaymentDueIn {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub due_ts: i64, // next pay boundary; 0 when the first payment is due
+    pub seconds_remaining: i64, // 0 once process_payment would accept the run
+}
+
+#[event]
+pub struct VaultVerified {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub vault: Pubkey,
+    pub mint: Pubkey,
+    pub authority: Pubkey, // token account owner
+    pub balance: u64,
+    pub total_funds: u64, // tracked balance, for reconciling against `balance`
+}
+
+#[event]
+pub struct SolvencyReport {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub total_funds: u64,
+    pub total_obligations: u64,
+    pub is_solvent: bool,
+}
+
+#[event]
+pub struct PayrollStats {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_count: u64,
+    pub is_active: bool,
+    pub total_funds: u64,
+    pub current_period: u64,
+    pub total_paid_in_period: u64,
+    pub lifetime_paid: u64,
+    pub lifetime_deposited: u64,
+}
+
+#[event]
+pub struct ComputationFeeEstimate {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub cu_amount: u64,
+    pub cu_price: u64,
+    pub lamports: u64,
+}
+
+#[event]
+pub struct EmployeeCountReconciled {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub old: u64,
+    pub new: u64,
+}
+
+#[event]
+pub struct SalaryChangeScheduled {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub new_salary: u64,
+    pub effective_ts: i64,
+}
+
+#[event]
+pub struct ScheduledSalaryApplied {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub old_salary: u64,
+    pub new_salary: u64,
+}
+
+#[event]
+pub struct DeductionAdded {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub label: String,
+    pub amount: u64,
+}
+
+#[event]
+pub struct DeductionRemoved {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub label: String,
+    pub amount: u64,
+}
+
+#[event]
+pub struct DeductionBpsUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub deduction_bps: u16,
+}
+
+#[event]
+pub struct GarnishmentUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub garnishment_amount: u64,
+    pub garnishment_bps: u16,
+}
+
+#[event]
+pub struct EncryptionPubkeyUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub old_pubkey: [u8; 32],
+    pub new_pubkey: [u8; 32],
+}
+
+#[event]
+pub struct DepositSplitsUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub splits: Vec<DepositSplit>,
+}
+
+#[event]
+pub struct DepositSplitPaid {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub wallet: Pubkey,
+    pub bps: u16,
+    pub amount: u64,
+}
+
+#[event]
+pub struct FundingFailureThresholdUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub funding_failure_threshold: u32,
+}
+
+#[event]
+pub struct EmployeeAutoSuspended {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub consecutive_funding_failures: u32,
+}
+
+#[event]
+pub struct EmployeeReactivated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+}
+
+#[event]
+pub struct PeriodPayCapUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub period_pay_cap: u64,
+}
+
+#[event]
+pub struct PaymentRefunded {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub amount: u64,
+    pub received: u64, // amount credited after any Token-2022 transfer fee
+    pub clawback_balance: u64, // remaining after the refund is applied
+}
+
+#[event]
+pub struct OvertimeRecorded {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub amount: u64,
+    pub overtime_pay: u64,
+}
+
+#[event]
+pub struct ClawbackAdded {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub amount: u64,
+    pub clawback_balance: u64,
+}
+
+#[event]
+pub struct ClawbackApplied {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub amount: u64,
+    pub remaining: u64,
+}
+
+#[event]
+pub struct EmployeeMetadataUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub department: String,
+    pub cost_center: String,
+}
+
+#[event]
+pub struct EmployeeMigrated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub old_size: u64,
+    pub new_size: u64,
+}
+
+#[event]
+pub struct PaymentIntervalOverrideUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub payment_interval_override: Option<i64>,
+}
+
+#[event]
+pub struct PaymentFrequencyUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub old: PaymentFrequency,
+    pub new: PaymentFrequency,
+}
+
+#[event]
+pub struct EmployeeWalletUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub old_wallet: Pubkey,
+    pub new_wallet: Pubkey,
+    pub effective_ts: i64,
+    pub updated_by: Pubkey,
+}
+
+#[event]
+pub struct PaymentApproved {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub approver: Pubkey,
+    pub payment_sequence: u64, // employee.payment_count of the approved payment
+    pub amount: u64,
+}
+
+#[event]
+pub struct CatchupPeriodsUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub max_catchup_periods: u8,
+}
+
+#[event]
+pub struct FunderUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub funder: Option<Pubkey>,
+}
+
+#[event]
+pub struct OperatorUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub operator: Option<Pubkey>,
+}
+
+#[event]
+pub struct LowBalanceThresholdUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub low_balance_threshold: u64,
+}
+
+#[event]
+pub struct LowBalanceWarning {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub total_funds: u64,
+    pub threshold: u64,
+}
+
+#[event]
+pub struct FundingSourceUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub funding_source: Option<Pubkey>,
+}
+
+#[event]
+pub struct AutoTopUp {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub funding_source: Pubkey,
+    pub amount: u64, // credited to the vault, net of any transfer fee
+    pub total_funds: u64,
+}
+
+#[event]
+pub struct FundsDeposited {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub amount: u64,
+    pub received: u64, // amount credited after any Token-2022 transfer fee
+    pub total_funds: u64,
+}
+
+#[event]
+pub struct DustSwept {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub amount: u64,
+}
+
+#[event]
+pub struct GuardianUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub guardian: Option<Pubkey>,
+}
+
+#[event]
+pub struct PayrollFrozen {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub frozen_by: Pubkey,
+}
+
+#[event]
+pub struct PayrollUnfrozen {
+    pub payroll_id: String,
+    pub sequence: u64,
+}
+
+#[event]
+pub struct Disbursed {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct WithdrawalDelayUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub withdrawal_delay_seconds: i64,
+    pub effective_ts: i64,
+}
+
+#[event]
+pub struct WithdrawalRequested {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub unlock_ts: i64,
+}
+
+#[event]
+pub struct WithdrawalExecuted {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub approvers: Vec<Pubkey>, // withdrawal signers whose approvals were consumed
+}
+
+#[event]
+pub struct WithdrawalSignersUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub withdrawal_signers: Vec<Pubkey>,
+    pub withdrawal_threshold: u8,
+}
+
+#[event]
+pub struct WithdrawalApproved {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub signer: Pubkey,
+    pub amount: u64,
+    pub approvals: u8,
+    pub withdrawal_threshold: u8,
+}
+
+#[event]
+pub struct WithdrawalCancelled {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub amount: u64,
+    pub cancelled_by: Pubkey,
+}
+
+#[event]
+pub struct VestingCreated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub total_amount: u64,
+    pub start_ts: i64,
+    pub cliff_ts: i64,
+    pub duration: i64,
+}
+
+#[event]
+pub struct VestedClaimed {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub amount: u64,
+    pub claimed: u64,
+    pub total_amount: u64,
+}
+
+#[event]
+pub struct ReimbursementPaid {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub amount: u64,
+    pub total_reimbursed: u64,
+}
+
+#[event]
+pub struct PeriodClosed {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub period: u64,
+    pub total_paid_in_period: u64,
+}
+
+#[event]
+pub struct TaxRateUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub old_rate: u16,
+    pub new_rate: u16,
 }
 
-#[callback_accounts("calculate_net_pay")]
-#[derive(Accounts)]
-pub struct CalculateNetPayCallback<'info> {
-    pub arcium_program: Program<'info, Arcium>,
-    #[account(
-        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CALCULATE_NET_PAY)
-    )]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
-    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
-    /// CHECK: instructions_sysvar, checked by the account constraint
-    pub instructions_sysvar: AccountInfo<'info>,
+// Spans several payrolls, so it carries no payroll_id or sequence; see TaxRateUpdated
+#[event]
+pub struct TaxRateBatchUpdated {
+    pub count: u64,
+    pub new_rate: u16,
 }
 
-#[init_computation_definition_accounts("calculate_net_pay", payer)]
-#[derive(Accounts)]
-pub struct InitCalculateNetPayCompDef<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(
-        mut,
-        address = derive_mxe_pda!()
-    )]
-    pub mxe_account: Box<Account<'info, MXEAccount>>,
-    #[account(mut)]
-    /// CHECK: comp_def_account, checked by arcium program.
-    /// Can't check it here as it's not initialized yet.
-    pub comp_def_account: UncheckedAccount<'info>,
-    pub arcium_program: Program<'info, Arcium>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct EncryptedTaxRateUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub encrypted_tax_rate: [u8; 32],
+    pub nonce: [u8; 16],
 }
 
-#[derive(Accounts)]
-#[instruction(payroll_id: String)]
-pub struct InitializePayroll<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + 32 + (4 + 64) + 32 + 8 + 1 + 2 + 8 + 1, // discriminator + authority + payroll_id + payment_token + employee_count + is_active + tax_rate + total_funds + vault_bump
-        seeds = [b"payroll", payroll_id.as_bytes()],
-        bump
-    )]
-    pub payroll: Account<'info, Payroll>,
-    #[account(
-        init,
-        payer = authority,
-        seeds = [b"payroll_vault", payroll.key().as_ref()],
-        bump,
-        token::mint = payment_token,
-        token::authority = payroll_vault,
-    )]
-    pub payroll_vault: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub payment_token: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct OutflowLimitUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub max_outflow_per_window: u64,
+    pub window_seconds: i64,
 }
 
-#[derive(Accounts)]
-#[instruction(employee_id: String)]
-pub struct AddEmployee<'info> {
-    #[account(
-        mut,
-        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
-        bump
-    )]
-    pub payroll: Account<'info, Payroll>,
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + 32 + (4 + 64) + 32 + 8 + 8 + 1 + 8 + 1, // discriminator + payroll + employee_id + wallet + salary_amount + deductions + payment_frequency + last_payment + is_active
-        seeds = [b"employee", payroll.key().as_ref(), employee_id.as_bytes()],
-        bump
-    )]
-    pub employee: Account<'info, Employee>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    /// CHECK: employee_wallet is checked by token program
-    pub employee_wallet: AccountInfo<'info>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct OracleUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub oracle: Option<Pubkey>,
+    pub feed_id: [u8; 32],
+    pub max_price_age: u64,
+    pub max_conf_bps: u16,
 }
 
-#[derive(Accounts)]
-pub struct ProcessPayment<'info> {
-    #[account(
-        mut,
-        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
-        bump
-    )]
-    pub payroll: Account<'info, Payroll>,
-    #[account(
-        mut,
-        seeds = [b"employee", payroll.key().as_ref(), employee.employee_id.as_bytes()],
-        bump
-    )]
-    pub employee: Account<'info, Employee>,
-    #[account(
-        mut,
-        seeds = [b"payroll_vault", payroll.key().as_ref()],
-        bump
-    )]
-    pub payroll_vault: Account<'info, TokenAccount>,
-    /// CHECK: employee_wallet is checked by token program
-    #[account(mut)]
-    pub employee_wallet: AccountInfo<'info>,
-    pub token_program: Program<'info, Token>,
+#[event]
+pub struct SalaryDenominationUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub salary_is_usd: bool,
 }
 
-#[derive(Accounts)]
-pub struct DepositFunds<'info> {
-    #[account(
-        mut,
-        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
-        bump
-    )]
-    pub payroll: Account<'info, Payroll>,
-    #[account(
-        mut,
-        seeds = [b"payroll_vault", payroll.key().as_ref()],
-        bump
-    )]
-    pub payroll_vault: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    #[account(mut)]
-    pub authority_token_account: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
+#[event]
+pub struct EventLevelUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub event_level: u8,
 }
 
-#[derive(Accounts)]
-pub struct PausePayroll<'info> {
-    #[account(
-        mut,
-        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
-        bump
-    )]
-    pub payroll: Account<'info, Payroll>,
-    pub authority: Signer<'info>,
+#[event]
+pub struct PayStubsToggled {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub enabled: bool,
 }
 
-#[derive(Accounts)]
-pub struct ResumePayroll<'info> {
-    #[account(
-        mut,
-        seeds = [b"payroll", payroll.payroll_id.as_bytes()],
-        bump
-    )]
-    pub payroll: Account<'info, Payroll>,
-    pub authority: Signer<'info>,
+#[event]
+pub struct PayStubClosed {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee: Pubkey,
+    pub payment_sequence: u64, // the closed stub's payment number
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Payroll {
-    pub authority: Pubkey,
-    #[max_len(64)]
+#[event]
+pub struct RoundingModeUpdated {
     pub payroll_id: String,
-    pub payment_token: Pubkey,
-    pub employee_count: u64,
-    pub is_active: bool,
-    pub tax_rate: u16, // basis points
-    pub total_funds: u64,
-    pub vault_bump: u8,
+    pub sequence: u64,
+    pub rounding_mode: RoundingMode,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Employee {
-    pub payroll: Pubkey,
-    #[max_len(64)]
-    pub employee_id: String,
-    pub wallet: Pubkey,
-    pub salary_amount: u64,
-    pub deductions: u64,
-    pub payment_frequency: PaymentFrequency,
-    pub last_payment: i64,
-    pub is_active: bool,
+#[event]
+pub struct WalletChangeDelayUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub wallet_change_delay: i64,
 }
 
-#[repr(u8)]
-#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
-pub enum PaymentFrequency {
-    Weekly,
-    BiWeekly,
-    Monthly,
+#[event]
+pub struct ApprovalPolicyUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub approver: Option<Pubkey>,
+    pub approval_threshold: u64,
 }
 
 #[event]
-pub struct PayrollInitialized {
+pub struct ClawbackPolicyUpdated {
     pub payroll_id: String,
-    pub authority: Pubkey,
-    pub tax_rate: u16,
+    pub sequence: u64,
+    pub clawback_max_per_period: u64,
+    pub min_net_pay: u64,
 }
 
 #[event]
-pub struct EmployeeAdded {
+pub struct SalaryBoundsUpdated {
     pub payroll_id: String,
-    pub employee_id: String,
-    pub salary_amount: u64,
-    pub deductions: u64,
+    pub sequence: u64,
+    pub min_salary: u64,
+    pub max_salary: u64,
 }
 
 #[event]
-pub struct NetPayCalculated {
-    pub net_pay: [u8; 32],
-    pub nonce: [u8; 16],
+pub struct EmployerTaxUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employer_tax_bps: u16,
+    pub tax_account: Option<Pubkey>,
 }
 
 #[event]
-pub struct PaymentProcessed {
+pub struct RecoveryGuardiansUpdated {
     pub payroll_id: String,
-    pub employee_id: String,
-    pub gross_salary: u64,
-    pub tax_amount: u64,
-    pub deductions: u64,
-    pub net_pay: u64,
+    pub sequence: u64,
+    pub guardians: Vec<Pubkey>,
+    pub recovery_threshold: u8,
+    pub recovery_delay_seconds: i64,
 }
 
 #[event]
-pub struct FundsDeposited {
+pub struct RecoveryProposed {
     pub payroll_id: String,
-    pub amount: u64,
-    pub total_funds: u64,
+    pub sequence: u64,
+    pub proposer: Pubkey,
+    pub new_authority: Pubkey,
+    pub executable_at: i64,
+}
+
+#[event]
+pub struct RecoveryApproved {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub guardian: Pubkey,
+    pub approvals: u8,
+}
+
+#[event]
+pub struct RecoveryExecuted {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
+
+#[event]
+pub struct RecoveryCancelled {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub cancelled_by: Pubkey,
+}
+
+#[event]
+pub struct NetRoundingUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub round_net_to_whole: bool,
+    pub payment_decimals: u8,
+}
+
+#[event]
+pub struct SettlementDelayUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub settlement_delay_seconds: i64,
+}
+
+#[event]
+pub struct TaxFreeAllowanceUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub tax_free_allowance: u64,
+}
+
+#[event]
+pub struct ConfidentialRequirementUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub require_confidential: bool,
+}
+
+#[event]
+pub struct PayDayAnchorUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub pay_day_anchor: i64,
+}
+
+#[event]
+pub struct BusinessDayShiftUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub business_day_shift: bool,
+    pub max_business_day_shift: i64,
+}
+
+#[event]
+pub struct SchedulePolicyUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub strict_schedule: bool,
+    pub schedule_tolerance: i64,
+}
+
+#[event]
+pub struct PaymentTokenChanged {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub old_mint: Pubkey,
+    pub new_mint: Pubkey,
+}
+
+#[event]
+pub struct VaultAuthorityUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub vault_authority: Option<Pubkey>,
+}
+
+#[event]
+pub struct PlatformFeeUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub platform_fee_bps: u16,
+    pub platform_account: Option<Pubkey>,
+    pub platform_fee_from_employee: bool,
+}
+
+#[event]
+pub struct PauseCooldownUpdated {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub pause_cooldown_seconds: i64,
 }
 
 #[event]
 pub struct PayrollPaused {
     pub payroll_id: String,
+    pub sequence: u64,
 }
 
 #[event]
 pub struct PayrollResumed {
     pub payroll_id: String,
+    pub sequence: u64,
+    pub paused_seconds: i64, // added to every paid employee's schedule
+}
+
+#[event]
+pub struct ScheduleAdjustedForPause {
+    pub payroll_id: String,
+    pub sequence: u64,
+    pub employee_id: String,
+    pub shift_seconds: i64,
+    pub last_payment: i64,
 }
 
 #[error_code]
@@ -539,15 +8547,243 @@ pub enum ErrorCode {
     #[msg("Payroll is not active")]
     PayrollInactive,
     #[msg("Payroll is already active")]
-    PayrollActive,
+    PayrollAlreadyActive,
     #[msg("Employee is not active")]
     EmployeeInactive,
     #[msg("Invalid employee ID")]
     InvalidEmployeeId,
-    #[msg("Payment is too soon")]
+    #[msg("Payment is too soon; payment_due_in reports the remaining wait")]
     PaymentTooSoon,
     #[msg("Insufficient funds in payroll vault")]
     InsufficientFunds,
     #[msg("Cluster not set")]
     ClusterNotSet,
+    #[msg("Signer is not authorized for this payroll")]
+    Unauthorized,
+    #[msg("Max catch-up periods must be at least 1")]
+    InvalidCatchupPeriods,
+    #[msg("Payroll is already paused")]
+    PayrollAlreadyPaused,
+    #[msg("Effective timestamp must be in the future")]
+    InvalidEffectiveTimestamp,
+    #[msg("Deduction label is empty, too long or already in use")]
+    InvalidDeductionLabel,
+    #[msg("Employee already has the maximum number of deduction items")]
+    TooManyDeductions,
+    #[msg("No deduction item with that label")]
+    DeductionNotFound,
+    #[msg("Payroll has reached its maximum number of employees")]
+    EmployeeLimitReached,
+    #[msg("Garnishment basis points must not exceed 10000")]
+    InvalidGarnishmentBps,
+    #[msg("Payroll is frozen")]
+    PayrollFrozen,
+    #[msg("Payroll is not frozen")]
+    PayrollNotFrozen,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+    #[msg("Payment interval must be positive")]
+    InvalidPaymentInterval,
+    #[msg("Token account mint does not match the payroll's payment token")]
+    MintMismatch,
+    #[msg("Encrypted tax rate has not been set for this payroll")]
+    EncryptedTaxRateNotSet,
+    #[msg("Outflow would exceed the payroll's rate limit for this window")]
+    RateLimitExceeded,
+    #[msg("Outflow window must be positive when a limit is set")]
+    InvalidOutflowWindow,
+    #[msg("No price oracle is configured for this payroll")]
+    OracleNotConfigured,
+    #[msg("Price account is not the configured oracle feed")]
+    InvalidPriceAccount,
+    #[msg("Oracle price must be positive")]
+    InvalidPrice,
+    #[msg("Oracle price is too old")]
+    StalePrice,
+    #[msg("Oracle price confidence interval is too wide")]
+    PriceConfidenceTooWide,
+    #[msg("Invalid oracle configuration")]
+    InvalidOracleConfig,
+    #[msg("Batch is empty or exceeds the per-call limit")]
+    BatchTooLarge,
+    #[msg("Employee account does not match the expected PDA")]
+    InvalidEmployeeAccount,
+    #[msg("Pay stubs are enabled for this payroll; a pay stub account is required")]
+    PayStubRequired,
+    #[msg("Wallet change delay must not be negative")]
+    InvalidWalletChangeDelay,
+    #[msg("Payment exceeds the approval threshold and needs a second approval")]
+    ApprovalRequired,
+    #[msg("Approval does not cover the payment amount")]
+    ApprovalAmountTooLow,
+    #[msg("Approver must be set, and differ from the authority, when a threshold is configured")]
+    InvalidApprovalPolicy,
+    #[msg("Invalid vesting schedule")]
+    InvalidVestingSchedule,
+    #[msg("Nothing has vested since the last claim")]
+    NothingToClaim,
+    #[msg("Destination does not match the employee's wallet")]
+    EmployeeWalletMismatch,
+    #[msg("A payment with this idempotency key was already processed")]
+    DuplicatePayment,
+    #[msg("Clawback amount must be greater than zero")]
+    InvalidClawbackAmount,
+    #[msg("Department and cost center must be at most 32 bytes")]
+    InvalidEmployeeMetadata,
+    #[msg("All fund movements are paused by the program admin")]
+    GloballyPaused,
+    #[msg("A payment attempted now would succeed; nothing to record")]
+    PaymentWouldSucceed,
+    #[msg("Salary is outside the payroll's configured bounds")]
+    SalaryOutOfBounds,
+    #[msg("Minimum salary must not exceed the maximum")]
+    InvalidSalaryBounds,
+    #[msg("Reimbursement amount must be greater than zero")]
+    InvalidReimbursementAmount,
+    #[msg("Employer tax must be at most 10000 bps and needs a tax account")]
+    InvalidEmployerTaxConfig,
+    #[msg("Tax account is missing or does not match the payroll's tax account")]
+    InvalidTaxAccount,
+    #[msg("No tokens to sweep")]
+    NothingToSweep,
+    #[msg("Vault accounts do not match the payroll's payment mode")]
+    VaultMismatch,
+    #[msg("Not supported for native SOL payrolls")]
+    NativeUnsupported,
+    #[msg("Withdrawal delay must not be negative")]
+    InvalidWithdrawalDelay,
+    #[msg("Withdrawal amount must be greater than zero")]
+    InvalidWithdrawalAmount,
+    #[msg("A withdrawal is already pending")]
+    WithdrawalPending,
+    #[msg("No withdrawal is pending")]
+    NoPendingWithdrawal,
+    #[msg("Withdrawal is still time-locked")]
+    WithdrawalLocked,
+    #[msg("Destination does not match the requested withdrawal")]
+    InvalidWithdrawalDestination,
+    #[msg("Withdrawals are time-locked; use request_withdrawal")]
+    WithdrawalTimeLocked,
+    #[msg("Minimum payment exceeds the payroll salary ceiling")]
+    InvalidMinPayment,
+    #[msg("Salary cut requires the employee's signature")]
+    SalaryCutNotApproved,
+    #[msg("Platform fee exceeds the maximum or has no platform account")]
+    InvalidPlatformFeeConfig,
+    #[msg("Platform account does not match the payroll configuration")]
+    InvalidPlatformAccount,
+    #[msg("No stored net pay inputs to retry")]
+    NoStoredNetPayInputs,
+    #[msg("Schedule tolerance must be non-negative and requires a strict schedule")]
+    InvalidScheduleTolerance,
+    #[msg("Pay day anchor must not be negative")]
+    InvalidPayDayAnchor,
+    #[msg("Tax rate must not exceed 10000 basis points")]
+    InvalidTaxRate,
+    #[msg("Payroll account is duplicated or not writable")]
+    InvalidPayrollAccount,
+    #[msg("Deduction basis points must not exceed 10000")]
+    InvalidDeductionBps,
+    #[msg("Fixed and percentage deductions exceed gross pay")]
+    DeductionsExceedGross,
+    #[msg("Vault authority is missing, mismatched, or the vault itself")]
+    InvalidVaultAuthority,
+    #[msg("Employee already exists with different data")]
+    EmployeeImportConflict,
+    #[msg("Pause cooldown must not be negative")]
+    InvalidPauseCooldown,
+    #[msg("Pause cooldown has not elapsed since the last pause or resume")]
+    PauseCooldownActive,
+    #[msg("YTD tax accumulator has not been initialized with reset_ytd_tax")]
+    YtdTaxNotInitialized,
+    #[msg("A YTD tax accumulation is already in flight")]
+    YtdTaxComputationPending,
+    #[msg("Invalid payroll ID")]
+    InvalidPayrollId,
+    #[msg("Refund amount must be greater than zero")]
+    InvalidRefundAmount,
+    #[msg("Computed tax exceeds gross salary")]
+    TaxExceedsSalary,
+    #[msg("Deposit splits must sum to 10000 bps and match the passed token accounts")]
+    InvalidDepositSplits,
+    #[msg("Payroll requires a fresh confidential net pay result before payment")]
+    ConfidentialRequired,
+    #[msg("Payment would exceed the employee's per-period pay cap")]
+    PeriodCapExceeded,
+    #[msg("Employee count must match the employee accounts passed")]
+    EmployeeCountMismatch,
+    #[msg("Memo exceeds MAX_PAYMENT_MEMO_LEN bytes or the memo program is missing")]
+    InvalidMemo,
+    #[msg("Employee is already active")]
+    EmployeeAlreadyActive,
+    #[msg("Tax-free allowance exceeds the payroll's max salary")]
+    InvalidTaxFreeAllowance,
+    #[msg("Vault is not the payroll's vault PDA")]
+    VaultAddressMismatch,
+    #[msg("Vault is not owned by the vault PDA or the payroll's vault authority")]
+    VaultOwnerMismatch,
+    #[msg("Employee encryption pubkey is not set")]
+    EncryptionPubkeyNotSet,
+    #[msg("Settlement delay must not be negative")]
+    InvalidSettlementDelay,
+    #[msg("Net pay is still inside the settlement delay")]
+    SettlementPending,
+    #[msg("Payroll still holds funds or employees")]
+    PayrollNotEmpty,
+    #[msg("New payment token is the current one")]
+    PaymentTokenUnchanged,
+    #[msg("Invalid recovery guardians, threshold or delay")]
+    InvalidRecoveryConfig,
+    #[msg("A recovery proposal is open")]
+    RecoveryPending,
+    #[msg("Recovery must hand the payroll to a key that is neither the authority nor a guardian")]
+    InvalidRecoveryAuthority,
+    #[msg("Guardian already approved this recovery")]
+    RecoveryAlreadyApproved,
+    #[msg("Not enough guardian approvals for recovery")]
+    RecoveryThresholdNotMet,
+    #[msg("Recovery delay has not elapsed")]
+    RecoveryDelayActive,
+    #[msg("A total net payroll computation is already in flight")]
+    TotalNetPayComputationPending,
+    #[msg("Pay history account is required for this employee")]
+    PayHistoryRequired,
+    #[msg("Clock is unset or behind the employee's last payment")]
+    ClockRegression,
+    #[msg("Unknown event level")]
+    InvalidEventLevel,
+    #[msg("Business-day shift must be between zero and three days and requires shifting enabled")]
+    InvalidBusinessDayShift,
+    #[msg("Business-day shifting is not enabled for this payroll")]
+    BusinessDayShiftDisabled,
+    #[msg("Adjusted pay date is after the due date or outside the allowed shift")]
+    InvalidAdjustedPayTs,
+    #[msg("Funding source does not match the payroll configuration")]
+    InvalidFundingSource,
+    #[msg("Funding source balance or delegation does not cover the shortfall")]
+    FundingSourceInsufficient,
+    #[msg("Minimum tenure must not be negative")]
+    InvalidMinTenure,
+    #[msg("Employee has not completed the payroll's minimum tenure")]
+    TenureNotMet,
+    #[msg("No confidential net pay result to validate")]
+    NoNetPayResult,
+    #[msg("A net pay validation is already in flight")]
+    NetPayValidationPending,
+    #[msg("The confidential net pay result has not passed validate_net_pay")]
+    NetPayNotValidated,
+    #[msg("Withdrawal signers must be distinct, at most five, with a threshold they can meet")]
+    InvalidWithdrawalSigners,
+    #[msg("Signer has already approved this withdrawal")]
+    WithdrawalAlreadyApproved,
+    #[msg("Not enough withdrawal signers have approved")]
+    WithdrawalApprovalsNotMet,
+    #[msg("Overtime amount must be positive")]
+    InvalidOvertimeAmount,
+    #[msg("Only non-exempt employees earn overtime")]
+    OvertimeNotEligible,
+    #[msg("An initial deposit needs the authority's token account")]
+    DepositAccountRequired,
+    #[msg("A recount cannot lower employee_count")]
+    EmployeeCountDecrease,
 }
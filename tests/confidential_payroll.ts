@@ -24,7 +24,11 @@ import {
 import * as fs from "fs";
 import * as os from "os";
 import { expect } from "chai";
-import { createMint, createAccount, mintTo, getAccount } from "@solana/spl-token";
+import { createMint, createAccount, mintTo, getAccount, approve } from "@solana/spl-token";
+
+const MEMO_PROGRAM_ID = new PublicKey("MemoSq4gqABAXKb96qnH8TuNzTRBsFhTNKELCC5eJmS");
+// Employees whose confidential net pay key is registered later, if at all
+const NO_ENCRYPTION_PUBKEY = Array(32).fill(0);
 
 describe("ConfidentialPayroll", () => {
   // Configure the client to use the local cluster.
@@ -79,6 +83,27 @@ describe("ConfidentialPayroll", () => {
     await mintTo(provider.connection, authority, mint, authorityTokenAccount, authority, 1000000000); // 1 token
   });
 
+  it("Initialize program config", async () => {
+    // Deployed by the provider wallet, which is therefore the upgrade authority
+    const [programData] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+
+    await program.methods
+      .initializeProgramConfig(provider.publicKey)
+      .accountsPartial({
+        program: program.programId,
+        programData,
+        payer: provider.publicKey,
+      })
+      .rpc();
+
+    const config = await program.account.programConfig.fetch(programConfigPda());
+    expect(config.admin.toBase58()).to.equal(provider.publicKey.toBase58());
+    expect(config.globalPaused).to.equal(false);
+  });
+
   it("Initialize payroll", async () => {
     const [payrollPda] = PublicKey.findProgramAddressSync(
       [Buffer.from("payroll"), Buffer.from(payrollId)],
@@ -92,13 +117,14 @@ describe("ConfidentialPayroll", () => {
     payrollVault = vaultPda;
 
     await program.methods
-      .initializePayroll(payrollId, 1000) // 10% tax rate
+      .initializePayroll(payrollId, 1000, new anchor.BN(0), { weekly: {} }, new anchor.BN(0)) // 10% tax rate, unlimited employees
       .accounts({
         payroll: payrollPda,
         payrollVault: vaultPda,
         authority: authority.publicKey,
         paymentToken: mint,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .signers([authority])
@@ -122,12 +148,13 @@ describe("ConfidentialPayroll", () => {
     );
 
     await program.methods
-      .addEmployee(employeeId, new anchor.BN(100000000), new anchor.BN(5000000), { weekly: {} }) // 0.1 token salary, 0.005 deductions
+      .addEmployee(employeeId, new anchor.BN(100000000), new anchor.BN(5000000), { weekly: {} }, "engineering", "cc-100", NO_ENCRYPTION_PUBKEY, { exempt: {} }) // 0.1 token salary, 0.005 deductions
       .accounts({
         payroll: payrollPda,
         employee: employeePda,
         authority: authority.publicKey,
         employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .signers([authority])
@@ -137,6 +164,23 @@ describe("ConfidentialPayroll", () => {
     expect(employeeAccount.employeeId).to.equal(employeeId);
     expect(employeeAccount.salaryAmount.toNumber()).to.equal(100000000);
     expect(employeeAccount.deductions.toNumber()).to.equal(5000000);
+    expect(employeeAccount.salaryDecimals).to.equal(9);
+    expect(employeeAccount.department).to.equal("engineering");
+    expect(employeeAccount.costCenter).to.equal("cc-100");
+    expect(employeeAccount.createdAt.toNumber()).to.be.greaterThan(0);
+  });
+
+  it("Reports employee tenure", async () => {
+    const payrollPda = payrollPdaFor(payrollId);
+    const employeePda = employeePdaFor(payrollPda, employeeId);
+    const employeeAccount = await program.account.employee.fetch(employeePda);
+
+    const tenurePromise = awaitEvent("employeeTenure");
+    await program.methods.employeeTenure().accountsPartial({ payroll: payrollPda, employee: employeePda }).rpc();
+    const tenure = await tenurePromise;
+
+    expect(tenure.createdAt.toNumber()).to.equal(employeeAccount.createdAt.toNumber());
+    expect(tenure.tenureSeconds.toNumber()).to.be.at.least(0);
   });
 
   it("Deposit funds", async () => {
@@ -150,8 +194,10 @@ describe("ConfidentialPayroll", () => {
       .accounts({
         payroll: payrollPda,
         payrollVault: payrollVault,
+        nativeVault: null,
         authority: authority.publicKey,
         authorityTokenAccount: authorityTokenAccount,
+        paymentToken: mint,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
       })
       .signers([authority])
@@ -178,23 +224,5244 @@ describe("ConfidentialPayroll", () => {
     const initialEmployeeBalance = await getAccount(provider.connection, employeeTokenAccount);
     
     await program.methods
-      .processPayment()
+      .processPayment(Array.from(randomBytes(16)), "", null)
       .accounts({
         payroll: payrollPda,
         employee: employeePda,
         payrollVault: payrollVault,
+        nativeVault: null,
+        employeeWallet: employeeTokenAccount,
+        nativeWallet: null,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        priceUpdate: null,
+        payStub: null,
+        payer: null,
+        systemProgram: null,
+        taxAccount: null,
+        platformAccount: null,
+        vaultAuthority: null,
+        memoProgram: null,
+        approval: null,
+        approver: null,
+        payHistory: null,
+        fundingSource: null,
+        authority: null,
+      })
+      .rpc();
+
+    const updatedPayroll = await program.account.payroll.fetch(payrollPda);
+    const updatedEmployee = await program.account.employee.fetch(employeePda);
+
+    // Check that funds were transferred
+    const employeeTokenBalance = await getAccount(provider.connection, employeeTokenAccount);
+    const expectedNetPay = 85000000; // 100000000 - (100000000 * 0.1) - 5000000
+    expect(employeeTokenBalance.amount.toString()).to.equal((Number(initialEmployeeBalance.amount) + expectedNetPay).toString());
+  });
+
+  it("Funder can deposit funds", async () => {
+    const [payrollPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("payroll"), Buffer.from(payrollId)],
+      program.programId
+    );
+
+    const funder = await newFundedKeypair();
+    const funderTokenAccount = await createAccount(provider.connection, funder, mint, funder.publicKey);
+    await mintTo(provider.connection, authority, mint, funderTokenAccount, authority, 50000000);
+
+    await program.methods
+      .setFunder(funder.publicKey)
+      .accountsPartial({
+        payroll: payrollPda,
+        authority: authority.publicKey,
+      })
+      .signers([authority])
+      .rpc();
+
+    const before = await program.account.payroll.fetch(payrollPda);
+    expect(before.funder.toBase58()).to.equal(funder.publicKey.toBase58());
+
+    await program.methods
+      .depositFunds(new anchor.BN(50000000))
+      .accountsPartial({
+        payroll: payrollPda,
+        payrollVault: payrollVault,
+        nativeVault: null,
+        authority: funder.publicKey,
+        authorityTokenAccount: funderTokenAccount,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([funder])
+      .rpc();
+
+    const after = await program.account.payroll.fetch(payrollPda);
+    expect(after.totalFunds.toNumber()).to.equal(before.totalFunds.toNumber() + 50000000);
+  });
+
+  it("Rejects deposits from an unauthorized signer", async () => {
+    const [payrollPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("payroll"), Buffer.from(payrollId)],
+      program.programId
+    );
+
+    const stranger = await newFundedKeypair();
+    const strangerTokenAccount = await createAccount(provider.connection, stranger, mint, stranger.publicKey);
+    await mintTo(provider.connection, authority, mint, strangerTokenAccount, authority, 10000000);
+
+    await expectAnchorError(
+      program.methods
+        .depositFunds(new anchor.BN(10000000))
+        .accountsPartial({
+          payroll: payrollPda,
+          payrollVault: payrollVault,
+          nativeVault: null,
+          authority: stranger.publicKey,
+          authorityTokenAccount: strangerTokenAccount,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([stranger])
+        .rpc(),
+      "Unauthorized"
+    );
+  });
+
+  it("Funds the vault as part of initialization", async () => {
+    const seededPayroll = payrollPdaFor("seeded_payroll");
+    const seededVault = vaultPdaFor(seededPayroll);
+    const depositedPromise = awaitEvent("fundsDeposited");
+
+    await program.methods
+      .initializePayroll("seeded_payroll", 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(2500))
+      .accountsPartial({
+        payroll: seededPayroll,
+        payrollVault: seededVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: authorityTokenAccount,
+      })
+      .signers([authority])
+      .rpc();
+    const deposited = await depositedPromise;
+
+    expect(deposited.received.toNumber()).to.equal(2500);
+    const payrollAccount = await program.account.payroll.fetch(seededPayroll);
+    expect(payrollAccount.totalFunds.toNumber()).to.equal(2500);
+    expect(Number((await getAccount(provider.connection, seededVault)).amount)).to.equal(2500);
+  });
+
+  it("Changes the payment token of an empty payroll", async () => {
+    const mintChangePayroll = payrollPdaFor("mint_change_payroll");
+    const mintChangeVault = vaultPdaFor(mintChangePayroll);
+    const newMint = await createMint(provider.connection, authority, authority.publicKey, null, 6);
+    const changeAccounts = {
+      authority: authority.publicKey,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      newMint,
+      newTokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+    };
+
+    await program.methods
+      .initializePayroll("mint_change_payroll", 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: mintChangePayroll,
+        payrollVault: mintChangeVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    // A funded payroll keeps its mint
+    const seededPayroll = payrollPdaFor("seeded_payroll");
+    await expectAnchorError(
+      program.methods
+        .changePaymentToken()
+        .accountsPartial({ ...changeAccounts, payroll: seededPayroll, payrollVault: vaultPdaFor(seededPayroll) })
+        .signers([authority])
+        .rpc(),
+      "PayrollNotEmpty"
+    );
+
+    const changedPromise = awaitEvent("paymentTokenChanged");
+    await program.methods
+      .changePaymentToken()
+      .accountsPartial({ ...changeAccounts, payroll: mintChangePayroll, payrollVault: mintChangeVault })
+      .signers([authority])
+      .rpc();
+    const changed = await changedPromise;
+
+    expect(changed.oldMint.toBase58()).to.equal(mint.toBase58());
+    const payrollAccount = await program.account.payroll.fetch(mintChangePayroll);
+    expect(payrollAccount.paymentToken.toBase58()).to.equal(newMint.toBase58());
+    const vaultAccount = await getAccount(provider.connection, mintChangeVault);
+    expect(vaultAccount.mint.toBase58()).to.equal(newMint.toBase58());
+    expect(vaultAccount.owner.toBase58()).to.equal(mintChangeVault.toBase58());
+  });
+
+  it("Falls back to the payroll's default frequency when none is given", async () => {
+    const defaultsPayroll = payrollPdaFor("defaults_payroll");
+
+    await program.methods
+      .initializePayroll("defaults_payroll", 0, new anchor.BN(0), { monthly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: defaultsPayroll,
+        payrollVault: vaultPdaFor(defaultsPayroll),
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    const add = async (employeeId: string, frequency: any) => {
+      const addedPromise = awaitEvent("employeeAdded");
+      await program.methods
+        .addEmployee(employeeId, new anchor.BN(1000), new anchor.BN(0), frequency, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+        .accountsPartial({
+          payroll: defaultsPayroll,
+          employee: employeePdaFor(defaultsPayroll, employeeId),
+          authority: authority.publicKey,
+          employeeWallet: employeeTokenAccount,
+          paymentToken: mint,
+        })
+        .signers([authority])
+        .rpc();
+      return addedPromise;
+    };
+
+    const defaulted = await add("emp_default", null);
+    expect(defaulted.paymentFrequency).to.deep.equal({ monthly: {} });
+    // An explicit frequency still overrides the default
+    const overridden = await add("emp_override", { weekly: {} });
+    expect(overridden.paymentFrequency).to.deep.equal({ weekly: {} });
+    const stored = await program.account.employee.fetch(employeePdaFor(defaultsPayroll, "emp_default"));
+    expect(stored.paymentFrequency).to.deep.equal({ monthly: {} });
+  });
+
+  it("Skips non-essential events at the essential event level", async () => {
+    const defaultsPayroll = payrollPdaFor("defaults_payroll");
+    const configAccounts = { payroll: defaultsPayroll, authority: authority.publicKey };
+    const emittedEvents = async (signature: string) => {
+      await provider.connection.confirmTransaction(signature, "confirmed");
+      const tx = await provider.connection.getTransaction(signature, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      return tx.meta.logMessages.filter((log) => log.startsWith("Program data:")).length;
+    };
+
+    await expectAnchorError(
+      program.methods.setEventLevel(2).accountsPartial(configAccounts).signers([authority]).rpc(),
+      "InvalidEventLevel"
+    );
+
+    const levelSignature = await program.methods
+      .setEventLevel(0)
+      .accountsPartial(configAccounts)
+      .signers([authority])
+      .rpc();
+    // The level change itself is always reported
+    expect(await emittedEvents(levelSignature)).to.equal(1);
+
+    const quietSignature = await program.methods
+      .setMinPayment(new anchor.BN(1))
+      .accountsPartial(configAccounts)
+      .signers([authority])
+      .rpc();
+    expect(await emittedEvents(quietSignature)).to.equal(0);
+
+    await program.methods.setEventLevel(1).accountsPartial(configAccounts).signers([authority]).rpc();
+    const loudSignature = await program.methods
+      .setMinPayment(new anchor.BN(0))
+      .accountsPartial(configAccounts)
+      .signers([authority])
+      .rpc();
+    expect(await emittedEvents(loudSignature)).to.equal(1);
+  });
+
+  it("Rejects deposits from a token account of another mint", async () => {
+    const payrollPda = payrollPdaFor(payrollId);
+    const otherMint = await createMint(provider.connection, authority, authority.publicKey, null, 6);
+    const otherTokenAccount = await createAccount(provider.connection, authority, otherMint, authority.publicKey);
+    await mintTo(provider.connection, authority, otherMint, otherTokenAccount, authority, 1000000);
+    const before = await program.account.payroll.fetch(payrollPda);
+
+    await expectAnchorError(
+      program.methods
+        .depositFunds(new anchor.BN(1000000))
+        .accountsPartial({
+          payroll: payrollPda,
+          payrollVault: payrollVault,
+          nativeVault: null,
+          authority: authority.publicKey,
+          authorityTokenAccount: otherTokenAccount,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([authority])
+        .rpc(),
+      "MintMismatch"
+    );
+
+    const after = await program.account.payroll.fetch(payrollPda);
+    expect(after.totalFunds.toNumber()).to.equal(before.totalFunds.toNumber());
+  });
+
+  it("Enforces max_employees when set", async () => {
+    const cappedPayrollId = "capped_payroll";
+    const cappedPayroll = payrollPdaFor(cappedPayrollId);
+
+    await program.methods
+      .initializePayroll(cappedPayrollId, 1000, new anchor.BN(1), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: cappedPayroll,
+        payrollVault: vaultPdaFor(cappedPayroll),
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    const addToCapped = (id: string) =>
+      program.methods
+        .addEmployee(id, new anchor.BN(100000000), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+        .accountsPartial({
+          payroll: cappedPayroll,
+          employee: employeePdaFor(cappedPayroll, id),
+          authority: authority.publicKey,
+          employeeWallet: employeeTokenAccount,
+          paymentToken: mint,
+        })
+        .signers([authority])
+        .rpc();
+
+    await addToCapped("capped_001");
+    await expectAnchorError(addToCapped("capped_002"), "EmployeeLimitReached");
+
+    const payrollAccount = await program.account.payroll.fetch(cappedPayroll);
+    expect(payrollAccount.employeeCount.toNumber()).to.equal(1);
+  });
+
+  it("Adds a batch of employees in one instruction", async () => {
+    const batchId = "batch_add_payroll";
+    const batchPayroll = payrollPdaFor(batchId);
+
+    await program.methods
+      .initializePayroll(batchId, 1000, new anchor.BN(3), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: batchPayroll,
+        payrollVault: vaultPdaFor(batchPayroll),
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    const paramsFor = (id: string, salary: number) => ({
+      employeeId: id,
+      wallet: employeeTokenAccount,
+      salaryAmount: new anchor.BN(salary),
+      deductions: new anchor.BN(0),
+      paymentFrequency: { weekly: {} },
+      department: "eng",
+      costCenter: "",
+      encryptionPubkey: NO_ENCRYPTION_PUBKEY,
+      classification: { exempt: {} },
+    });
+    const addBatch = (ids: string[]) =>
+      program.methods
+        .addEmployeesBatch(ids.map((id, i) => paramsFor(id, 1000 * (i + 1))))
+        .accountsPartial({
+          payroll: batchPayroll,
+          authority: authority.publicKey,
+          paymentToken: mint,
+        })
+        .remainingAccounts(
+          ids.map((id) => ({ pubkey: employeePdaFor(batchPayroll, id), isSigner: false, isWritable: true }))
+        )
+        .signers([authority])
+        .rpc();
+
+    const ids = ["batch_001", "batch_002"];
+    const addedEvent = awaitEvent("employeesAdded");
+    await addBatch(ids);
+    expect((await addedEvent).count.toNumber()).to.equal(2);
+
+    for (const [i, id] of ids.entries()) {
+      const employeeAccount = await program.account.employee.fetch(employeePdaFor(batchPayroll, id));
+      expect(employeeAccount.payroll.toBase58()).to.equal(batchPayroll.toBase58());
+      expect(employeeAccount.employeeId).to.equal(id);
+      expect(employeeAccount.wallet.toBase58()).to.equal(employeeTokenAccount.toBase58());
+      expect(employeeAccount.salaryAmount.toNumber()).to.equal(1000 * (i + 1));
+      expect(employeeAccount.isActive).to.be.true;
+    }
+
+    // Two more would exceed max_employees of 3, and neither is created
+    const overLimit = ["batch_003", "batch_004"];
+    await expectAnchorError(addBatch(overLimit), "EmployeeLimitReached");
+    for (const id of overLimit) {
+      expect(await provider.connection.getAccountInfo(employeePdaFor(batchPayroll, id))).to.be.null;
+    }
+
+    const payrollAccount = await program.account.payroll.fetch(batchPayroll);
+    expect(payrollAccount.employeeCount.toNumber()).to.equal(2);
+  });
+
+  it("Enforces payroll salary bounds", async () => {
+    const boundedPayrollId = "bounded_payroll";
+    const boundedPayroll = payrollPdaFor(boundedPayrollId);
+    const boundedEmployeeId = "emp_bounded";
+    const boundedEmployee = employeePdaFor(boundedPayroll, boundedEmployeeId);
+
+    await program.methods
+      .initializePayroll(boundedPayrollId, 1000, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: boundedPayroll,
+        payrollVault: vaultPdaFor(boundedPayroll),
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .setSalaryBounds(new anchor.BN(100), new anchor.BN(1000))
+      .accountsPartial({ payroll: boundedPayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const addEmployee = () =>
+      program.methods
+        .addEmployee(boundedEmployeeId, new anchor.BN(5000), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+        .accountsPartial({
+          payroll: boundedPayroll,
+          employee: boundedEmployee,
+          authority: authority.publicKey,
+          employeeWallet: employeeTokenAccount,
+          paymentToken: mint,
+        })
+        .signers([authority])
+        .rpc();
+
+    await expectAnchorError(addEmployee(), "SalaryOutOfBounds");
+
+    // Lifting the ceiling lets the same salary through
+    await program.methods
+      .setSalaryBounds(new anchor.BN(100), new anchor.BN(0))
+      .accountsPartial({ payroll: boundedPayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    await addEmployee();
+
+    const employeeAccount = await program.account.employee.fetch(boundedEmployee);
+    expect(employeeAccount.salaryAmount.toNumber()).to.equal(5000);
+  });
+
+  it("Tracks deposits up to u64::MAX without wrapping", async () => {
+    const bigPayrollId = "big_payroll";
+    const bigPayroll = payrollPdaFor(bigPayrollId);
+    const bigVault = vaultPdaFor(bigPayroll);
+    const u64Max = BigInt("18446744073709551615");
+
+    const bigMint = await createMint(provider.connection, authority, authority.publicKey, null, 0);
+    const bigTokenAccount = await createAccount(provider.connection, authority, bigMint, authority.publicKey);
+    await mintTo(provider.connection, authority, bigMint, bigTokenAccount, authority, u64Max);
+
+    await program.methods
+      .initializePayroll(bigPayrollId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: bigPayroll,
+        payrollVault: bigVault,
+        authority: authority.publicKey,
+        paymentToken: bigMint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .depositFunds(new anchor.BN(u64Max.toString()))
+      .accountsPartial({
+        payroll: bigPayroll,
+        payrollVault: bigVault,
+        nativeVault: null,
+        authority: authority.publicKey,
+        authorityTokenAccount: bigTokenAccount,
+        paymentToken: bigMint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    const payrollAccount = await program.account.payroll.fetch(bigPayroll);
+    expect(payrollAccount.totalFunds.toString()).to.equal(u64Max.toString());
+  });
+
+  it("Updates an employee's payment frequency", async () => {
+    const payrollPda = payrollPdaFor(payrollId);
+    const monthlyId = "emp_monthly";
+    const monthlyEmployee = employeePdaFor(payrollPda, monthlyId);
+
+    await program.methods
+      .addEmployee(monthlyId, new anchor.BN(100000000), new anchor.BN(0), { monthly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: payrollPda,
+        employee: monthlyEmployee,
+        authority: authority.publicKey,
         employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .updatePaymentFrequency({ weekly: {} })
+      .accountsPartial({
+        payroll: payrollPda,
+        employee: monthlyEmployee,
+        authority: authority.publicKey,
+      })
+      .signers([authority])
+      .rpc();
+
+    const employeeAccount = await program.account.employee.fetch(monthlyEmployee);
+    expect(employeeAccount.paymentFrequency).to.deep.equal({ weekly: {} });
+    // Next due time is last_payment + 7 days; last_payment is untouched by the update
+    expect(employeeAccount.lastPayment.toNumber()).to.equal(0);
+  });
+
+  it("Reports the next payment timestamp", async () => {
+    const payrollPda = payrollPdaFor(payrollId);
+    const employeePda = employeePdaFor(payrollPda, employeeId);
+
+    const nextPaymentPromise = awaitEvent("nextPayment");
+    await program.methods
+      .nextPaymentTimestamp()
+      .accountsPartial({ payroll: payrollPda, employee: employeePda })
+      .rpc();
+    const nextPayment = await nextPaymentPromise;
+
+    const employeeAccount = await program.account.employee.fetch(employeePda);
+    const weekSeconds = 7 * 24 * 60 * 60;
+    expect(nextPayment.employeeId).to.equal(employeeId);
+    expect(nextPayment.nextTs.toNumber()).to.equal(employeeAccount.lastPayment.toNumber() + weekSeconds);
+  });
+
+  it("Reports how long until the next payment is due", async () => {
+    const payrollPda = payrollPdaFor(payrollId);
+    const employeePda = employeePdaFor(payrollPda, employeeId);
+
+    const dueInPromise = awaitEvent("paymentDueIn");
+    await program.methods
+      .paymentDueIn()
+      .accountsPartial({ payroll: payrollPda, employee: employeePda })
+      .rpc();
+    const dueIn = await dueInPromise;
+
+    // Just paid, so almost a full week remains
+    const weekSeconds = 7 * 24 * 60 * 60;
+    expect(dueIn.employeeId).to.equal(employeeId);
+    expect(dueIn.secondsRemaining.toNumber()).to.be.greaterThan(0);
+    expect(dueIn.secondsRemaining.toNumber()).to.be.at.most(weekSeconds);
+  });
+
+  it("Reports payroll solvency across employees", async () => {
+    const payrollPda = payrollPdaFor(payrollId);
+    const employeePda = employeePdaFor(payrollPda, employeeId);
+
+    const reportPromise = awaitEvent("solvencyReport");
+    await program.methods
+      .checkSolvency()
+      .accountsPartial({ payroll: payrollPda, priceUpdate: null })
+      .remainingAccounts([{ pubkey: employeePda, isSigner: false, isWritable: false }])
+      .rpc();
+    const report = await reportPromise;
+
+    const payrollAccount = await program.account.payroll.fetch(payrollPda);
+    expect(report.totalObligations.toNumber()).to.equal(85000000);
+    expect(report.totalFunds.toNumber()).to.equal(payrollAccount.totalFunds.toNumber());
+    expect(report.isSolvent).to.equal(payrollAccount.totalFunds.toNumber() >= 85000000);
+  });
+
+  it("Verifies the vault for audits", async () => {
+    const payrollPda = payrollPdaFor(payrollId);
+
+    const verifiedPromise = awaitEvent("vaultVerified");
+    await program.methods.verifyVault().accountsPartial({ payroll: payrollPda, payrollVault: payrollVault }).rpc();
+    const verified = await verifiedPromise;
+
+    const payrollAccount = await program.account.payroll.fetch(payrollPda);
+    expect(verified.vault.toBase58()).to.equal(payrollVault.toBase58());
+    expect(verified.mint.toBase58()).to.equal(mint.toBase58());
+    expect(verified.authority.toBase58()).to.equal(payrollVault.toBase58());
+    expect(verified.totalFunds.toNumber()).to.equal(payrollAccount.totalFunds.toNumber());
+
+    // Another payroll's vault is a valid token account of the right mint, but not this vault
+    await expectAnchorError(
+      program.methods
+        .verifyVault()
+        .accountsPartial({ payroll: payrollPda, payrollVault: vaultPdaFor(payrollPdaFor("capped_payroll")) })
+        .rpc(),
+      "VaultAddressMismatch"
+    );
+  });
+
+  it("Records a payment attempt that would fail", async () => {
+    const payrollPda = payrollPdaFor(payrollId);
+    const employeePda = employeePdaFor(payrollPda, employeeId);
+
+    // The employee was paid above, so the next attempt is too soon
+    const attemptPromise = awaitEvent("paymentAttemptFailed");
+    await program.methods
+      .recordPaymentAttempt()
+      .accountsPartial({
+        payroll: payrollPda,
+        employee: employeePda,
+        caller: authority.publicKey,
+        priceUpdate: null,
+      })
+      .signers([authority])
+      .rpc();
+    const attempt = await attemptPromise;
+
+    expect(attempt.reason).to.deep.equal({ tooSoon: {} });
+    const employeeAccount = await program.account.employee.fetch(employeePda);
+    expect(employeeAccount.failedAttempts).to.equal(1);
+  });
+
+  it("Suspends an employee after repeated underfunded due dates", async () => {
+    const underfundedPayroll = payrollPdaFor("underfunded_payroll");
+    const underfundedEmployee = employeePdaFor(underfundedPayroll, "emp_underfunded");
+    const configAccounts = { payroll: underfundedPayroll, authority: authority.publicKey };
+
+    await program.methods
+      .initializePayroll("underfunded_payroll", 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: underfundedPayroll,
+        payrollVault: vaultPdaFor(underfundedPayroll),
+        authority: authority.publicKey,
+        paymentToken: mint,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
       })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .addEmployee("emp_underfunded", new anchor.BN(1000), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: underfundedPayroll,
+        employee: underfundedEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods.setFundingFailureThreshold(2).accountsPartial(configAccounts).signers([authority]).rpc();
+
+    const recordAttempt = () =>
+      program.methods
+        .recordPaymentAttempt()
+        .accountsPartial({
+          payroll: underfundedPayroll,
+          employee: underfundedEmployee,
+          caller: authority.publicKey,
+          priceUpdate: null,
+        })
+        .signers([authority])
+        .rpc();
+
+    // Recording the same missed due date twice counts it once
+    await recordAttempt();
+    await recordAttempt();
+    let employeeAccount = await program.account.employee.fetch(underfundedEmployee);
+    expect(employeeAccount.consecutiveFundingFailures).to.equal(1);
+    expect(employeeAccount.isActive).to.be.true;
+
+    await program.methods.setFundingFailureThreshold(1).accountsPartial(configAccounts).signers([authority]).rpc();
+    const suspendedPromise = awaitEvent("employeeAutoSuspended");
+    await recordAttempt();
+    const suspended = await suspendedPromise;
+    expect(suspended.consecutiveFundingFailures).to.equal(1);
+    employeeAccount = await program.account.employee.fetch(underfundedEmployee);
+    expect(employeeAccount.isActive).to.be.false;
+
+    await program.methods
+      .reactivateEmployee()
+      .accountsPartial({ payroll: underfundedPayroll, employee: underfundedEmployee, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    employeeAccount = await program.account.employee.fetch(underfundedEmployee);
+    expect(employeeAccount.isActive).to.be.true;
+    expect(employeeAccount.consecutiveFundingFailures).to.equal(0);
+  });
+
+  it("Rejects add_employee from a signer that does not own the payroll", async () => {
+    const payrollPda = payrollPdaFor(payrollId);
+    const stranger = await newFundedKeypair();
+    const intruderId = "emp_intruder";
+
+    await expectAnchorError(
+      program.methods
+        .addEmployee(intruderId, new anchor.BN(100000000), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+        .accountsPartial({
+          payroll: payrollPda,
+          employee: employeePdaFor(payrollPda, intruderId),
+          authority: stranger.publicKey,
+          employeeWallet: employeeTokenAccount,
+          paymentToken: mint,
+        })
+        .signers([stranger])
+        .rpc(),
+      "Unauthorized"
+    );
+  });
+
+  it("Rejects empty, whitespace-only and mixed-case ids", async () => {
+    const payrollPda = payrollPdaFor(payrollId);
+
+    for (const badId of ["", "   ", "Emp_001"]) {
+      await expectAnchorError(
+        program.methods
+          .addEmployee(badId, new anchor.BN(100000000), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+          .accountsPartial({
+            payroll: payrollPda,
+            employee: employeePdaFor(payrollPda, badId),
+            authority: authority.publicKey,
+            employeeWallet: employeeTokenAccount,
+            paymentToken: mint,
+          })
+          .signers([authority])
+          .rpc(),
+        "InvalidEmployeeId"
+      );
+    }
+
+    for (const badId of ["", "   "]) {
+      const badPayroll = payrollPdaFor(badId);
+      await expectAnchorError(
+        program.methods
+          .initializePayroll(badId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+          .accountsPartial({
+            payroll: badPayroll,
+            payrollVault: vaultPdaFor(badPayroll),
+            authority: authority.publicKey,
+            paymentToken: mint,
+            tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+            authorityTokenAccount: null,
+          })
+          .signers([authority])
+          .rpc(),
+        "InvalidPayrollId"
+      );
+    }
+  });
+
+  it("Caps payroll ids at the 32-byte seed limit", async () => {
+    const longestId = "p".repeat(32);
+    const longestPayroll = payrollPdaFor(longestId);
+    await program.methods
+      .initializePayroll(longestId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: longestPayroll,
+        payrollVault: vaultPdaFor(longestPayroll),
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+    expect((await program.account.payroll.fetch(longestPayroll)).payrollId).to.equal(longestId);
+
+    // One byte more can't seed a PDA, in the client or on-chain
+    const tooLongId = "p".repeat(33);
+    expect(() => payrollPdaFor(tooLongId)).to.throw();
+    const bogusPayroll = Keypair.generate().publicKey;
+    let caught: any;
+    try {
+      await program.methods
+        .initializePayroll(tooLongId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+        .accountsPartial({
+          payroll: bogusPayroll,
+          payrollVault: vaultPdaFor(bogusPayroll),
+          authority: authority.publicKey,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          authorityTokenAccount: null,
+        })
+        .signers([authority])
+        .rpc();
+    } catch (err) {
+      caught = err;
+    }
+    expect(caught, "expected a 33-byte payroll id to be rejected").to.exist;
+    expect(await provider.connection.getAccountInfo(bogusPayroll)).to.be.null;
+  });
+
+  it("Requires the authority's token account for an initial deposit", async () => {
+    const depositPayroll = payrollPdaFor("deposit_required_payroll");
+    await expectAnchorError(
+      program.methods
+        .initializePayroll("deposit_required_payroll", 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(100))
+        .accountsPartial({
+          payroll: depositPayroll,
+          payrollVault: vaultPdaFor(depositPayroll),
+          authority: authority.publicKey,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          authorityTokenAccount: null,
+        })
+        .signers([authority])
+        .rpc(),
+      "DepositAccountRequired"
+    );
+    expect(await provider.connection.getAccountInfo(depositPayroll)).to.be.null;
+  });
+
+  it("Rejects pause and resume from a signer that does not own the payroll", async () => {
+    const payrollPda = payrollPdaFor(payrollId);
+    const stranger = await newFundedKeypair();
+
+    await expectAnchorError(
+      program.methods
+        .pausePayroll()
+        .accountsPartial({ payroll: payrollPda, authority: stranger.publicKey })
+        .signers([stranger])
+        .rpc(),
+      "Unauthorized"
+    );
+    await expectAnchorError(
+      program.methods
+        .resumePayroll()
+        .accountsPartial({ payroll: payrollPda, authority: stranger.publicKey })
+        .signers([stranger])
+        .rpc(),
+      "Unauthorized"
+    );
+
+    const payrollAccount = await program.account.payroll.fetch(payrollPda);
+    expect(payrollAccount.isActive).to.be.true;
+  });
+
+  it("Enforces a cooldown between pause and resume", async () => {
+    const cooldownPayroll = payrollPdaFor("cooldown_payroll");
+    await program.methods
+      .initializePayroll("cooldown_payroll", 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: cooldownPayroll,
+        payrollVault: vaultPdaFor(cooldownPayroll),
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .setPauseCooldown(new anchor.BN(2))
+      .accountsPartial({ payroll: cooldownPayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const accounts = { payroll: cooldownPayroll, authority: authority.publicKey };
+    await program.methods.pausePayroll().accountsPartial(accounts).signers([authority]).rpc();
+    await expectAnchorError(
+      program.methods.resumePayroll().accountsPartial(accounts).signers([authority]).rpc(),
+      "PauseCooldownActive"
+    );
+
+    await new Promise((r) => setTimeout(r, 3000));
+    await program.methods.resumePayroll().accountsPartial(accounts).signers([authority]).rpc();
+    const payrollAccount = await program.account.payroll.fetch(cooldownPayroll);
+    expect(payrollAccount.isActive).to.be.true;
+
+    // Re-pausing right after the resume is held back too
+    await expectAnchorError(
+      program.methods.pausePayroll().accountsPartial(accounts).signers([authority]).rpc(),
+      "PauseCooldownActive"
+    );
+  });
+
+  it("Rejects a tax amount larger than gross pay", async () => {
+    const badTaxPayroll = payrollPdaFor("bad_tax_payroll");
+    const badTaxEmployee = employeePdaFor(badTaxPayroll, "emp_bad_tax");
+
+    await program.methods
+      .initializePayroll("bad_tax_payroll", 20000, new anchor.BN(0), { weekly: {} }, new anchor.BN(0)) // 200%
+      .accountsPartial({
+        payroll: badTaxPayroll,
+        payrollVault: vaultPdaFor(badTaxPayroll),
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .addEmployee("emp_bad_tax", new anchor.BN(1000), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: badTaxPayroll,
+        employee: badTaxEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    await expectAnchorError(
+      program.methods
+        .previewNetPay()
+        .accountsPartial({
+          payroll: badTaxPayroll,
+          employee: badTaxEmployee,
+          caller: authority.publicKey,
+          priceUpdate: null,
+        })
+        .signers([authority])
+        .rpc(),
+      "TaxExceedsSalary"
+    );
+  });
+
+  it("Previews net pay for the authority or operator without paying", async () => {
+    const previewPayroll = payrollPdaFor("preview_payroll");
+    const previewVault = vaultPdaFor(previewPayroll);
+    const previewEmployee = employeePdaFor(previewPayroll, "emp_preview");
+    const operator = await newFundedKeypair();
+    const stranger = await newFundedKeypair();
+
+    await program.methods
+      .initializePayroll("preview_payroll", 1000, new anchor.BN(0), { weekly: {} }, new anchor.BN(500)) // 10% tax rate
+      .accountsPartial({
+        payroll: previewPayroll,
+        payrollVault: previewVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: authorityTokenAccount,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .addEmployee("emp_preview", new anchor.BN(1000), new anchor.BN(50), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: previewPayroll,
+        employee: previewEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .setOperator(operator.publicKey)
+      .accountsPartial({ payroll: previewPayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const preview = (caller: Keypair) =>
+      program.methods
+        .previewNetPay()
+        .accountsPartial({
+          payroll: previewPayroll,
+          employee: previewEmployee,
+          caller: caller.publicKey,
+          priceUpdate: null,
+        })
+        .signers([caller])
+        .rpc();
+
+    const vaultBefore = await getAccount(provider.connection, previewVault);
+    for (const caller of [authority, operator]) {
+      const previewPromise = awaitEvent("netPayPreview");
+      await preview(caller);
+      const event = await previewPromise;
+      expect(event.gross.toNumber()).to.equal(1000);
+      expect(event.tax.toNumber()).to.equal(100);
+      expect(event.deductions.toNumber()).to.equal(50);
+      expect(event.net.toNumber()).to.equal(850);
+    }
+    await expectAnchorError(preview(stranger), "Unauthorized");
+
+    // Nothing moved and the schedule is untouched
+    const vaultAfter = await getAccount(provider.connection, previewVault);
+    expect(vaultAfter.amount).to.equal(vaultBefore.amount);
+    const employeeAccount = await program.account.employee.fetch(previewEmployee);
+    expect(employeeAccount.lastPayment.toNumber()).to.equal(0);
+    expect(employeeAccount.paymentCount.toNumber()).to.equal(0);
+  });
+
+  it("Applies the configured tax rounding mode", async () => {
+    const roundingPayrollId = "rounding_payroll";
+    const roundingPayroll = payrollPdaFor(roundingPayrollId);
+    const roundingEmployeeId = "emp_rounding";
+    const roundingEmployee = employeePdaFor(roundingPayroll, roundingEmployeeId);
+
+    await program.methods
+      .initializePayroll(roundingPayrollId, 1000, new anchor.BN(0), { weekly: {} }, new anchor.BN(0)) // 10% tax rate
+      .accountsPartial({
+        payroll: roundingPayroll,
+        payrollVault: vaultPdaFor(roundingPayroll),
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    // 10% of 15 is 1.5: floor = 1, ceil = 2, round = 2
+    await program.methods
+      .addEmployee(roundingEmployeeId, new anchor.BN(15), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: roundingPayroll,
+        employee: roundingEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    const cases: [any, number][] = [
+      [{ floor: {} }, 1],
+      [{ ceil: {} }, 2],
+      [{ round: {} }, 2],
+    ];
+    for (const [roundingMode, expectedTax] of cases) {
+      await program.methods
+        .setRoundingMode(roundingMode)
+        .accountsPartial({ payroll: roundingPayroll, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+      const previewPromise = awaitEvent("netPayPreview");
+      await program.methods
+        .previewNetPay()
+        .accountsPartial({
+          payroll: roundingPayroll,
+          employee: roundingEmployee,
+          caller: authority.publicKey,
+          priceUpdate: null,
+        })
+        .signers([authority])
+        .rpc();
+      const preview = await previewPromise;
+
+      expect(preview.tax.toNumber()).to.equal(expectedTax);
+      expect(preview.net.toNumber()).to.equal(15 - expectedTax);
+    }
+  });
+
+  it("Taxes only pay above the tax-free allowance", async () => {
+    const allowancePayroll = payrollPdaFor("allowance_payroll");
+
+    await program.methods
+      .initializePayroll("allowance_payroll", 1000, new anchor.BN(0), { weekly: {} }, new anchor.BN(0)) // 10% tax rate
+      .accountsPartial({
+        payroll: allowancePayroll,
+        payrollVault: vaultPdaFor(allowancePayroll),
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .setTaxFreeAllowance(new anchor.BN(500))
+      .accountsPartial({ payroll: allowancePayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    // [employee id, salary, expected tax]
+    const cases: [string, number, number][] = [
+      ["emp_below_allowance", 400, 0],
+      ["emp_above_allowance", 1500, 100],
+    ];
+    for (const [id, salary, expectedTax] of cases) {
+      const allowanceEmployee = employeePdaFor(allowancePayroll, id);
+      await program.methods
+        .addEmployee(id, new anchor.BN(salary), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+        .accountsPartial({
+          payroll: allowancePayroll,
+          employee: allowanceEmployee,
+          authority: authority.publicKey,
+          employeeWallet: employeeTokenAccount,
+          paymentToken: mint,
+        })
+        .signers([authority])
+        .rpc();
+
+      const previewPromise = awaitEvent("netPayPreview");
+      await program.methods
+        .previewNetPay()
+        .accountsPartial({
+          payroll: allowancePayroll,
+          employee: allowanceEmployee,
+          caller: authority.publicKey,
+          priceUpdate: null,
+        })
+        .signers([authority])
+        .rpc();
+      const preview = await previewPromise;
+
+      expect(preview.tax.toNumber()).to.equal(expectedTax);
+      expect(preview.net.toNumber()).to.equal(salary - expectedTax);
+    }
+  });
+
+  it("Requires a second approval for payments above the threshold", async () => {
+    const approvalPayrollId = "approval_payroll";
+    const approvalPayroll = payrollPdaFor(approvalPayrollId);
+    const approvalVault = vaultPdaFor(approvalPayroll);
+    const approvalEmployeeId = "emp_approval";
+    const approvalEmployee = employeePdaFor(approvalPayroll, approvalEmployeeId);
+    const approver = await newFundedKeypair();
+
+    await program.methods
+      .initializePayroll(approvalPayrollId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: approvalPayroll,
+        payrollVault: approvalVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .addEmployee(approvalEmployeeId, new anchor.BN(1000), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: approvalPayroll,
+        employee: approvalEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .depositFunds(new anchor.BN(10000))
+      .accountsPartial({
+        payroll: approvalPayroll,
+        payrollVault: approvalVault,
+        nativeVault: null,
+        authority: authority.publicKey,
+        authorityTokenAccount: authorityTokenAccount,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .setApprovalPolicy(approver.publicKey, new anchor.BN(500))
+      .accountsPartial({ payroll: approvalPayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const [approvalPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("approval"), approvalEmployee.toBuffer(), new anchor.BN(0).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const processAccounts = {
+      payroll: approvalPayroll,
+      employee: approvalEmployee,
+      payrollVault: approvalVault,
+      nativeVault: null,
+      employeeWallet: employeeTokenAccount,
+      nativeWallet: null,
+      paymentToken: mint,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      priceUpdate: null,
+      payStub: null,
+      payer: null,
+      systemProgram: null,
+      taxAccount: null,
+      platformAccount: null,
+      vaultAuthority: null,
+      memoProgram: null,
+      payHistory: null,
+      fundingSource: null,
+      authority: null,
+    };
+
+    await expectAnchorError(
+      program.methods
+        .processPayment(Array.from(randomBytes(16)), "", null)
+        .accountsPartial({ ...processAccounts, approval: null, approver: null })
+        .rpc(),
+      "ApprovalRequired"
+    );
+
+    await program.methods
+      .approvePayment(new anchor.BN(1000))
+      .accountsPartial({
+        payroll: approvalPayroll,
+        employee: approvalEmployee,
+        approval: approvalPda,
+        approver: approver.publicKey,
+      })
+      .signers([approver])
+      .rpc();
+
+    await program.methods
+      .processPayment(Array.from(randomBytes(16)), "", null)
+      .accountsPartial({ ...processAccounts, approval: approvalPda, approver: approver.publicKey })
+      .rpc();
+
+    const payrollAccount = await program.account.payroll.fetch(approvalPayroll);
+    expect(payrollAccount.totalFunds.toNumber()).to.equal(9000);
+    expect(await program.account.approval.fetchNullable(approvalPda)).to.be.null;
+  });
+
+  it("Releases vested compensation after the cliff", async () => {
+    const vestingPayrollId = "vesting_payroll";
+    const vestingPayroll = payrollPdaFor(vestingPayrollId);
+    const vestingVault = vaultPdaFor(vestingPayroll);
+    const vestingEmployeeId = "emp_vesting";
+    const vestingEmployee = employeePdaFor(vestingPayroll, vestingEmployeeId);
+    const [vestingPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vesting"), vestingEmployee.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .initializePayroll(vestingPayrollId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: vestingPayroll,
+        payrollVault: vestingVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .addEmployee(vestingEmployeeId, new anchor.BN(0), new anchor.BN(0), { monthly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: vestingPayroll,
+        employee: vestingEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .depositFunds(new anchor.BN(5000))
+      .accountsPartial({
+        payroll: vestingPayroll,
+        payrollVault: vestingVault,
+        nativeVault: null,
+        authority: authority.publicKey,
+        authorityTokenAccount: authorityTokenAccount,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    // Schedule started and finished in the past, so everything has vested
+    const now = Math.floor(Date.now() / 1000);
+    await program.methods
+      .createVesting(new anchor.BN(3000), new anchor.BN(now - 1000), new anchor.BN(now - 500), new anchor.BN(600))
+      .accountsPartial({
+        payroll: vestingPayroll,
+        employee: vestingEmployee,
+        vesting: vestingPda,
+        authority: authority.publicKey,
+      })
+      .signers([authority])
+      .rpc();
+
+    const claimAccounts = {
+      payroll: vestingPayroll,
+      employee: vestingEmployee,
+      vesting: vestingPda,
+      payrollVault: vestingVault,
+      employeeWallet: employeeTokenAccount,
+      paymentToken: mint,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+    };
+    await program.methods.claimVested().accountsPartial(claimAccounts).rpc();
+
+    const vestingAccount = await program.account.vestingSchedule.fetch(vestingPda);
+    expect(vestingAccount.claimed.toNumber()).to.equal(3000);
+
+    await expectAnchorError(
+      program.methods.claimVested().accountsPartial(claimAccounts).rpc(),
+      "NothingToClaim"
+    );
+  });
+
+  it("Rejects a retried payment with the same idempotency key", async () => {
+    const retryPayrollId = "retry_payroll";
+    const retryPayroll = payrollPdaFor(retryPayrollId);
+    const retryVault = vaultPdaFor(retryPayroll);
+    const retryEmployeeId = "emp_retry";
+    const retryEmployee = employeePdaFor(retryPayroll, retryEmployeeId);
+
+    await program.methods
+      .initializePayroll(retryPayrollId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: retryPayroll,
+        payrollVault: retryVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .addEmployee(retryEmployeeId, new anchor.BN(100), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: retryPayroll,
+        employee: retryEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    // A one-second interval lets the same test make a second, legitimate payment
+    await program.methods
+      .setPaymentIntervalOverride(new anchor.BN(1))
+      .accountsPartial({ payroll: retryPayroll, employee: retryEmployee, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .depositFunds(new anchor.BN(1000))
+      .accountsPartial({
+        payroll: retryPayroll,
+        payrollVault: retryVault,
+        nativeVault: null,
+        authority: authority.publicKey,
+        authorityTokenAccount: authorityTokenAccount,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    const processAccounts = {
+      payroll: retryPayroll,
+      employee: retryEmployee,
+      payrollVault: retryVault,
+      nativeVault: null,
+      employeeWallet: employeeTokenAccount,
+      nativeWallet: null,
+      paymentToken: mint,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      priceUpdate: null,
+      payStub: null,
+      payer: null,
+      systemProgram: null,
+      taxAccount: null,
+      platformAccount: null,
+      vaultAuthority: null,
+      memoProgram: null,
+      approval: null,
+      approver: null,
+      payHistory: null,
+      fundingSource: null,
+      authority: null,
+    };
+    const key = Array.from(randomBytes(16));
+    await program.methods.processPayment(key, "", null).accountsPartial(processAccounts).rpc();
+
+    await new Promise((r) => setTimeout(r, 2000));
+    await expectAnchorError(
+      program.methods.processPayment(key, "", null).accountsPartial(processAccounts).rpc(),
+      "DuplicatePayment"
+    );
+
+    await program.methods
+      .processPayment(Array.from(randomBytes(16)), "", null)
+      .accountsPartial(processAccounts)
+      .rpc();
+
+    const employeeAccount = await program.account.employee.fetch(retryEmployee);
+    expect(employeeAccount.paymentCount.toNumber()).to.equal(2);
+  });
+
+  it("Keeps strict schedules on a fixed cadence across cycles", async () => {
+    const cadencePayrollId = "cadence_payroll";
+    const cadencePayroll = payrollPdaFor(cadencePayrollId);
+    const cadenceVault = vaultPdaFor(cadencePayroll);
+    const cadenceEmployeeId = "emp_cadence";
+    const cadenceEmployee = employeePdaFor(cadencePayroll, cadenceEmployeeId);
+    const interval = 2;
+
+    await program.methods
+      .initializePayroll(cadencePayrollId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: cadencePayroll,
+        payrollVault: cadenceVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .addEmployee(cadenceEmployeeId, new anchor.BN(10), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: cadencePayroll,
+        employee: cadenceEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .setPaymentIntervalOverride(new anchor.BN(interval))
+      .accountsPartial({ payroll: cadencePayroll, employee: cadenceEmployee, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .depositFunds(new anchor.BN(1000))
+      .accountsPartial({
+        payroll: cadencePayroll,
+        payrollVault: cadenceVault,
+        nativeVault: null,
+        authority: authority.publicKey,
+        authorityTokenAccount: authorityTokenAccount,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    const pay = () =>
+      program.methods
+        .processPayment(Array.from(randomBytes(16)), "", null)
+        .accountsPartial({
+          payroll: cadencePayroll,
+          employee: cadenceEmployee,
+          payrollVault: cadenceVault,
+          nativeVault: null,
+          employeeWallet: employeeTokenAccount,
+          nativeWallet: null,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          priceUpdate: null,
+          payStub: null,
+          payer: null,
+          systemProgram: null,
+          taxAccount: null,
+          platformAccount: null,
+          vaultAuthority: null,
+          memoProgram: null,
+          approval: null,
+          approver: null,
+          payHistory: null,
+          fundingSource: null,
+          authority: null,
+        })
+        .rpc();
+
+    await pay();
+    const anchorTs = (await program.account.employee.fetch(cadenceEmployee)).lastPayment.toNumber();
+
+    // Each payment lands late, but last_payment stays on the original boundaries
+    for (let cycle = 0; cycle < 3; cycle++) {
+      await new Promise((r) => setTimeout(r, (interval + 1) * 1000));
+      await pay();
+      const lastPayment = (await program.account.employee.fetch(cadenceEmployee)).lastPayment.toNumber();
+      expect((lastPayment - anchorTs) % interval).to.equal(0);
+    }
+  });
+
+  it("Catches up missed periods in whole intervals, up to the cap", async () => {
+    const catchupId = "catchup_payroll";
+    const catchupPayroll = payrollPdaFor(catchupId);
+    const catchupVault = vaultPdaFor(catchupPayroll);
+    const catchupEmployee = employeePdaFor(catchupPayroll, "emp_catchup");
+    const interval = 1;
+
+    await program.methods
+      .initializePayroll(catchupId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(1000))
+      .accountsPartial({
+        payroll: catchupPayroll,
+        payrollVault: catchupVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: authorityTokenAccount,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .addEmployee("emp_catchup", new anchor.BN(10), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: catchupPayroll,
+        employee: catchupEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .setPaymentIntervalOverride(new anchor.BN(interval))
+      .accountsPartial({ payroll: catchupPayroll, employee: catchupEmployee, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    await expectAnchorError(
+      program.methods
+        .setMaxCatchupPeriods(0)
+        .accountsPartial({ payroll: catchupPayroll, authority: authority.publicKey })
+        .signers([authority])
+        .rpc(),
+      "InvalidCatchupPeriods"
+    );
+    await program.methods
+      .setMaxCatchupPeriods(3)
+      .accountsPartial({ payroll: catchupPayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const pay = async () => {
+      const paidPromise = awaitEvent("paymentProcessed");
+      await program.methods
+        .processPayment(Array.from(randomBytes(16)), "", null)
+        .accountsPartial({
+          payroll: catchupPayroll,
+          employee: catchupEmployee,
+          payrollVault: catchupVault,
+          nativeVault: null,
+          employeeWallet: employeeTokenAccount,
+          nativeWallet: null,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          priceUpdate: null,
+          payStub: null,
+          payer: null,
+          systemProgram: null,
+          taxAccount: null,
+          platformAccount: null,
+          vaultAuthority: null,
+          memoProgram: null,
+          approval: null,
+          approver: null,
+          payHistory: null,
+          fundingSource: null,
+          authority: null,
+        })
+        .rpc();
+      return paidPromise;
+    };
+
+    await pay();
+    const first = (await program.account.employee.fetch(catchupEmployee)).lastPayment.toNumber();
+    await new Promise((r) => setTimeout(r, 6 * interval * 1000));
+
+    // Five or more periods are owed, but one call pays at most three
+    const caughtUp = await pay();
+    expect(caughtUp.periodsPaid.toNumber()).to.equal(3);
+    expect(caughtUp.grossSalary.toNumber()).to.equal(10);
+    expect(caughtUp.netPay.toNumber()).to.equal(30);
+    let lastPayment = (await program.account.employee.fetch(catchupEmployee)).lastPayment.toNumber();
+    expect(lastPayment).to.equal(first + 3 * interval);
+
+    // The rest stays payable, still on whole-interval boundaries
+    const rest = await pay();
+    expect(rest.periodsPaid.toNumber()).to.be.at.least(1);
+    lastPayment = (await program.account.employee.fetch(catchupEmployee)).lastPayment.toNumber();
+    expect(lastPayment).to.equal(first + (3 + rest.periodsPaid.toNumber()) * interval);
+    const payrollAccount = await program.account.payroll.fetch(catchupPayroll);
+    expect(payrollAccount.totalFunds.toNumber()).to.equal(1000 - 10 * (4 + rest.periodsPaid.toNumber()));
+  });
+
+  it("Keeps periods beyond the catch-up cap payable on a non-strict schedule", async () => {
+    const looseId = "loose_payroll";
+    const loosePayroll = payrollPdaFor(looseId);
+    const looseVault = vaultPdaFor(loosePayroll);
+    const looseEmployee = employeePdaFor(loosePayroll, "emp_loose");
+    const interval = 1;
+
+    await program.methods
+      .initializePayroll(looseId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(1000))
+      .accountsPartial({
+        payroll: loosePayroll,
+        payrollVault: looseVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: authorityTokenAccount,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .addEmployee("emp_loose", new anchor.BN(10), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: loosePayroll,
+        employee: looseEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .setPaymentIntervalOverride(new anchor.BN(interval))
+      .accountsPartial({ payroll: loosePayroll, employee: looseEmployee, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .setSchedulePolicy(false, new anchor.BN(0))
+      .accountsPartial({ payroll: loosePayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .setMaxCatchupPeriods(2)
+      .accountsPartial({ payroll: loosePayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const pay = async () => {
+      const paidPromise = awaitEvent("paymentProcessed");
+      await program.methods
+        .processPayment(Array.from(randomBytes(16)), "", null)
+        .accountsPartial({
+          payroll: loosePayroll,
+          employee: looseEmployee,
+          payrollVault: looseVault,
+          nativeVault: null,
+          employeeWallet: employeeTokenAccount,
+          nativeWallet: null,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          priceUpdate: null,
+          payStub: null,
+          payer: null,
+          systemProgram: null,
+          taxAccount: null,
+          platformAccount: null,
+          vaultAuthority: null,
+          memoProgram: null,
+          approval: null,
+          approver: null,
+          payHistory: null,
+          fundingSource: null,
+          authority: null,
+        })
+        .rpc();
+      return paidPromise;
+    };
+
+    await pay();
+    const first = (await program.account.employee.fetch(looseEmployee)).lastPayment.toNumber();
+    await new Promise((r) => setTimeout(r, 6 * interval * 1000));
+
+    // The capped run advances by the periods it paid instead of restarting from now
+    const capped = await pay();
+    expect(capped.periodsPaid.toNumber()).to.equal(2);
+    const lastPayment = (await program.account.employee.fetch(looseEmployee)).lastPayment.toNumber();
+    expect(lastPayment).to.equal(first + 2 * interval);
+
+    // So the periods still owed are paid by the next run
+    const next = await pay();
+    expect(next.periodsPaid.toNumber()).to.be.at.least(1);
+  });
+
+  it("Appends every payment to the employee's pay history", async () => {
+    const historyPayroll = payrollPdaFor("history_payroll");
+    const historyVault = vaultPdaFor(historyPayroll);
+    const historyEmployee = employeePdaFor(historyPayroll, "emp_history");
+    const [payHistory] = PublicKey.findProgramAddressSync(
+      [Buffer.from("history"), historyEmployee.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .initializePayroll("history_payroll", 1000, new anchor.BN(0), { weekly: {} }, new anchor.BN(10_000))
+      .accountsPartial({
+        payroll: historyPayroll,
+        payrollVault: historyVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: authorityTokenAccount,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .addEmployee("emp_history", new anchor.BN(1000), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: historyPayroll,
+        employee: historyEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .initPayHistory()
+      .accountsPartial({ payroll: historyPayroll, employee: historyEmployee, payHistory, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const processAccounts = {
+      payroll: historyPayroll,
+      employee: historyEmployee,
+      payrollVault: historyVault,
+      nativeVault: null,
+      employeeWallet: employeeTokenAccount,
+      nativeWallet: null,
+      paymentToken: mint,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      priceUpdate: null,
+      payStub: null,
+      payer: null,
+      systemProgram: null,
+      taxAccount: null,
+      platformAccount: null,
+      vaultAuthority: null,
+      memoProgram: null,
+      approval: null,
+      approver: null,
+      payHistory,
+      fundingSource: null,
+      authority: null,
+    };
+
+    // Once enabled, a payment can't leave the history out
+    await expectAnchorError(
+      program.methods
+        .processPayment(Array.from(randomBytes(16)), "", null)
+        .accountsPartial({ ...processAccounts, payHistory: null })
+        .rpc(),
+      "PayHistoryRequired"
+    );
+    await program.methods
+      .processPayment(Array.from(randomBytes(16)), "", null)
+      .accountsPartial(processAccounts)
+      .rpc();
+
+    const reportPromise = awaitEvent("payHistoryReported");
+    await program.methods
+      .reportPayHistory()
+      .accountsPartial({ payroll: historyPayroll, employee: historyEmployee, payHistory })
+      .rpc();
+    const report = await reportPromise;
+
+    expect(report.records).to.have.length(1);
+    expect(report.records[0].gross.toNumber()).to.equal(1000);
+    expect(report.records[0].tax.toNumber()).to.equal(100);
+    expect(report.records[0].net.toNumber()).to.equal(900);
+    expect(report.records[0].ts.toNumber()).to.be.greaterThan(0);
+  });
+
+  it("Reports lifetime payroll statistics", async () => {
+    const historyPayroll = payrollPdaFor("history_payroll");
+    const stats = async () => {
+      const statsPromise = awaitEvent("payrollStats");
+      await program.methods.emitPayrollStats().accountsPartial({ payroll: historyPayroll }).rpc();
+      return statsPromise;
+    };
+
+    // The initial deposit and the one payment above
+    const before = await stats();
+    expect(before.employeeCount.toNumber()).to.equal(1);
+    expect(before.lifetimeDeposited.toNumber()).to.equal(10_000);
+    expect(before.lifetimePaid.toNumber()).to.equal(900);
+    expect(before.totalFunds.toNumber()).to.equal(9_100);
+
+    await program.methods
+      .depositFunds(new anchor.BN(500))
+      .accountsPartial({
+        payroll: historyPayroll,
+        payrollVault: vaultPdaFor(historyPayroll),
+        nativeVault: null,
+        authority: authority.publicKey,
+        authorityTokenAccount: authorityTokenAccount,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    const after = await stats();
+    expect(after.lifetimeDeposited.toNumber()).to.equal(10_500);
+    expect(after.lifetimePaid.toNumber()).to.equal(900);
+    expect(after.totalFunds.toNumber()).to.equal(9_600);
+  });
+
+  it("Rounds net pay to whole tokens and carries the remainder", async () => {
+    const roundPayroll = payrollPdaFor("round_payroll");
+    const roundVault = vaultPdaFor(roundPayroll);
+    const roundEmployee = employeePdaFor(roundPayroll, "emp_round");
+    const interval = 3;
+    // 2 decimals, so a whole token is 100 base units
+    const centsMint = await createMint(provider.connection, authority, authority.publicKey, null, 2);
+    const funderAccount = await createAccount(provider.connection, authority, centsMint, authority.publicKey);
+    const walletAccount = await createAccount(provider.connection, authority, centsMint, employee.publicKey);
+    await mintTo(provider.connection, authority, centsMint, funderAccount, authority, 10_000);
+
+    await program.methods
+      .initializePayroll("round_payroll", 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(5_000))
+      .accountsPartial({
+        payroll: roundPayroll,
+        payrollVault: roundVault,
+        authority: authority.publicKey,
+        paymentToken: centsMint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: funderAccount,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .addEmployee("emp_round", new anchor.BN(250), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: roundPayroll,
+        employee: roundEmployee,
+        authority: authority.publicKey,
+        employeeWallet: walletAccount,
+        paymentToken: centsMint,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .setPaymentIntervalOverride(new anchor.BN(interval))
+      .accountsPartial({ payroll: roundPayroll, employee: roundEmployee, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .setRoundNetToWhole(true)
+      .accountsPartial({ payroll: roundPayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const pay = async () => {
+      const paidPromise = awaitEvent("paymentProcessed");
+      await program.methods
+        .processPayment(Array.from(randomBytes(16)), "", null)
+        .accountsPartial({
+          payroll: roundPayroll,
+          employee: roundEmployee,
+          payrollVault: roundVault,
+          nativeVault: null,
+          employeeWallet: walletAccount,
+          nativeWallet: null,
+          paymentToken: centsMint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          priceUpdate: null,
+          payStub: null,
+          payer: null,
+          systemProgram: null,
+          taxAccount: null,
+          platformAccount: null,
+          vaultAuthority: null,
+          memoProgram: null,
+          approval: null,
+          approver: null,
+          payHistory: null,
+          fundingSource: null,
+          authority: null,
+        })
+        .rpc();
+      return paidPromise;
+    };
+
+    // 2.50 due: 2 tokens paid, 0.50 carried
+    const first = await pay();
+    expect(first.netPay.toNumber()).to.equal(200);
+    expect(first.carryForward.toNumber()).to.equal(50);
+
+    // The carried 0.50 joins the next run, which may cover one or two periods
+    await new Promise((r) => setTimeout(r, (interval + 1) * 1000));
+    const second = await pay();
+    const due = 250 * second.periodsPaid.toNumber() + 50;
+    expect(second.netPay.toNumber() % 100).to.equal(0);
+    expect(second.netPay.toNumber() + second.carryForward.toNumber()).to.equal(due);
+
+    const employeeAccount = await program.account.employee.fetch(roundEmployee);
+    expect(employeeAccount.carryForward.toNumber()).to.equal(second.carryForward.toNumber());
+    const wallet = await getAccount(provider.connection, walletAccount);
+    expect(Number(wallet.amount)).to.equal(first.netPay.toNumber() + second.netPay.toNumber());
+    // Carried amounts stay in the vault's books until paid
+    const payrollAccount = await program.account.payroll.fetch(roundPayroll);
+    expect(payrollAccount.totalFunds.toNumber()).to.equal(5_000 - Number(wallet.amount));
+  });
+
+  it("Pays early on a client-supplied business day within the allowed shift", async () => {
+    const shiftPayroll = payrollPdaFor("shift_payroll");
+    const shiftVault = vaultPdaFor(shiftPayroll);
+    const shiftEmployee = employeePdaFor(shiftPayroll, "emp_shift");
+    const configAccounts = { payroll: shiftPayroll, authority: authority.publicKey };
+    // Two days, so a due date can be brought forward to the last payment itself
+    const interval = 2 * 24 * 60 * 60;
+
+    await program.methods
+      .initializePayroll("shift_payroll", 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(10_000))
+      .accountsPartial({
+        payroll: shiftPayroll,
+        payrollVault: shiftVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: authorityTokenAccount,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .addEmployee("emp_shift", new anchor.BN(1000), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: shiftPayroll,
+        employee: shiftEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .setPaymentIntervalOverride(new anchor.BN(interval))
+      .accountsPartial({ payroll: shiftPayroll, employee: shiftEmployee, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const processAccounts = {
+      payroll: shiftPayroll,
+      employee: shiftEmployee,
+      payrollVault: shiftVault,
+      nativeVault: null,
+      employeeWallet: employeeTokenAccount,
+      nativeWallet: null,
+      paymentToken: mint,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      priceUpdate: null,
+      payStub: null,
+      payer: null,
+      systemProgram: null,
+      taxAccount: null,
+      platformAccount: null,
+      vaultAuthority: null,
+      memoProgram: null,
+      approval: null,
+      approver: null,
+      payHistory: null,
+      fundingSource: null,
+      authority: null,
+    };
+    const pay = (adjustedPayTs: anchor.BN | null) =>
+      program.methods
+        .processPayment(Array.from(randomBytes(16)), "", adjustedPayTs)
+        .accountsPartial(processAccounts)
+        .rpc();
+
+    await pay(null);
+    const lastPayment = (await program.account.employee.fetch(shiftEmployee)).lastPayment.toNumber();
+    const dueTs = lastPayment + interval;
+
+    await expectAnchorError(pay(new anchor.BN(lastPayment)), "BusinessDayShiftDisabled");
+    await expectAnchorError(
+      program.methods.setBusinessDayShift(false, new anchor.BN(60)).accountsPartial(configAccounts).signers([authority]).rpc(),
+      "InvalidBusinessDayShift"
+    );
+    await expectAnchorError(
+      program.methods
+        .setBusinessDayShift(true, new anchor.BN(4 * 24 * 60 * 60))
+        .accountsPartial(configAccounts)
+        .signers([authority])
+        .rpc(),
+      "InvalidBusinessDayShift"
+    );
+
+    await program.methods
+      .setBusinessDayShift(true, new anchor.BN(interval))
+      .accountsPartial(configAccounts)
+      .signers([authority])
+      .rpc();
+
+    // Neither after the due date nor further back than the allowed shift
+    await expectAnchorError(pay(new anchor.BN(dueTs + 1)), "InvalidAdjustedPayTs");
+    await expectAnchorError(pay(new anchor.BN(lastPayment - 1)), "InvalidAdjustedPayTs");
+
+    const paidPromise = awaitEvent("paymentProcessed");
+    await pay(new anchor.BN(lastPayment));
+    const paid = await paidPromise;
+    expect(paid.periodsPaid.toNumber()).to.equal(1);
+    // Recorded against the due date, so the cadence doesn't move earlier
+    const employeeAccount = await program.account.employee.fetch(shiftEmployee);
+    expect(employeeAccount.lastPayment.toNumber()).to.equal(dueTs);
+    // A last payment ahead of the clock by the shift still reads as not yet due
+    await expectAnchorError(pay(null), "PaymentTooSoon");
+  });
+
+  it("Tops the vault up from a delegated funding source", async () => {
+    const topUpPayroll = payrollPdaFor("topup_payroll");
+    const topUpVault = vaultPdaFor(topUpPayroll);
+    const topUpEmployee = employeePdaFor(topUpPayroll, "emp_topup");
+    const fundingAccount = await createAccount(
+      provider.connection,
+      authority,
+      mint,
+      authority.publicKey,
+      Keypair.generate()
+    );
+    await mintTo(provider.connection, authority, mint, fundingAccount, authority, 3000);
+
+    await program.methods
+      .initializePayroll("topup_payroll", 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: topUpPayroll,
+        payrollVault: topUpVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .addEmployee("emp_topup", new anchor.BN(1000), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: topUpPayroll,
+        employee: topUpEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .setFundingSource(fundingAccount)
+      .accountsPartial({ payroll: topUpPayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const pay = () =>
+      program.methods
+        .processPayment(Array.from(randomBytes(16)), "", null)
+        .accountsPartial({
+          payroll: topUpPayroll,
+          employee: topUpEmployee,
+          payrollVault: topUpVault,
+          nativeVault: null,
+          employeeWallet: employeeTokenAccount,
+          nativeWallet: null,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          priceUpdate: null,
+          payStub: null,
+          payer: null,
+          systemProgram: null,
+          taxAccount: null,
+          platformAccount: null,
+          vaultAuthority: null,
+          memoProgram: null,
+          approval: null,
+          approver: null,
+          payHistory: null,
+          fundingSource: fundingAccount,
+          authority: null,
+        })
+        .rpc();
+
+    // A delegation short of the gap pulls nothing
+    await approve(provider.connection, authority, fundingAccount, topUpVault, authority, 500);
+    await expectAnchorError(pay(), "FundingSourceInsufficient");
+    expect(Number((await getAccount(provider.connection, fundingAccount)).amount)).to.equal(3000);
+
+    await approve(provider.connection, authority, fundingAccount, topUpVault, authority, 1000);
+    const topUpPromise = awaitEvent("autoTopUp");
+    await pay();
+    const topUp = await topUpPromise;
+    expect(topUp.amount.toNumber()).to.equal(1000);
+    expect(topUp.fundingSource.toBase58()).to.equal(fundingAccount.toBase58());
+
+    expect(Number((await getAccount(provider.connection, fundingAccount)).amount)).to.equal(2000);
+    const payrollAccount = await program.account.payroll.fetch(topUpPayroll);
+    expect(payrollAccount.totalFunds.toNumber()).to.equal(0);
+    expect(payrollAccount.lifetimeDeposited.toNumber()).to.equal(1000);
+  });
+
+  it("Holds a first payment until the minimum tenure, then back-pays", async () => {
+    const tenurePayroll = payrollPdaFor("tenure_payroll");
+    const tenureVault = vaultPdaFor(tenurePayroll);
+    const tenureEmployee = employeePdaFor(tenurePayroll, "emp_tenure");
+    const interval = 2;
+    const minTenure = 4;
+
+    await program.methods
+      .initializePayroll("tenure_payroll", 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(10_000))
+      .accountsPartial({
+        payroll: tenurePayroll,
+        payrollVault: tenureVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: authorityTokenAccount,
+      })
+      .signers([authority])
+      .rpc();
+
+    await expectAnchorError(
+      program.methods
+        .setMinTenure(new anchor.BN(-1))
+        .accountsPartial({ payroll: tenurePayroll, authority: authority.publicKey })
+        .signers([authority])
+        .rpc(),
+      "InvalidMinTenure"
+    );
+    await program.methods
+      .setMinTenure(new anchor.BN(minTenure))
+      .accountsPartial({ payroll: tenurePayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .addEmployee("emp_tenure", new anchor.BN(100), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: tenurePayroll,
+        employee: tenureEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .setPaymentIntervalOverride(new anchor.BN(interval))
+      .accountsPartial({ payroll: tenurePayroll, employee: tenureEmployee, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const pay = async () => {
+      const paidPromise = awaitEvent("paymentProcessed");
+      await program.methods
+        .processPayment(Array.from(randomBytes(16)), "", null)
+        .accountsPartial({
+          payroll: tenurePayroll,
+          employee: tenureEmployee,
+          payrollVault: tenureVault,
+          nativeVault: null,
+          employeeWallet: employeeTokenAccount,
+          nativeWallet: null,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          priceUpdate: null,
+          payStub: null,
+          payer: null,
+          systemProgram: null,
+          taxAccount: null,
+          platformAccount: null,
+          vaultAuthority: null,
+          memoProgram: null,
+          approval: null,
+          approver: null,
+          payHistory: null,
+          fundingSource: null,
+          authority: null,
+        })
+        .rpc();
+      return paidPromise;
+    };
+
+    // Still inside probation
+    await expectAnchorError(pay(), "TenureNotMet");
+
+    const { createdAt } = await program.account.employee.fetch(tenureEmployee);
+    await new Promise((r) => setTimeout(r, (minTenure + 1) * 1000));
+
+    // Every period since onboarding is paid on the first eligible run
+    const paid = await pay();
+    const employeeAccount = await program.account.employee.fetch(tenureEmployee);
+    const elapsedPeriods = Math.floor((employeeAccount.lastPayment.toNumber() - createdAt.toNumber()) / interval);
+    expect(paid.periodsPaid.toNumber()).to.be.at.least(minTenure / interval);
+    // Capped by the default max_catchup_periods on a slow validator
+    expect(paid.periodsPaid.toNumber()).to.equal(Math.min(elapsedPeriods, 4));
+    expect(paid.netPay.toNumber()).to.equal(100 * paid.periodsPaid.toNumber());
+  });
+
+  it("Force-pays off-cycle only for the authority", async () => {
+    const forcedPayroll = payrollPdaFor("forced_payroll");
+    const forcedVault = vaultPdaFor(forcedPayroll);
+    const forcedEmployee = employeePdaFor(forcedPayroll, "emp_forced");
+    const stranger = await newFundedKeypair();
+
+    await program.methods
+      .initializePayroll("forced_payroll", 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(10_000))
+      .accountsPartial({
+        payroll: forcedPayroll,
+        payrollVault: forcedVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: authorityTokenAccount,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .addEmployee("emp_forced", new anchor.BN(1000), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: forcedPayroll,
+        employee: forcedEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    const processAccounts = {
+      payroll: forcedPayroll,
+      employee: forcedEmployee,
+      payrollVault: forcedVault,
+      nativeVault: null,
+      employeeWallet: employeeTokenAccount,
+      nativeWallet: null,
+      paymentToken: mint,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      priceUpdate: null,
+      payStub: null,
+      payer: null,
+      systemProgram: null,
+      taxAccount: null,
+      platformAccount: null,
+      vaultAuthority: null,
+      memoProgram: null,
+      approval: null,
+      approver: null,
+      payHistory: null,
+      fundingSource: null,
+      authority: null,
+    };
+
+    await program.methods.processPayment(Array.from(randomBytes(16)), "", null).accountsPartial(processAccounts).rpc();
+    await expectAnchorError(
+      program.methods.processPayment(Array.from(randomBytes(16)), "", null).accountsPartial(processAccounts).rpc(),
+      "PaymentTooSoon"
+    );
+    await expectAnchorError(
+      program.methods.forcePayment(Array.from(randomBytes(16)), "").accountsPartial(processAccounts).rpc(),
+      "Unauthorized"
+    );
+    await expectAnchorError(
+      program.methods
+        .forcePayment(Array.from(randomBytes(16)), "")
+        .accountsPartial({ ...processAccounts, authority: stranger.publicKey })
+        .signers([stranger])
+        .rpc(),
+      "Unauthorized"
+    );
+
+    const before = await program.account.employee.fetch(forcedEmployee);
+    const forcedPromise = awaitEvent("forcedPaymentProcessed");
+    await program.methods
+      .forcePayment(Array.from(randomBytes(16)), "")
+      .accountsPartial({ ...processAccounts, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    const forced = await forcedPromise;
+
+    expect(forced.periodsPaid.toNumber()).to.equal(1);
+    expect(forced.netPay.toNumber()).to.equal(1000);
+    expect(forced.authority.toBase58()).to.equal(authority.publicKey.toBase58());
+    const after = await program.account.employee.fetch(forcedEmployee);
+    expect(after.paymentCount.toNumber()).to.equal(before.paymentCount.toNumber() + 1);
+    // Paid ahead: the schedule stays put and the next due run is skipped instead
+    expect(after.lastPayment.toNumber()).to.equal(before.lastPayment.toNumber());
+    expect(after.skipNext).to.be.true;
+    expect(forced.lastPayment.toNumber()).to.equal(after.lastPayment.toNumber());
+
+    // An early regular run is still just too soon, and only one period can be paid ahead
+    await expectAnchorError(
+      program.methods.processPayment(Array.from(randomBytes(16)), "", null).accountsPartial(processAccounts).rpc(),
+      "PaymentTooSoon"
+    );
+    await expectAnchorError(
+      program.methods
+        .forcePayment(Array.from(randomBytes(16)), "")
+        .accountsPartial({ ...processAccounts, authority: authority.publicKey })
+        .signers([authority])
+        .rpc(),
+      "PaymentTooSoon"
+    );
+  });
+
+  it("Withholds and pays overtime by employee classification", async () => {
+    const classPayroll = payrollPdaFor("class_payroll");
+    const classVault = vaultPdaFor(classPayroll);
+
+    await program.methods
+      .initializePayroll("class_payroll", 1000, new anchor.BN(0), { weekly: {} }, new anchor.BN(10_000)) // 10% tax rate
+      .accountsPartial({
+        payroll: classPayroll,
+        payrollVault: classVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: authorityTokenAccount,
+      })
+      .signers([authority])
+      .rpc();
+
+    const add = async (id: string, classification: any) => {
+      const addedPromise = awaitEvent("employeeAdded");
+      await program.methods
+        .addEmployee(id, new anchor.BN(1000), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, classification)
+        .accountsPartial({
+          payroll: classPayroll,
+          employee: employeePdaFor(classPayroll, id),
+          authority: authority.publicKey,
+          employeeWallet: employeeTokenAccount,
+          paymentToken: mint,
+        })
+        .signers([authority])
+        .rpc();
+      return addedPromise;
+    };
+    const recordOvertime = (id: string, amount: number) =>
+      program.methods
+        .recordOvertime(new anchor.BN(amount))
+        .accountsPartial({
+          payroll: classPayroll,
+          employee: employeePdaFor(classPayroll, id),
+          authority: authority.publicKey,
+        })
+        .signers([authority])
+        .rpc();
+    const pay = async (id: string) => {
+      const paidPromise = awaitEvent("paymentProcessed");
+      await program.methods
+        .processPayment(Array.from(randomBytes(16)), "", null)
+        .accountsPartial({
+          payroll: classPayroll,
+          employee: employeePdaFor(classPayroll, id),
+          payrollVault: classVault,
+          nativeVault: null,
+          employeeWallet: employeeTokenAccount,
+          nativeWallet: null,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          priceUpdate: null,
+          payStub: null,
+          payer: null,
+          systemProgram: null,
+          taxAccount: null,
+          platformAccount: null,
+          vaultAuthority: null,
+          memoProgram: null,
+          approval: null,
+          approver: null,
+          payHistory: null,
+          fundingSource: null,
+          authority: null,
+        })
+        .rpc();
+      return paidPromise;
+    };
+
+    const added = await add("emp_contractor", { contractor: {} });
+    expect(added.classification).to.deep.equal({ contractor: {} });
+    await add("emp_exempt", { exempt: {} });
+    await add("emp_hourly", { nonExempt: {} });
+
+    // Contractors are paid gross, with nothing withheld
+    const contractorPaid = await pay("emp_contractor");
+    expect(contractorPaid.taxAmount.toNumber()).to.equal(0);
+    expect(contractorPaid.netPay.toNumber()).to.equal(1000);
+
+    await expectAnchorError(recordOvertime("emp_exempt", 100), "OvertimeNotEligible");
+    await expectAnchorError(recordOvertime("emp_contractor", 100), "OvertimeNotEligible");
+
+    // Overtime is withheld like salary and paid once
+    await recordOvertime("emp_hourly", 200);
+    const hourlyPaid = await pay("emp_hourly");
+    expect(hourlyPaid.taxAmount.toNumber()).to.equal(100);
+    expect(hourlyPaid.overtime.toNumber()).to.equal(200);
+    expect(hourlyPaid.overtimeTax.toNumber()).to.equal(20);
+    expect(hourlyPaid.netPay.toNumber()).to.equal(900 + 180);
+    const hourly = await program.account.employee.fetch(employeePdaFor(classPayroll, "emp_hourly"));
+    expect(hourly.overtimePay.toNumber()).to.equal(0);
+  });
+
+  it("Counts accrued overtime in the funding preflights", async () => {
+    const overtimeId = "overtime_funds_payroll";
+    const overtimePayroll = payrollPdaFor(overtimeId);
+    const overtimeVault = vaultPdaFor(overtimePayroll);
+    const overtimeEmployee = employeePdaFor(overtimePayroll, "emp_overtime_funds");
+
+    await program.methods
+      .initializePayroll(overtimeId, 1000, new anchor.BN(0), { weekly: {} }, new anchor.BN(1000)) // 10% tax rate
+      .accountsPartial({
+        payroll: overtimePayroll,
+        payrollVault: overtimeVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: authorityTokenAccount,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .addEmployee("emp_overtime_funds", new anchor.BN(500), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { nonExempt: {} })
+      .accountsPartial({
+        payroll: overtimePayroll,
+        employee: overtimeEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    // Salary alone nets 450 and fits the 1000 funded; 630 of net overtime does not
+    await program.methods
+      .recordOvertime(new anchor.BN(700))
+      .accountsPartial({ payroll: overtimePayroll, employee: overtimeEmployee, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const reportPromise = awaitEvent("solvencyReport");
+    await program.methods
+      .checkSolvency()
+      .accountsPartial({ payroll: overtimePayroll, priceUpdate: null })
+      .remainingAccounts([{ pubkey: overtimeEmployee, isSigner: false, isWritable: false }])
+      .rpc();
+    const report = await reportPromise;
+    expect(report.totalObligations.toNumber()).to.equal(450 + 630);
+    expect(report.isSolvent).to.be.false;
+
+    const attemptPromise = awaitEvent("paymentAttemptFailed");
+    await program.methods
+      .recordPaymentAttempt()
+      .accountsPartial({
+        payroll: overtimePayroll,
+        employee: overtimeEmployee,
+        caller: authority.publicKey,
+        priceUpdate: null,
+      })
+      .signers([authority])
+      .rpc();
+    expect((await attemptPromise).reason).to.deep.equal({ insufficientFunds: {} });
+
+    await expectAnchorError(
+      program.methods
+        .processPayment(Array.from(randomBytes(16)), "", null)
+        .accountsPartial({
+          payroll: overtimePayroll,
+          employee: overtimeEmployee,
+          payrollVault: overtimeVault,
+          nativeVault: null,
+          employeeWallet: employeeTokenAccount,
+          nativeWallet: null,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          priceUpdate: null,
+          payStub: null,
+          payer: null,
+          systemProgram: null,
+          taxAccount: null,
+          platformAccount: null,
+          vaultAuthority: null,
+          memoProgram: null,
+          approval: null,
+          approver: null,
+          payHistory: null,
+          fundingSource: null,
+          authority: null,
+        })
+        .rpc(),
+      "InsufficientFunds"
+    );
+  });
+
+  it("Skips a single pay run without paying", async () => {
+    const cadencePayroll = payrollPdaFor("cadence_payroll");
+    const cadenceEmployee = employeePdaFor(cadencePayroll, "emp_cadence");
+    const processAccounts = {
+      payroll: cadencePayroll,
+      employee: cadenceEmployee,
+      payrollVault: vaultPdaFor(cadencePayroll),
+      nativeVault: null,
+      employeeWallet: employeeTokenAccount,
+      nativeWallet: null,
+      paymentToken: mint,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      priceUpdate: null,
+      payStub: null,
+      payer: null,
+      systemProgram: null,
+      taxAccount: null,
+      platformAccount: null,
+      vaultAuthority: null,
+      memoProgram: null,
+      approval: null,
+      approver: null,
+      payHistory: null,
+      fundingSource: null,
+      authority: null,
+    };
+
+    await program.methods
+      .skipNextPayment()
+      .accountsPartial({ payroll: cadencePayroll, employee: cadenceEmployee, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    await new Promise((r) => setTimeout(r, 3000));
+    const before = await program.account.payroll.fetch(cadencePayroll);
+    const skippedPromise = awaitEvent("paymentSkipped");
+    await program.methods.processPayment(Array.from(randomBytes(16)), "", null).accountsPartial(processAccounts).rpc();
+    const skipped = await skippedPromise;
+
+    const afterSkip = await program.account.payroll.fetch(cadencePayroll);
+    const employeeAccount = await program.account.employee.fetch(cadenceEmployee);
+    expect(afterSkip.totalFunds.toNumber()).to.equal(before.totalFunds.toNumber());
+    expect(employeeAccount.skipNext).to.be.false;
+    expect(skipped.lastPayment.toNumber()).to.equal(employeeAccount.lastPayment.toNumber());
+
+    // The following run pays normally
+    await new Promise((r) => setTimeout(r, 3000));
+    await program.methods.processPayment(Array.from(randomBytes(16)), "", null).accountsPartial(processAccounts).rpc();
+    const afterPay = await program.account.payroll.fetch(cadencePayroll);
+    expect(afterPay.totalFunds.toNumber()).to.be.lessThan(afterSkip.totalFunds.toNumber());
+  });
+
+  it("Aligns pay runs to the payroll pay day anchor", async () => {
+    const cadencePayroll = payrollPdaFor("cadence_payroll");
+    const cadenceEmployee = employeePdaFor(cadencePayroll, "emp_cadence");
+    const interval = 2;
+    const employeeAccount = await program.account.employee.fetch(cadenceEmployee);
+    // Pick an anchor off the employee's current boundaries
+    const payDayAnchor = employeeAccount.lastPayment.toNumber() + 1;
+
+    await program.methods
+      .setPayDayAnchor(new anchor.BN(payDayAnchor))
+      .accountsPartial({ payroll: cadencePayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const nextPaymentPromise = awaitEvent("nextPayment");
+    await program.methods
+      .nextPaymentTimestamp()
+      .accountsPartial({ payroll: cadencePayroll, employee: cadenceEmployee })
+      .rpc();
+    const nextPayment = await nextPaymentPromise;
+
+    // The next boundary is the first anchor + k * interval after last_payment
+    expect(nextPayment.nextTs.toNumber()).to.equal(payDayAnchor);
+    expect((nextPayment.nextTs.toNumber() - payDayAnchor) % interval).to.equal(0);
+  });
+
+  it("Shifts employee schedules by time spent paused", async () => {
+    const pausePayrollId = "pause_shift_payroll";
+    const pauseShiftPayroll = payrollPdaFor(pausePayrollId);
+    const pauseShiftVault = vaultPdaFor(pauseShiftPayroll);
+    const pauseShiftEmployee = employeePdaFor(pauseShiftPayroll, "emp_pause_shift");
+    const interval = 2;
+
+    await program.methods
+      .initializePayroll(pausePayrollId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: pauseShiftPayroll,
+        payrollVault: pauseShiftVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .addEmployee("emp_pause_shift", new anchor.BN(10), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: pauseShiftPayroll,
+        employee: pauseShiftEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .setPaymentIntervalOverride(new anchor.BN(interval))
+      .accountsPartial({ payroll: pauseShiftPayroll, employee: pauseShiftEmployee, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .depositFunds(new anchor.BN(100))
+      .accountsPartial({
+        payroll: pauseShiftPayroll,
+        payrollVault: pauseShiftVault,
+        nativeVault: null,
+        authority: authority.publicKey,
+        authorityTokenAccount: authorityTokenAccount,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .processPayment(Array.from(randomBytes(16)), "", null)
+      .accountsPartial({
+        payroll: pauseShiftPayroll,
+        employee: pauseShiftEmployee,
+        payrollVault: pauseShiftVault,
+        nativeVault: null,
+        employeeWallet: employeeTokenAccount,
+        nativeWallet: null,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        priceUpdate: null,
+        payStub: null,
+        payer: null,
+        systemProgram: null,
+        taxAccount: null,
+        platformAccount: null,
+        vaultAuthority: null,
+        memoProgram: null,
+        approval: null,
+        approver: null,
+        payHistory: null,
+        fundingSource: null,
+        authority: null,
+      })
+      .rpc();
+    const lastPayment = (await program.account.employee.fetch(pauseShiftEmployee)).lastPayment.toNumber();
+
+    const accounts = { payroll: pauseShiftPayroll, authority: authority.publicKey };
+    await program.methods.pausePayroll().accountsPartial(accounts).signers([authority]).rpc();
+    await new Promise((r) => setTimeout(r, 3000));
+    const resumedPromise = awaitEvent("payrollResumed");
+    await program.methods.resumePayroll().accountsPartial(accounts).signers([authority]).rpc();
+    const resumed = await resumedPromise;
+    expect(resumed.pausedSeconds.toNumber()).to.be.at.least(2);
+
+    const nextPaymentPromise = awaitEvent("nextPayment");
+    await program.methods
+      .nextPaymentTimestamp()
+      .accountsPartial({ payroll: pauseShiftPayroll, employee: pauseShiftEmployee })
+      .rpc();
+    const nextPayment = await nextPaymentPromise;
+    expect(nextPayment.nextTs.toNumber()).to.equal(lastPayment + resumed.pausedSeconds.toNumber() + interval);
+  });
+
+  it("Updates the tax rate across several payrolls at once", async () => {
+    const batchPayrolls = ["tax_batch_a", "tax_batch_b"].map((id) => payrollPdaFor(id));
+    for (const [i, id] of ["tax_batch_a", "tax_batch_b"].entries()) {
+      await program.methods
+        .initializePayroll(id, 1000, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+        .accountsPartial({
+          payroll: batchPayrolls[i],
+          payrollVault: vaultPdaFor(batchPayrolls[i]),
+          authority: authority.publicKey,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          authorityTokenAccount: null,
+        })
+        .signers([authority])
+        .rpc();
+    }
+
+    const batchPromise = awaitEvent("taxRateBatchUpdated");
+    await program.methods
+      .updateTaxRateBatch(1500)
+      .accountsPartial({ authority: authority.publicKey })
+      .remainingAccounts(batchPayrolls.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })))
+      .signers([authority])
+      .rpc();
+    const batch = await batchPromise;
+
+    expect(batch.count.toNumber()).to.equal(2);
+    expect(batch.newRate).to.equal(1500);
+    for (const payrollPda of batchPayrolls) {
+      const payrollAccount = await program.account.payroll.fetch(payrollPda);
+      expect(payrollAccount.taxRate).to.equal(1500);
+    }
+  });
+
+  it("Recovers clawbacks from net pay up to the per-period cap", async () => {
+    const clawbackPayrollId = "clawback_payroll";
+    const clawbackPayroll = payrollPdaFor(clawbackPayrollId);
+    const clawbackVault = vaultPdaFor(clawbackPayroll);
+    const clawbackEmployeeId = "emp_clawback";
+    const clawbackEmployee = employeePdaFor(clawbackPayroll, clawbackEmployeeId);
+
+    await program.methods
+      .initializePayroll(clawbackPayrollId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: clawbackPayroll,
+        payrollVault: clawbackVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .addEmployee(clawbackEmployeeId, new anchor.BN(1000), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: clawbackPayroll,
+        employee: clawbackEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .depositFunds(new anchor.BN(5000))
+      .accountsPartial({
+        payroll: clawbackPayroll,
+        payrollVault: clawbackVault,
+        nativeVault: null,
+        authority: authority.publicKey,
+        authorityTokenAccount: authorityTokenAccount,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .setClawbackPolicy(new anchor.BN(300), new anchor.BN(100))
+      .accountsPartial({ payroll: clawbackPayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .addClawback(new anchor.BN(500))
+      .accountsPartial({ payroll: clawbackPayroll, employee: clawbackEmployee, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .processPayment(Array.from(randomBytes(16)), "", null)
+      .accountsPartial({
+        payroll: clawbackPayroll,
+        employee: clawbackEmployee,
+        payrollVault: clawbackVault,
+        nativeVault: null,
+        employeeWallet: employeeTokenAccount,
+        nativeWallet: null,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        priceUpdate: null,
+        payStub: null,
+        payer: null,
+        systemProgram: null,
+        taxAccount: null,
+        platformAccount: null,
+        vaultAuthority: null,
+        memoProgram: null,
+        approval: null,
+        approver: null,
+        payHistory: null,
+        fundingSource: null,
+        authority: null,
+      })
+      .rpc();
+
+    // 1000 net pay, 300 recovered (the cap), 700 paid out
+    const payrollAccount = await program.account.payroll.fetch(clawbackPayroll);
+    const employeeAccount = await program.account.employee.fetch(clawbackEmployee);
+    expect(payrollAccount.totalFunds.toNumber()).to.equal(4300);
+    expect(employeeAccount.clawbackBalance.toNumber()).to.equal(200);
+  });
+
+  it("Requires the employee's signature for salary cuts once opted in", async () => {
+    const cutPayroll = payrollPdaFor("clawback_payroll");
+    const cutEmployee = employeePdaFor(cutPayroll, "emp_clawback");
+
+    await program.methods
+      .setCutApproval(true)
+      .accountsPartial({
+        payroll: cutPayroll,
+        employee: cutEmployee,
+        employeeWallet: employeeTokenAccount,
+        walletOwner: employee.publicKey,
+      })
+      .signers([employee])
+      .rpc();
+
+    const updateSalary = (salary: number, withConsent: boolean) =>
+      program.methods
+        .updateEmployeeSalary(new anchor.BN(salary))
+        .accountsPartial({
+          payroll: cutPayroll,
+          employee: cutEmployee,
+          authority: authority.publicKey,
+          employeeWallet: withConsent ? employeeTokenAccount : null,
+          walletOwner: withConsent ? employee.publicKey : null,
+        })
+        .signers(withConsent ? [authority, employee] : [authority])
+        .rpc();
+
+    await expectAnchorError(updateSalary(900, false), "SalaryCutNotApproved");
+    await updateSalary(900, true);
+    // Raises never need consent
+    await updateSalary(1000, false);
+
+    const employeeAccount = await program.account.employee.fetch(cutEmployee);
+    expect(employeeAccount.employeeMustApproveCuts).to.be.true;
+    expect(employeeAccount.salaryAmount.toNumber()).to.equal(1000);
+  });
+
+  it("Defers net pay below the payroll minimum payment", async () => {
+    const dustPayrollId = "min_payment_payroll";
+    const dustPayroll = payrollPdaFor(dustPayrollId);
+    const dustVault = vaultPdaFor(dustPayroll);
+    const dustEmployeeId = "emp_min_payment";
+    const dustEmployee = employeePdaFor(dustPayroll, dustEmployeeId);
+
+    await program.methods
+      .initializePayroll(dustPayrollId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: dustPayroll,
+        payrollVault: dustVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .addEmployee(dustEmployeeId, new anchor.BN(100), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: dustPayroll,
+        employee: dustEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .depositFunds(new anchor.BN(1000))
+      .accountsPartial({
+        payroll: dustPayroll,
+        payrollVault: dustVault,
+        nativeVault: null,
+        authority: authority.publicKey,
+        authorityTokenAccount: authorityTokenAccount,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .setMinPayment(new anchor.BN(500))
+      .accountsPartial({ payroll: dustPayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const deferredPromise = awaitEvent("paymentDeferred");
+    await program.methods
+      .processPayment(Array.from(randomBytes(16)), "", null)
+      .accountsPartial({
+        payroll: dustPayroll,
+        employee: dustEmployee,
+        payrollVault: dustVault,
+        nativeVault: null,
+        employeeWallet: employeeTokenAccount,
+        nativeWallet: null,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        priceUpdate: null,
+        payStub: null,
+        payer: null,
+        systemProgram: null,
+        taxAccount: null,
+        platformAccount: null,
+        vaultAuthority: null,
+        memoProgram: null,
+        approval: null,
+        approver: null,
+        payHistory: null,
+        fundingSource: null,
+        authority: null,
+      })
+      .rpc();
+    const deferred = await deferredPromise;
+
+    const payrollAccount = await program.account.payroll.fetch(dustPayroll);
+    const employeeAccount = await program.account.employee.fetch(dustEmployee);
+    expect(deferred.accumulated.toNumber()).to.equal(100);
+    expect(employeeAccount.outstandingBalance.toNumber()).to.equal(100);
+    expect(employeeAccount.lastPayment.toNumber()).to.be.greaterThan(0);
+    expect(payrollAccount.totalFunds.toNumber()).to.equal(1000);
+  });
+
+  it("Pays employer tax to the tax account on top of net pay", async () => {
+    const employerTaxPayrollId = "employer_tax_payroll";
+    const employerTaxPayroll = payrollPdaFor(employerTaxPayrollId);
+    const employerTaxVault = vaultPdaFor(employerTaxPayroll);
+    const employerTaxEmployeeId = "emp_employer_tax";
+    const employerTaxEmployee = employeePdaFor(employerTaxPayroll, employerTaxEmployeeId);
+    const taxAccount = await createAccount(
+      provider.connection,
+      authority,
+      mint,
+      Keypair.generate().publicKey
+    );
+
+    await program.methods
+      .initializePayroll(employerTaxPayrollId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: employerTaxPayroll,
+        payrollVault: employerTaxVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .addEmployee(employerTaxEmployeeId, new anchor.BN(1000), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: employerTaxPayroll,
+        employee: employerTaxEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .depositFunds(new anchor.BN(5000))
+      .accountsPartial({
+        payroll: employerTaxPayroll,
+        payrollVault: employerTaxVault,
+        nativeVault: null,
+        authority: authority.publicKey,
+        authorityTokenAccount: authorityTokenAccount,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .setEmployerTax(1000, taxAccount) // 10%
+      .accountsPartial({ payroll: employerTaxPayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .processPayment(Array.from(randomBytes(16)), "", null)
+      .accountsPartial({
+        payroll: employerTaxPayroll,
+        employee: employerTaxEmployee,
+        payrollVault: employerTaxVault,
+        nativeVault: null,
+        employeeWallet: employeeTokenAccount,
+        nativeWallet: null,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        priceUpdate: null,
+        payStub: null,
+        payer: null,
+        systemProgram: null,
+        taxAccount,
+        platformAccount: null,
+        vaultAuthority: null,
+        memoProgram: null,
+        approval: null,
+        approver: null,
+        payHistory: null,
+        fundingSource: null,
+        authority: null,
+      })
+      .rpc();
+
+    const taxBalance = await getAccount(provider.connection, taxAccount);
+    const payrollAccount = await program.account.payroll.fetch(employerTaxPayroll);
+    expect(Number(taxBalance.amount)).to.equal(100);
+    expect(payrollAccount.totalFunds.toNumber()).to.equal(3900);
+  });
+
+  it("Routes a platform fee deducted from net pay", async () => {
+    const feePayrollId = "platform_fee_payroll";
+    const feePayroll = payrollPdaFor(feePayrollId);
+    const feeVault = vaultPdaFor(feePayroll);
+    const feeEmployeeId = "emp_platform_fee";
+    const feeEmployee = employeePdaFor(feePayroll, feeEmployeeId);
+    const platformAccount = await createAccount(
+      provider.connection,
+      authority,
+      mint,
+      Keypair.generate().publicKey
+    );
+
+    await program.methods
+      .initializePayroll(feePayrollId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: feePayroll,
+        payrollVault: feeVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .addEmployee(feeEmployeeId, new anchor.BN(1000), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: feePayroll,
+        employee: feeEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .depositFunds(new anchor.BN(5000))
+      .accountsPartial({
+        payroll: feePayroll,
+        payrollVault: feeVault,
+        nativeVault: null,
+        authority: authority.publicKey,
+        authorityTokenAccount: authorityTokenAccount,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    await expectAnchorError(
+      program.methods
+        .setPlatformFee(5000, platformAccount, true)
+        .accountsPartial({ payroll: feePayroll, authority: authority.publicKey })
+        .signers([authority])
+        .rpc(),
+      "InvalidPlatformFeeConfig"
+    );
+    await program.methods
+      .setPlatformFee(200, platformAccount, true) // 2%
+      .accountsPartial({ payroll: feePayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const initialBalance = await getAccount(provider.connection, employeeTokenAccount);
+    await program.methods
+      .processPayment(Array.from(randomBytes(16)), "", null)
+      .accountsPartial({
+        payroll: feePayroll,
+        employee: feeEmployee,
+        payrollVault: feeVault,
+        nativeVault: null,
+        employeeWallet: employeeTokenAccount,
+        nativeWallet: null,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        priceUpdate: null,
+        payStub: null,
+        payer: null,
+        systemProgram: null,
+        taxAccount: null,
+        platformAccount,
+        vaultAuthority: null,
+        memoProgram: null,
+        approval: null,
+        approver: null,
+        payHistory: null,
+        fundingSource: null,
+        authority: null,
+      })
+      .rpc();
+
+    const platformBalance = await getAccount(provider.connection, platformAccount);
+    const employeeBalance = await getAccount(provider.connection, employeeTokenAccount);
+    const payrollAccount = await program.account.payroll.fetch(feePayroll);
+    expect(Number(platformBalance.amount)).to.equal(20);
+    expect(Number(employeeBalance.amount) - Number(initialBalance.amount)).to.equal(980);
+    expect(payrollAccount.totalFunds.toNumber()).to.equal(4000);
+  });
+
+  it("Splits net pay across several deposit accounts", async () => {
+    const splitPayrollId = "split_payroll";
+    const splitPayroll = payrollPdaFor(splitPayrollId);
+    const splitVault = vaultPdaFor(splitPayroll);
+    const splitEmployeeId = "emp_split";
+    const splitEmployee = employeePdaFor(splitPayroll, splitEmployeeId);
+    const checking = await createAccount(provider.connection, authority, mint, Keypair.generate().publicKey);
+    const savings = await createAccount(provider.connection, authority, mint, Keypair.generate().publicKey);
+
+    await program.methods
+      .initializePayroll(splitPayrollId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: splitPayroll,
+        payrollVault: splitVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .addEmployee(splitEmployeeId, new anchor.BN(1000), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: splitPayroll,
+        employee: splitEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .depositFunds(new anchor.BN(5000))
+      .accountsPartial({
+        payroll: splitPayroll,
+        payrollVault: splitVault,
+        nativeVault: null,
+        authority: authority.publicKey,
+        authorityTokenAccount: authorityTokenAccount,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    const splitWallets = [checking, savings].map((pubkey) => ({ pubkey, isSigner: false, isWritable: true }));
+    const splitAccounts = {
+      payroll: splitPayroll,
+      employee: splitEmployee,
+      employeeWallet: employeeTokenAccount,
+      walletOwner: employee.publicKey,
+    };
+    await expectAnchorError(
+      program.methods
+        .setDepositSplits([
+          { wallet: checking, bps: 7000 },
+          { wallet: savings, bps: 2000 },
+        ])
+        .accountsPartial(splitAccounts)
+        .remainingAccounts(splitWallets)
+        .signers([employee])
+        .rpc(),
+      "InvalidDepositSplits"
+    );
+    await program.methods
+      .setDepositSplits([
+        { wallet: checking, bps: 7000 },
+        { wallet: savings, bps: 3000 },
+      ])
+      .accountsPartial(splitAccounts)
+      .remainingAccounts(splitWallets)
+      .signers([employee])
+      .rpc();
+
+    await program.methods
+      .processPayment(Array.from(randomBytes(16)), "", null)
+      .accountsPartial({
+        payroll: splitPayroll,
+        employee: splitEmployee,
+        payrollVault: splitVault,
+        nativeVault: null,
+        employeeWallet: employeeTokenAccount,
+        nativeWallet: null,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        priceUpdate: null,
+        payStub: null,
+        payer: null,
+        systemProgram: null,
+        taxAccount: null,
+        platformAccount: null,
+        vaultAuthority: null,
+        memoProgram: null,
+        approval: null,
+        approver: null,
+        payHistory: null,
+        fundingSource: null,
+        authority: null,
+      })
+      .remainingAccounts(splitWallets)
+      .rpc();
+
+    expect(Number((await getAccount(provider.connection, checking)).amount)).to.equal(700);
+    expect(Number((await getAccount(provider.connection, savings)).amount)).to.equal(300);
+  });
+
+  it("Pays only to the employee's wallet, honouring the wallet change delay", async () => {
+    const walletPayroll = payrollPdaFor("wallet_payroll");
+    const walletVault = vaultPdaFor(walletPayroll);
+    const walletEmployee = employeePdaFor(walletPayroll, "emp_wallet");
+    const stranger = await newFundedKeypair();
+    const strangerWallet = await createAccount(provider.connection, stranger, mint, stranger.publicKey);
+    const rotatedWallet = await createAccount(
+      provider.connection,
+      employee,
+      mint,
+      employee.publicKey,
+      Keypair.generate()
+    );
+
+    await program.methods
+      .initializePayroll("wallet_payroll", 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(1000))
+      .accountsPartial({
+        payroll: walletPayroll,
+        payrollVault: walletVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: authorityTokenAccount,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .addEmployee("emp_wallet", new anchor.BN(100), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: walletPayroll,
+        employee: walletEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    const pay = (employeeWallet: PublicKey) =>
+      program.methods
+        .processPayment(Array.from(randomBytes(16)), "", null)
+        .accountsPartial({
+          payroll: walletPayroll,
+          employee: walletEmployee,
+          payrollVault: walletVault,
+          nativeVault: null,
+          employeeWallet,
+          nativeWallet: null,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          priceUpdate: null,
+          payStub: null,
+          payer: null,
+          systemProgram: null,
+          taxAccount: null,
+          platformAccount: null,
+          vaultAuthority: null,
+          memoProgram: null,
+          approval: null,
+          approver: null,
+          payHistory: null,
+          fundingSource: null,
+          authority: null,
+        })
+        .rpc();
+    const rotate = (signer: Keypair) =>
+      program.methods
+        .updateEmployeeWallet()
+        .accountsPartial({
+          payroll: walletPayroll,
+          employee: walletEmployee,
+          currentWallet: employeeTokenAccount,
+          newWallet: rotatedWallet,
+          signer: signer.publicKey,
+        })
+        .signers([signer])
+        .rpc();
+
+    // Anyone can crank a payment, but not redirect it
+    await expectAnchorError(pay(strangerWallet), "EmployeeWalletMismatch");
+
+    await program.methods
+      .setWalletChangeDelay(new anchor.BN(2))
+      .accountsPartial({ payroll: walletPayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    await expectAnchorError(rotate(stranger), "Unauthorized");
+    await rotate(employee);
+
+    // The new wallet only takes over once the delay has passed
+    await expectAnchorError(pay(rotatedWallet), "EmployeeWalletMismatch");
+    await new Promise((resolve) => setTimeout(resolve, 4000));
+    await expectAnchorError(pay(employeeTokenAccount), "EmployeeWalletMismatch");
+    await pay(rotatedWallet);
+
+    expect(Number((await getAccount(provider.connection, rotatedWallet)).amount)).to.equal(100);
+    const employeeAccount = await program.account.employee.fetch(walletEmployee);
+    expect(employeeAccount.wallet.toBase58()).to.equal(rotatedWallet.toBase58());
+    expect(employeeAccount.pendingWallet).to.be.null;
+  });
+
+  it("Rejects a payment destination of another mint", async () => {
+    const mintCheckPayroll = payrollPdaFor("mint_check_payroll");
+    const mintCheckVault = vaultPdaFor(mintCheckPayroll);
+    const mintCheckEmployee = employeePdaFor(mintCheckPayroll, "emp_mint_check");
+    const otherMint = await createMint(provider.connection, authority, authority.publicKey, null, 9);
+    const otherWallet = await createAccount(provider.connection, authority, otherMint, employee.publicKey);
+
+    await program.methods
+      .initializePayroll("mint_check_payroll", 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: mintCheckPayroll,
+        payrollVault: mintCheckVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+    // Registered as the employee's wallet, so only the mint check stands in the way
+    await program.methods
+      .addEmployee("emp_mint_check", new anchor.BN(100), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: mintCheckPayroll,
+        employee: mintCheckEmployee,
+        authority: authority.publicKey,
+        employeeWallet: otherWallet,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .depositFunds(new anchor.BN(500))
+      .accountsPartial({
+        payroll: mintCheckPayroll,
+        payrollVault: mintCheckVault,
+        nativeVault: null,
+        authority: authority.publicKey,
+        authorityTokenAccount: authorityTokenAccount,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    await expectAnchorError(
+      program.methods
+        .processPayment(Array.from(randomBytes(16)), "", null)
+        .accountsPartial({
+          payroll: mintCheckPayroll,
+          employee: mintCheckEmployee,
+          payrollVault: mintCheckVault,
+          nativeVault: null,
+          employeeWallet: otherWallet,
+          nativeWallet: null,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          priceUpdate: null,
+          payStub: null,
+          payer: null,
+          systemProgram: null,
+          taxAccount: null,
+          platformAccount: null,
+          vaultAuthority: null,
+          memoProgram: null,
+          approval: null,
+          approver: null,
+          payHistory: null,
+          fundingSource: null,
+          authority: null,
+        })
+        .rpc(),
+      "ConstraintTokenMint"
+    );
+
+    const employeeAccount = await program.account.employee.fetch(mintCheckEmployee);
+    expect(employeeAccount.paymentCount.toNumber()).to.equal(0);
+  });
+
+  it("Attaches a memo to the payment", async () => {
+    const mintCheckPayroll = payrollPdaFor("mint_check_payroll");
+    const processAccounts = {
+      payroll: mintCheckPayroll,
+      employee: employeePdaFor(mintCheckPayroll, "emp_mint_check"),
+      payrollVault: vaultPdaFor(mintCheckPayroll),
+      nativeVault: null,
+      employeeWallet: employeeTokenAccount,
+      nativeWallet: null,
+      paymentToken: mint,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      priceUpdate: null,
+      payStub: null,
+      payer: null,
+      systemProgram: null,
+      taxAccount: null,
+      platformAccount: null,
+      vaultAuthority: null,
+      approval: null,
+      approver: null,
+      payHistory: null,
+      fundingSource: null,
+      authority: null,
+      memoProgram: MEMO_PROGRAM_ID,
+    };
+
+    await expectAnchorError(
+      program.methods
+        .processPayment(Array.from(randomBytes(16)), "x".repeat(129), null)
+        .accountsPartial(processAccounts)
+        .rpc(),
+      "InvalidMemo"
+    );
+
+    const memo = "INV-2024-0042 March salary";
+    const processedPromise = awaitEvent("paymentProcessed");
+    const signature = await program.methods
+      .processPayment(Array.from(randomBytes(16)), memo, null)
+      .accountsPartial(processAccounts)
+      .rpc({ commitment: "confirmed" });
+    const processed = await processedPromise;
+    expect(processed.memo).to.equal(memo);
+
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    expect(tx.meta.logMessages.some((line) => line.includes(memo))).to.be.true;
+  });
+
+  it("Recounts employees only when the accounts back the count", async () => {
+    const mintCheckPayroll = payrollPdaFor("mint_check_payroll");
+    const employees = [employeePdaFor(mintCheckPayroll, "emp_mint_check")].map((pubkey) => ({
+      pubkey,
+      isSigner: false,
+      isWritable: false,
+    }));
+    const accounts = { payroll: mintCheckPayroll, authority: authority.publicKey };
+
+    await expectAnchorError(
+      program.methods
+        .recountEmployees(new anchor.BN(2))
+        .accountsPartial(accounts)
+        .remainingAccounts(employees)
+        .signers([authority])
+        .rpc(),
+      "EmployeeCountMismatch"
+    );
+    // An employee of another payroll doesn't count toward this one
+    await expectAnchorError(
+      program.methods
+        .recountEmployees(new anchor.BN(1))
+        .accountsPartial(accounts)
+        .remainingAccounts([
+          { pubkey: employeePdaFor(payrollPdaFor("split_payroll"), "emp_split"), isSigner: false, isWritable: false },
+        ])
+        .signers([authority])
+        .rpc(),
+      "InvalidEmployeeAccount"
+    );
+
+    const reconciledPromise = awaitEvent("employeeCountReconciled");
+    await program.methods
+      .recountEmployees(new anchor.BN(1))
+      .accountsPartial(accounts)
+      .remainingAccounts(employees)
+      .signers([authority])
+      .rpc();
+    const reconciled = await reconciledPromise;
+    expect(reconciled.new.toNumber()).to.equal(1);
+    expect((await program.account.payroll.fetch(mintCheckPayroll)).employeeCount.toNumber()).to.equal(1);
+  });
+
+  it("Refuses a recount below the employees already counted", async () => {
+    const recountPayroll = payrollPdaFor("recount_payroll");
+    const ids = ["emp_recount_1", "emp_recount_2"];
+
+    await program.methods
+      .initializePayroll("recount_payroll", 0, new anchor.BN(2), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: recountPayroll,
+        payrollVault: vaultPdaFor(recountPayroll),
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+    for (const id of ids) {
+      await program.methods
+        .addEmployee(id, new anchor.BN(100), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+        .accountsPartial({
+          payroll: recountPayroll,
+          employee: employeePdaFor(recountPayroll, id),
+          authority: authority.publicKey,
+          employeeWallet: employeeTokenAccount,
+          paymentToken: mint,
+        })
+        .signers([authority])
+        .rpc();
+    }
+
+    const recount = (counted: string[]) =>
+      program.methods
+        .recountEmployees(new anchor.BN(counted.length))
+        .accountsPartial({ payroll: recountPayroll, authority: authority.publicKey })
+        .remainingAccounts(
+          counted.map((id) => ({ pubkey: employeePdaFor(recountPayroll, id), isSigner: false, isWritable: false }))
+        )
+        .signers([authority])
+        .rpc();
+
+    // Passing only some of the employees would free a slot past max_employees
+    await expectAnchorError(recount(ids.slice(0, 1)), "EmployeeCountDecrease");
+    expect((await program.account.payroll.fetch(recountPayroll)).employeeCount.toNumber()).to.equal(2);
+
+    await recount(ids);
+    expect((await program.account.payroll.fetch(recountPayroll)).employeeCount.toNumber()).to.equal(2);
+  });
+
+  it("Rejects a batch above the compute-budgeted size before reading it", async () => {
+    const mintCheckPayroll = payrollPdaFor("mint_check_payroll");
+    // MAX_BATCH_SIZE + 1; none of these are employees, so only the size check can reject them
+    const tooMany = Array.from({ length: 26 }, () => ({
+      pubkey: Keypair.generate().publicKey,
+      isSigner: false,
+      isWritable: false,
+    }));
+
+    await expectAnchorError(
+      program.methods
+        .recountEmployees(new anchor.BN(tooMany.length))
+        .accountsPartial({ payroll: mintCheckPayroll, authority: authority.publicKey })
+        .remainingAccounts(tooMany)
+        .signers([authority])
+        .rpc(),
+      "BatchTooLarge"
+    );
+    expect((await program.account.payroll.fetch(mintCheckPayroll)).employeeCount.toNumber()).to.equal(1);
+  });
+
+  it("Blocks payment without a confidential net pay result when required", async () => {
+    const strictPayrollId = "confidential_payroll";
+    const strictPayroll = payrollPdaFor(strictPayrollId);
+    const strictVault = vaultPdaFor(strictPayroll);
+    const strictEmployee = employeePdaFor(strictPayroll, "emp_confidential");
+
+    await program.methods
+      .initializePayroll(strictPayrollId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: strictPayroll,
+        payrollVault: strictVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .addEmployee("emp_confidential", new anchor.BN(1000), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: strictPayroll,
+        employee: strictEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .depositFunds(new anchor.BN(5000))
+      .accountsPartial({
+        payroll: strictPayroll,
+        payrollVault: strictVault,
+        nativeVault: null,
+        authority: authority.publicKey,
+        authorityTokenAccount: authorityTokenAccount,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .setRequireConfidential(true)
+      .accountsPartial({ payroll: strictPayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    await expectAnchorError(
+      program.methods
+        .processPayment(Array.from(randomBytes(16)), "", null)
+        .accountsPartial({
+          payroll: strictPayroll,
+          employee: strictEmployee,
+          payrollVault: strictVault,
+          nativeVault: null,
+          employeeWallet: employeeTokenAccount,
+          nativeWallet: null,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          priceUpdate: null,
+          payStub: null,
+          payer: null,
+          systemProgram: null,
+          taxAccount: null,
+          platformAccount: null,
+          vaultAuthority: null,
+          memoProgram: null,
+          approval: null,
+          approver: null,
+          payHistory: null,
+          fundingSource: null,
+          authority: null,
+        })
+        .rpc(),
+      "ConfidentialRequired"
+    );
+    const payrollAccount = await program.account.payroll.fetch(strictPayroll);
+    expect(payrollAccount.totalFunds.toNumber()).to.equal(5000);
+  });
+
+  it("Pays from a vault owned by an external custody authority", async () => {
+    const custodyPayrollId = "custody_payroll";
+    const custodyPayroll = payrollPdaFor(custodyPayrollId);
+    const custodyVault = vaultPdaFor(custodyPayroll);
+    const custodyEmployeeId = "emp_custody";
+    const custodyEmployee = employeePdaFor(custodyPayroll, custodyEmployeeId);
+    const custodian = Keypair.generate();
+
+    await program.methods
+      .initializePayroll(custodyPayrollId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: custodyPayroll,
+        payrollVault: custodyVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .addEmployee(custodyEmployeeId, new anchor.BN(100), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: custodyPayroll,
+        employee: custodyEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .depositFunds(new anchor.BN(500))
+      .accountsPartial({
+        payroll: custodyPayroll,
+        payrollVault: custodyVault,
+        nativeVault: null,
+        authority: authority.publicKey,
+        authorityTokenAccount: authorityTokenAccount,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .setVaultAuthority(custodian.publicKey)
+      .accountsPartial({
+        payroll: custodyPayroll,
+        authority: authority.publicKey,
+        payrollVault: custodyVault,
+        currentVaultAuthority: null,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+    expect((await getAccount(provider.connection, custodyVault)).owner.toBase58()).to.equal(
+      custodian.publicKey.toBase58()
+    );
+
+    const processAccounts = {
+      payroll: custodyPayroll,
+      employee: custodyEmployee,
+      payrollVault: custodyVault,
+      nativeVault: null,
+      employeeWallet: employeeTokenAccount,
+      nativeWallet: null,
+      paymentToken: mint,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      priceUpdate: null,
+      payStub: null,
+      payer: null,
+      systemProgram: null,
+      taxAccount: null,
+      platformAccount: null,
+      approval: null,
+      approver: null,
+      payHistory: null,
+      fundingSource: null,
+      authority: null,
+      memoProgram: null,
+    };
+    // Without the custodian's co-signature the payment is rejected
+    await expectAnchorError(
+      program.methods
+        .processPayment(Array.from(randomBytes(16)), "", null)
+        .accountsPartial({ ...processAccounts, vaultAuthority: null })
+        .rpc(),
+      "InvalidVaultAuthority"
+    );
+    await program.methods
+      .processPayment(Array.from(randomBytes(16)), "", null)
+      .accountsPartial({ ...processAccounts, vaultAuthority: custodian.publicKey })
+      .signers([custodian])
+      .rpc();
+    const payrollAccount = await program.account.payroll.fetch(custodyPayroll);
+    expect(payrollAccount.totalFunds.toNumber()).to.equal(400);
+
+    // Hand the vault back to its PDA
+    await program.methods
+      .setVaultAuthority(null)
+      .accountsPartial({
+        payroll: custodyPayroll,
+        authority: authority.publicKey,
+        payrollVault: custodyVault,
+        currentVaultAuthority: custodian.publicKey,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority, custodian])
+      .rpc();
+    expect((await getAccount(provider.connection, custodyVault)).owner.toBase58()).to.equal(
+      custodyVault.toBase58()
+    );
+  });
+
+  it("Exports an employee and imports it into another payroll", async () => {
+    const sourcePayroll = payrollPdaFor(payrollId);
+    const sourceEmployee = employeePdaFor(sourcePayroll, employeeId);
+    const targetPayroll = payrollPdaFor("custody_payroll");
+    const targetEmployee = employeePdaFor(targetPayroll, employeeId);
+
+    const exportedPromise = awaitEvent("employeeExported");
+    await program.methods
+      .exportEmployee()
+      .accountsPartial({ payroll: sourcePayroll, employee: sourceEmployee })
+      .rpc();
+    const exported = await exportedPromise;
+
+    const importAccounts = { payroll: targetPayroll, employee: targetEmployee, authority: authority.publicKey };
+    const importedPromise = awaitEvent("employeeImported");
+    await program.methods
+      .importEmployee(employeeId, exported.employee)
+      .accountsPartial(importAccounts)
+      .signers([authority])
+      .rpc();
+    expect((await importedPromise).created).to.be.true;
+
+    const source = await program.account.employee.fetch(sourceEmployee);
+    const imported = await program.account.employee.fetch(targetEmployee);
+    expect(imported.payroll.toBase58()).to.equal(targetPayroll.toBase58());
+    expect(imported.salaryAmount.toNumber()).to.equal(source.salaryAmount.toNumber());
+    expect(imported.lastPayment.toNumber()).to.equal(source.lastPayment.toNumber());
+    expect(imported.paymentCount.toNumber()).to.equal(source.paymentCount.toNumber());
+
+    // Replaying the same snapshot changes nothing
+    const replayPromise = awaitEvent("employeeImported");
+    await program.methods
+      .importEmployee(employeeId, exported.employee)
+      .accountsPartial(importAccounts)
+      .signers([authority])
+      .rpc();
+    expect((await replayPromise).created).to.be.false;
+  });
+
+  it("Rejects a payment when the clock is behind the last payment", async () => {
+    const historyPayroll = payrollPdaFor("history_payroll");
+    const skewedEmployee = employeePdaFor(historyPayroll, "emp_skewed");
+
+    const exportedPromise = awaitEvent("employeeExported");
+    await program.methods
+      .exportEmployee()
+      .accountsPartial({ payroll: payrollPdaFor(payrollId), employee: employeePdaFor(payrollPdaFor(payrollId), employeeId) })
+      .rpc();
+    const exported = await exportedPromise;
+
+    // A last payment an hour ahead looks to the program exactly like a clock that went back
+    const now = (await provider.connection.getBlockTime(await provider.connection.getSlot())) ?? 0;
+    const snapshot = { ...exported.employee, employeeId: "emp_skewed", lastPayment: new anchor.BN(now + 3600) };
+    await program.methods
+      .importEmployee("emp_skewed", snapshot)
+      .accountsPartial({ payroll: historyPayroll, employee: skewedEmployee, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    await expectAnchorError(
+      program.methods
+        .processPayment(Array.from(randomBytes(16)), "", null)
+        .accountsPartial({
+          payroll: historyPayroll,
+          employee: skewedEmployee,
+          payrollVault: vaultPdaFor(historyPayroll),
+          nativeVault: null,
+          employeeWallet: employeeTokenAccount,
+          nativeWallet: null,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          priceUpdate: null,
+          payStub: null,
+          payer: null,
+          systemProgram: null,
+          taxAccount: null,
+          platformAccount: null,
+          vaultAuthority: null,
+          memoProgram: null,
+          approval: null,
+          approver: null,
+          payHistory: null,
+          fundingSource: null,
+          authority: null,
+        })
+        .rpc(),
+      "ClockRegression"
+    );
+  });
+
+  it("Accepts partial and full refunds from an employee", async () => {
+    const custodyPayroll = payrollPdaFor("custody_payroll");
+    const custodyEmployee = employeePdaFor(custodyPayroll, "emp_custody");
+    const refundAccounts = {
+      payroll: custodyPayroll,
+      employee: custodyEmployee,
+      payrollVault: vaultPdaFor(custodyPayroll),
+      nativeVault: null,
+      employeeWallet: employeeTokenAccount,
+      owner: employee.publicKey,
+      paymentToken: mint,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+    };
+    const before = await program.account.payroll.fetch(custodyPayroll);
+
+    // Part of the 100 paid earlier, then the rest
+    const partialPromise = awaitEvent("paymentRefunded");
+    await program.methods.refundPayment(new anchor.BN(40)).accountsPartial(refundAccounts).signers([employee]).rpc();
+    const partial = await partialPromise;
+    expect(partial.employeeId).to.equal("emp_custody");
+    expect(partial.received.toNumber()).to.equal(40);
+
+    await program.methods.refundPayment(new anchor.BN(60)).accountsPartial(refundAccounts).signers([employee]).rpc();
+    const after = await program.account.payroll.fetch(custodyPayroll);
+    expect(after.totalFunds.toNumber()).to.equal(before.totalFunds.toNumber() + 100);
+
+    // Only the wallet owner can refund
+    const stranger = await newFundedKeypair();
+    await expectAnchorError(
+      program.methods
+        .refundPayment(new anchor.BN(1))
+        .accountsPartial({ ...refundAccounts, owner: stranger.publicKey })
+        .signers([stranger])
+        .rpc(),
+      "Unauthorized"
+    );
+  });
+
+  it("Pays reimbursements without tax or touching the pay schedule", async () => {
+    const payrollPda = payrollPdaFor(payrollId);
+    const employeePda = employeePdaFor(payrollPda, employeeId);
+    const before = await program.account.employee.fetch(employeePda);
+    const initialBalance = await getAccount(provider.connection, employeeTokenAccount);
+
+    await program.methods
+      .payReimbursement(new anchor.BN(1000))
+      .accountsPartial({
+        payroll: payrollPda,
+        employee: employeePda,
+        payrollVault: vaultPdaFor(payrollPda),
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+        caller: authority.publicKey,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    const after = await program.account.employee.fetch(employeePda);
+    const balance = await getAccount(provider.connection, employeeTokenAccount);
+    expect(Number(balance.amount) - Number(initialBalance.amount)).to.equal(1000);
+    expect(after.totalReimbursed.toNumber()).to.equal(1000);
+    expect(after.lastPayment.toNumber()).to.equal(before.lastPayment.toNumber());
+  });
+
+  it("Caps salary and reimbursements paid within one period", async () => {
+    const payrollPda = payrollPdaFor(payrollId);
+    const employeePda = employeePdaFor(payrollPda, employeeId);
+    const setCap = (cap: number) =>
+      program.methods
+        .setPeriodPayCap(new anchor.BN(cap))
+        .accountsPartial({ payroll: payrollPda, employee: employeePda, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+    const reimburse = (amount: number) =>
+      program.methods
+        .payReimbursement(new anchor.BN(amount))
+        .accountsPartial({
+          payroll: payrollPda,
+          employee: employeePda,
+          payrollVault: vaultPdaFor(payrollPda),
+          employeeWallet: employeeTokenAccount,
+          paymentToken: mint,
+          caller: authority.publicKey,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([authority])
+        .rpc();
+
+    // Leave headroom for 500 more on top of what this period has already paid
+    const before = await program.account.employee.fetch(employeePda);
+    await setCap(before.paidThisPeriod.toNumber() + 500);
+    await reimburse(400);
+    await expectAnchorError(reimburse(200), "PeriodCapExceeded");
+
+    const after = await program.account.employee.fetch(employeePda);
+    expect(after.paidThisPeriod.toNumber()).to.equal(before.paidThisPeriod.toNumber() + 400);
+    await setCap(0);
+  });
+
+  it("Sweeps stranded tokens from an employee-owned account into the vault", async () => {
+    const payrollPda = payrollPdaFor(payrollId);
+    const employeePda = employeePdaFor(payrollPda, employeeId);
+    const dustAccount = await createAccount(
+      provider.connection,
+      authority,
+      mint,
+      employeePda,
+      Keypair.generate()
+    );
+    await mintTo(provider.connection, authority, mint, dustAccount, authority, 50);
+    const before = await program.account.payroll.fetch(payrollPda);
+
+    await program.methods
+      .sweepDust()
+      .accountsPartial({
+        payroll: payrollPda,
+        employee: employeePda,
+        dustAccount,
+        payrollVault: vaultPdaFor(payrollPda),
+        paymentToken: mint,
+        authority: authority.publicKey,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    const after = await program.account.payroll.fetch(payrollPda);
+    const dust = await getAccount(provider.connection, dustAccount);
+    expect(Number(dust.amount)).to.equal(0);
+    expect(after.totalFunds.toNumber()).to.equal(before.totalFunds.toNumber() + 50);
+  });
+
+  it("Pays salaries in native SOL from a lamport vault", async () => {
+    const nativePayrollId = `${payrollId}-sol`;
+    const nativePayroll = payrollPdaFor(nativePayrollId);
+    const [nativeVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("native_vault"), nativePayroll.toBuffer()],
+      program.programId
+    );
+    const nativeMint = new PublicKey("So11111111111111111111111111111111111111112");
+    const nativeEmployeeId = "sol-emp";
+    const nativeEmployee = employeePdaFor(nativePayroll, nativeEmployeeId);
+    const wallet = Keypair.generate();
+
+    await program.methods
+      .initializeNativePayroll(nativePayrollId, 1000, new anchor.BN(0), { weekly: {} })
+      .accountsPartial({ payroll: nativePayroll, nativeVault, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .addEmployee(nativeEmployeeId, new anchor.BN(100000000), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: nativePayroll,
+        employee: nativeEmployee,
+        authority: authority.publicKey,
+        employeeWallet: wallet.publicKey,
+        paymentToken: nativeMint,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .depositFunds(new anchor.BN(200000000))
+      .accountsPartial({
+        payroll: nativePayroll,
+        payrollVault: null,
+        nativeVault,
+        authority: authority.publicKey,
+        authorityTokenAccount: null,
+        paymentToken: nativeMint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .processPayment(Array.from(randomBytes(16)), "", null)
+      .accountsPartial({
+        payroll: nativePayroll,
+        employee: nativeEmployee,
+        payrollVault: null,
+        nativeVault,
+        employeeWallet: null,
+        nativeWallet: wallet.publicKey,
+        paymentToken: nativeMint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        priceUpdate: null,
+        payStub: null,
+        payer: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        taxAccount: null,
+        platformAccount: null,
+        vaultAuthority: null,
+        memoProgram: null,
+        approval: null,
+        approver: null,
+        payHistory: null,
+        fundingSource: null,
+        authority: null,
+      })
+      .rpc();
+
+    const payrollAccount = await program.account.payroll.fetch(nativePayroll);
+    expect(payrollAccount.native).to.be.true;
+    expect(await provider.connection.getBalance(wallet.publicKey)).to.equal(90000000);
+    expect(payrollAccount.totalFunds.toNumber()).to.equal(110000000);
+  });
+
+  it("Lets the guardian freeze all fund movement until the authority unfreezes", async () => {
+    const frozenId = "frozen_payroll";
+    const frozenPayroll = payrollPdaFor(frozenId);
+    const frozenVault = vaultPdaFor(frozenPayroll);
+    const frozenEmployee = employeePdaFor(frozenPayroll, "emp_frozen");
+    const guardian = await newFundedKeypair();
+    const stranger = await newFundedKeypair();
+
+    await program.methods
+      .initializePayroll(frozenId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(1000))
+      .accountsPartial({
+        payroll: frozenPayroll,
+        payrollVault: frozenVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: authorityTokenAccount,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .addEmployee("emp_frozen", new anchor.BN(100), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: frozenPayroll,
+        employee: frozenEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .setGuardian(guardian.publicKey)
+      .accountsPartial({ payroll: frozenPayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const freeze = (signer: Keypair) =>
+      program.methods
+        .freezePayroll()
+        .accountsPartial({ payroll: frozenPayroll, guardian: signer.publicKey })
+        .signers([signer])
+        .rpc();
+    const unfreeze = (signer: Keypair) =>
+      program.methods
+        .unfreezePayroll()
+        .accountsPartial({ payroll: frozenPayroll, authority: signer.publicKey })
+        .signers([signer])
+        .rpc();
+    const pay = () =>
+      program.methods
+        .processPayment(Array.from(randomBytes(16)), "", null)
+        .accountsPartial({
+          payroll: frozenPayroll,
+          employee: frozenEmployee,
+          payrollVault: frozenVault,
+          nativeVault: null,
+          employeeWallet: employeeTokenAccount,
+          nativeWallet: null,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          priceUpdate: null,
+          payStub: null,
+          payer: null,
+          systemProgram: null,
+          taxAccount: null,
+          platformAccount: null,
+          vaultAuthority: null,
+          memoProgram: null,
+          approval: null,
+          approver: null,
+          payHistory: null,
+          fundingSource: null,
+          authority: null,
+        })
+        .rpc();
+
+    await expectAnchorError(freeze(stranger), "Unauthorized");
+    const frozenPromise = awaitEvent("payrollFrozen");
+    await freeze(guardian);
+    expect((await frozenPromise).frozenBy.toBase58()).to.equal(guardian.publicKey.toBase58());
+
+    await expectAnchorError(pay(), "PayrollFrozen");
+    await expectAnchorError(
+      program.methods
+        .requestWithdrawal(new anchor.BN(100))
+        .accountsPartial({
+          payroll: frozenPayroll,
+          destination: authorityTokenAccount,
+          authority: authority.publicKey,
+        })
+        .signers([authority])
+        .rpc(),
+      "PayrollFrozen"
+    );
+    await expectAnchorError(
+      program.methods
+        .depositFunds(new anchor.BN(100))
+        .accountsPartial({
+          payroll: frozenPayroll,
+          payrollVault: frozenVault,
+          nativeVault: null,
+          authority: authority.publicKey,
+          authorityTokenAccount: authorityTokenAccount,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([authority])
+        .rpc(),
+      "PayrollFrozen"
+    );
+
+    // The guardian can only break the glass; lifting the freeze is the authority's call
+    await expectAnchorError(unfreeze(guardian), "Unauthorized");
+    await unfreeze(authority);
+    await pay();
+    const payrollAccount = await program.account.payroll.fetch(frozenPayroll);
+    expect(payrollAccount.frozen).to.equal(false);
+    expect(payrollAccount.totalFunds.toNumber()).to.equal(900);
+  });
+
+  it("Disburses vault funds to a third-party account", async () => {
+    const disburseId = "disburse_payroll";
+    const disbursePayroll = payrollPdaFor(disburseId);
+    const disburseVault = vaultPdaFor(disbursePayroll);
+    const stranger = await newFundedKeypair();
+    const benefitsProvider = Keypair.generate();
+    const benefitsAccount = await createAccount(provider.connection, authority, mint, benefitsProvider.publicKey);
+
+    await program.methods
+      .initializePayroll(disburseId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(500))
+      .accountsPartial({
+        payroll: disbursePayroll,
+        payrollVault: disburseVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: authorityTokenAccount,
+      })
+      .signers([authority])
+      .rpc();
+
+    const disburse = (amount: number, signer: Keypair) =>
+      program.methods
+        .disburse(new anchor.BN(amount))
+        .accountsPartial({
+          payroll: disbursePayroll,
+          payrollVault: disburseVault,
+          recipient: benefitsAccount,
+          paymentToken: mint,
+          authority: signer.publicKey,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([signer])
+        .rpc();
+
+    const disbursedPromise = awaitEvent("disbursed");
+    await disburse(200, authority);
+    const disbursed = await disbursedPromise;
+    expect(disbursed.recipient.toBase58()).to.equal(benefitsAccount.toBase58());
+    expect(disbursed.amount.toNumber()).to.equal(200);
+    expect(Number((await getAccount(provider.connection, benefitsAccount)).amount)).to.equal(200);
+
+    await expectAnchorError(disburse(100, stranger), "Unauthorized");
+    await expectAnchorError(disburse(301, authority), "InsufficientFunds");
+
+    const payrollAccount = await program.account.payroll.fetch(disbursePayroll);
+    expect(payrollAccount.totalFunds.toNumber()).to.equal(300);
+  });
+
+  it("Rate-limits outflows within a rolling window", async () => {
+    const limitId = "outflow_payroll";
+    const limitPayroll = payrollPdaFor(limitId);
+    const limitVault = vaultPdaFor(limitPayroll);
+    const limitEmployee = employeePdaFor(limitPayroll, "emp_outflow");
+    const windowSeconds = 3;
+
+    await program.methods
+      .initializePayroll(limitId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(1000))
+      .accountsPartial({
+        payroll: limitPayroll,
+        payrollVault: limitVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: authorityTokenAccount,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .addEmployee("emp_outflow", new anchor.BN(100), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: limitPayroll,
+        employee: limitEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    const setLimit = (max: number, seconds: number) =>
+      program.methods
+        .setOutflowLimit(new anchor.BN(max), new anchor.BN(seconds))
+        .accountsPartial({ payroll: limitPayroll, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+    const pay = () =>
+      program.methods
+        .processPayment(Array.from(randomBytes(16)), "", null)
+        .accountsPartial({
+          payroll: limitPayroll,
+          employee: limitEmployee,
+          payrollVault: limitVault,
+          nativeVault: null,
+          employeeWallet: employeeTokenAccount,
+          nativeWallet: null,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          priceUpdate: null,
+          payStub: null,
+          payer: null,
+          systemProgram: null,
+          taxAccount: null,
+          platformAccount: null,
+          vaultAuthority: null,
+          memoProgram: null,
+          approval: null,
+          approver: null,
+          payHistory: null,
+          fundingSource: null,
+          authority: null,
+        })
+        .rpc();
+
+    await expectAnchorError(setLimit(150, 0), "InvalidOutflowWindow");
+    await setLimit(150, windowSeconds);
+
+    // A disbursement uses up most of the window, leaving too little for the payment
+    await program.methods
+      .disburse(new anchor.BN(100))
+      .accountsPartial({
+        payroll: limitPayroll,
+        payrollVault: limitVault,
+        recipient: authorityTokenAccount,
+        paymentToken: mint,
+        authority: authority.publicKey,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+    await expectAnchorError(pay(), "RateLimitExceeded");
+
+    // Once the window rolls over the same payment goes through
+    await new Promise((r) => setTimeout(r, (windowSeconds + 1) * 1000));
+    await pay();
+    const payrollAccount = await program.account.payroll.fetch(limitPayroll);
+    expect(payrollAccount.windowOutflow.toNumber()).to.equal(100);
+    expect(payrollAccount.totalFunds.toNumber()).to.equal(800);
+  });
+
+  it("Applies a scheduled salary change once it takes effect", async () => {
+    const raiseId = "raise_payroll";
+    const raisePayroll = payrollPdaFor(raiseId);
+    const raiseVault = vaultPdaFor(raisePayroll);
+    const raiseEmployee = employeePdaFor(raisePayroll, "emp_raise");
+
+    await program.methods
+      .initializePayroll(raiseId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(1000))
+      .accountsPartial({
+        payroll: raisePayroll,
+        payrollVault: raiseVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: authorityTokenAccount,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .addEmployee("emp_raise", new anchor.BN(100), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: raisePayroll,
+        employee: raiseEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .setPaymentIntervalOverride(new anchor.BN(1))
+      .accountsPartial({ payroll: raisePayroll, employee: raiseEmployee, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const schedule = (salary: number, effectiveTs: number) =>
+      program.methods
+        .scheduleSalaryChange(new anchor.BN(salary), new anchor.BN(effectiveTs))
+        .accountsPartial({ payroll: raisePayroll, employee: raiseEmployee, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+    const pay = async () => {
+      const paidPromise = awaitEvent("paymentProcessed");
+      await program.methods
+        .processPayment(Array.from(randomBytes(16)), "", null)
+        .accountsPartial({
+          payroll: raisePayroll,
+          employee: raiseEmployee,
+          payrollVault: raiseVault,
+          nativeVault: null,
+          employeeWallet: employeeTokenAccount,
+          nativeWallet: null,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          priceUpdate: null,
+          payStub: null,
+          payer: null,
+          systemProgram: null,
+          taxAccount: null,
+          platformAccount: null,
+          vaultAuthority: null,
+          memoProgram: null,
+          approval: null,
+          approver: null,
+          payHistory: null,
+          fundingSource: null,
+          authority: null,
+        })
+        .rpc();
+      return paidPromise;
+    };
+
+    const now = Math.floor(Date.now() / 1000);
+    await expectAnchorError(schedule(200, now - 60), "InvalidEffectiveTimestamp");
+    await schedule(200, now + 4);
+
+    // Not yet effective: paid at the old salary, the change still pending
+    expect((await pay()).grossSalary.toNumber()).to.equal(100);
+    let employeeAccount = await program.account.employee.fetch(raiseEmployee);
+    expect(employeeAccount.pendingSalary.toNumber()).to.equal(200);
+
+    await new Promise((r) => setTimeout(r, 6000));
+    const appliedPromise = awaitEvent("scheduledSalaryApplied");
+    expect((await pay()).grossSalary.toNumber()).to.equal(200);
+    const applied = await appliedPromise;
+    expect(applied.oldSalary.toNumber()).to.equal(100);
+    expect(applied.newSalary.toNumber()).to.equal(200);
+
+    employeeAccount = await program.account.employee.fetch(raiseEmployee);
+    expect(employeeAccount.salaryAmount.toNumber()).to.equal(200);
+    expect(employeeAccount.pendingSalary).to.be.null;
+  });
+
+  it("Itemizes named deductions on the payment", async () => {
+    const itemizedId = "itemized_payroll";
+    const itemizedPayroll = payrollPdaFor(itemizedId);
+    const itemizedVault = vaultPdaFor(itemizedPayroll);
+    const itemizedEmployee = employeePdaFor(itemizedPayroll, "emp_itemized");
+
+    await program.methods
+      .initializePayroll(itemizedId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(1000))
+      .accountsPartial({
+        payroll: itemizedPayroll,
+        payrollVault: itemizedVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: authorityTokenAccount,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .addEmployee("emp_itemized", new anchor.BN(1000), new anchor.BN(25), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: itemizedPayroll,
+        employee: itemizedEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    const accounts = { payroll: itemizedPayroll, employee: itemizedEmployee, authority: authority.publicKey };
+    const addItem = (label: string, amount: number) =>
+      program.methods.addDeduction(label, new anchor.BN(amount)).accountsPartial(accounts).signers([authority]).rpc();
+    const removeItem = (label: string) =>
+      program.methods.removeDeduction(label).accountsPartial(accounts).signers([authority]).rpc();
+
+    await addItem("health", 100);
+    await addItem("retirement", 50);
+    await expectAnchorError(addItem("health", 10), "InvalidDeductionLabel");
+    await expectAnchorError(addItem("", 10), "InvalidDeductionLabel");
+    await expectAnchorError(addItem("x".repeat(33), 10), "InvalidDeductionLabel");
+    await expectAnchorError(removeItem("dental"), "DeductionNotFound");
+
+    const paidPromise = awaitEvent("paymentProcessed");
+    await program.methods
+      .processPayment(Array.from(randomBytes(16)), "", null)
+      .accountsPartial({
+        payroll: itemizedPayroll,
+        employee: itemizedEmployee,
+        payrollVault: itemizedVault,
+        nativeVault: null,
+        employeeWallet: employeeTokenAccount,
+        nativeWallet: null,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        priceUpdate: null,
+        payStub: null,
+        payer: null,
+        systemProgram: null,
+        taxAccount: null,
+        platformAccount: null,
+        vaultAuthority: null,
+        memoProgram: null,
+        approval: null,
+        approver: null,
+        payHistory: null,
+        fundingSource: null,
+        authority: null,
+      })
+      .rpc();
+    const paid = await paidPromise;
+
+    // The flat deduction and the items are summed into the total
+    expect(paid.deductions.toNumber()).to.equal(175);
+    expect(paid.netPay.toNumber()).to.equal(825);
+    expect(paid.deductionItems.map((item) => [item.label, item.amount.toNumber()])).to.deep.equal([
+      ["health", 100],
+      ["retirement", 50],
+    ]);
+
+    await removeItem("retirement");
+    for (let i = 0; i < 7; i++) {
+      await addItem(`item_${i}`, 0);
+    }
+    await expectAnchorError(addItem("one_too_many", 0), "TooManyDeductions");
+    const employeeAccount = await program.account.employee.fetch(itemizedEmployee);
+    expect(employeeAccount.deductionItems.map((item) => item.label)).to.deep.equal([
+      "health",
+      ...Array.from({ length: 7 }, (_, i) => `item_${i}`),
+    ]);
+  });
+
+  it("Caps garnishment at a share of disposable pay", async () => {
+    const garnishId = "garnish_payroll";
+    const garnishPayroll = payrollPdaFor(garnishId);
+    const garnishVault = vaultPdaFor(garnishPayroll);
+    const garnishEmployee = employeePdaFor(garnishPayroll, "emp_garnish");
+
+    await program.methods
+      .initializePayroll(garnishId, 1000, new anchor.BN(0), { weekly: {} }, new anchor.BN(1000)) // 10% tax rate
+      .accountsPartial({
+        payroll: garnishPayroll,
+        payrollVault: garnishVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: authorityTokenAccount,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .addEmployee("emp_garnish", new anchor.BN(1000), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: garnishPayroll,
+        employee: garnishEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    const setGarnishment = (amount: number, bps: number) =>
+      program.methods
+        .setGarnishment(new anchor.BN(amount), bps)
+        .accountsPartial({ payroll: garnishPayroll, employee: garnishEmployee, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+    const preview = async () => {
+      const previewPromise = awaitEvent("netPayPreview");
+      await program.methods
+        .previewNetPay()
+        .accountsPartial({ payroll: garnishPayroll, employee: garnishEmployee, caller: authority.publicKey, priceUpdate: null })
+        .signers([authority])
+        .rpc();
+      return previewPromise;
+    };
+
+    await expectAnchorError(setGarnishment(500, 10001), "InvalidGarnishmentBps");
+
+    // Disposable pay is 900 after tax; a fixed 100 sits under the 25% cap of 225
+    await setGarnishment(100, 2500);
+    expect((await preview()).garnishment.toNumber()).to.equal(100);
+
+    // A fixed 500 is held to the cap
+    await setGarnishment(500, 2500);
+    const previewed = await preview();
+    expect(previewed.garnishment.toNumber()).to.equal(225);
+    expect(previewed.net.toNumber()).to.equal(675);
+
+    const paidPromise = awaitEvent("paymentProcessed");
+    await program.methods
+      .processPayment(Array.from(randomBytes(16)), "", null)
+      .accountsPartial({
+        payroll: garnishPayroll,
+        employee: garnishEmployee,
+        payrollVault: garnishVault,
+        nativeVault: null,
+        employeeWallet: employeeTokenAccount,
+        nativeWallet: null,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        priceUpdate: null,
+        payStub: null,
+        payer: null,
+        systemProgram: null,
+        taxAccount: null,
+        platformAccount: null,
+        vaultAuthority: null,
+        memoProgram: null,
+        approval: null,
+        approver: null,
+        payHistory: null,
+        fundingSource: null,
+        authority: null,
+      })
+      .rpc();
+    const paid = await paidPromise;
+    expect(paid.garnishment.toNumber()).to.equal(225);
+    expect(paid.netPay.toNumber()).to.equal(675);
+  });
+
+  it("Schedules payments on the interval override instead of the frequency", async () => {
+    const overrideId = "override_payroll";
+    const overridePayroll = payrollPdaFor(overrideId);
+    const overrideVault = vaultPdaFor(overridePayroll);
+    const overrideEmployee = employeePdaFor(overridePayroll, "emp_override");
+    const interval = 2;
+
+    await program.methods
+      .initializePayroll(overrideId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(1000))
+      .accountsPartial({
+        payroll: overridePayroll,
+        payrollVault: overrideVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: authorityTokenAccount,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .addEmployee("emp_override", new anchor.BN(100), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: overridePayroll,
+        employee: overrideEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    const setOverride = (seconds: number | null) =>
+      program.methods
+        .setPaymentIntervalOverride(seconds === null ? null : new anchor.BN(seconds))
+        .accountsPartial({ payroll: overridePayroll, employee: overrideEmployee, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+    const pay = () =>
+      program.methods
+        .processPayment(Array.from(randomBytes(16)), "", null)
+        .accountsPartial({
+          payroll: overridePayroll,
+          employee: overrideEmployee,
+          payrollVault: overrideVault,
+          nativeVault: null,
+          employeeWallet: employeeTokenAccount,
+          nativeWallet: null,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          priceUpdate: null,
+          payStub: null,
+          payer: null,
+          systemProgram: null,
+          taxAccount: null,
+          platformAccount: null,
+          vaultAuthority: null,
+          memoProgram: null,
+          approval: null,
+          approver: null,
+          payHistory: null,
+          fundingSource: null,
+          authority: null,
+        })
+        .rpc();
+
+    await expectAnchorError(setOverride(0), "InvalidPaymentInterval");
+    await expectAnchorError(setOverride(-interval), "InvalidPaymentInterval");
+    await setOverride(interval);
+
+    await pay();
+    const first = (await program.account.employee.fetch(overrideEmployee)).lastPayment.toNumber();
+    await expectAnchorError(pay(), "PaymentTooSoon");
+
+    // Due after the override's seconds, long before the weekly frequency would allow
+    await new Promise((r) => setTimeout(r, (interval + 1) * 1000));
+    await pay();
+    const employeeAccount = await program.account.employee.fetch(overrideEmployee);
+    expect(employeeAccount.lastPayment.toNumber()).to.equal(first + interval);
+    expect(employeeAccount.paymentCount.toNumber()).to.equal(2);
+
+    // Clearing it falls back to the weekly interval
+    await setOverride(null);
+    await new Promise((r) => setTimeout(r, (interval + 1) * 1000));
+    await expectAnchorError(pay(), "PaymentTooSoon");
+  });
+
+  it("Closes pay periods with the net paid in each", async () => {
+    const periodId = "period_payroll";
+    const periodPayroll = payrollPdaFor(periodId);
+    const periodVault = vaultPdaFor(periodPayroll);
+    const periodEmployee = employeePdaFor(periodPayroll, "emp_period");
+    const stranger = await newFundedKeypair();
+
+    await program.methods
+      .initializePayroll(periodId, 1000, new anchor.BN(0), { weekly: {} }, new anchor.BN(1000)) // 10% tax rate
+      .accountsPartial({
+        payroll: periodPayroll,
+        payrollVault: periodVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: authorityTokenAccount,
+      })
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .addEmployee("emp_period", new anchor.BN(100), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: periodPayroll,
+        employee: periodEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc();
+
+    const close = async () => {
+      const closedPromise = awaitEvent("periodClosed");
+      await program.methods
+        .closePeriod()
+        .accountsPartial({ payroll: periodPayroll, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+      return closedPromise;
+    };
+
+    await program.methods
+      .processPayment(Array.from(randomBytes(16)), "", null)
+      .accountsPartial({
+        payroll: periodPayroll,
+        employee: periodEmployee,
+        payrollVault: periodVault,
+        nativeVault: null,
+        employeeWallet: employeeTokenAccount,
+        nativeWallet: null,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        priceUpdate: null,
+        payStub: null,
+        payer: null,
+        systemProgram: null,
+        taxAccount: null,
+        platformAccount: null,
+        vaultAuthority: null,
+        memoProgram: null,
+        approval: null,
+        approver: null,
+        payHistory: null,
+        fundingSource: null,
+        authority: null,
+      })
+      .rpc();
+
+    await expectAnchorError(
+      program.methods
+        .closePeriod()
+        .accountsPartial({ payroll: periodPayroll, authority: stranger.publicKey })
+        .signers([stranger])
+        .rpc(),
+      "Unauthorized"
+    );
+
+    const closed = await close();
+    expect(closed.period.toNumber()).to.equal(0);
+    expect(closed.totalPaidInPeriod.toNumber()).to.equal(90);
+
+    // The next period starts empty
+    const emptyClose = await close();
+    expect(emptyClose.period.toNumber()).to.equal(1);
+    expect(emptyClose.totalPaidInPeriod.toNumber()).to.equal(0);
+    const payrollAccount = await program.account.payroll.fetch(periodPayroll);
+    expect(payrollAccount.currentPeriod.toNumber()).to.equal(2);
+    expect(payrollAccount.totalPaidInPeriod.toNumber()).to.equal(0);
+  });
+
+  it("Time-locks withdrawals behind the configured delay", async () => {
+    const withdrawPayrollId = "withdraw_payroll";
+    const withdrawPayroll = payrollPdaFor(withdrawPayrollId);
+    const withdrawVault = vaultPdaFor(withdrawPayroll);
+
+    await program.methods
+      .initializePayroll(withdrawPayrollId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: withdrawPayroll,
+        payrollVault: withdrawVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .depositFunds(new anchor.BN(1000))
+      .accountsPartial({
+        payroll: withdrawPayroll,
+        payrollVault: withdrawVault,
+        nativeVault: null,
+        authority: authority.publicKey,
+        authorityTokenAccount: authorityTokenAccount,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    const request = (amount: number) =>
+      program.methods
+        .requestWithdrawal(new anchor.BN(amount))
+        .accountsPartial({
+          payroll: withdrawPayroll,
+          destination: authorityTokenAccount,
+          authority: authority.publicKey,
+        })
+        .signers([authority])
+        .rpc();
+    const execute = () =>
+      program.methods
+        .executeWithdrawal()
+        .accountsPartial({
+          payroll: withdrawPayroll,
+          payrollVault: withdrawVault,
+          nativeVault: null,
+          destination: authorityTokenAccount,
+          paymentToken: mint,
+          authority: authority.publicKey,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          withdrawalApprovals: null,
+        })
+        .signers([authority])
+        .rpc();
+    const setDelay = (seconds: number) =>
+      program.methods
+        .setWithdrawalDelay(new anchor.BN(seconds))
+        .accountsPartial({ payroll: withdrawPayroll, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+    // Without a delay the request unlocks immediately
+    await request(100);
+    await execute();
+    let payrollAccount = await program.account.payroll.fetch(withdrawPayroll);
+    expect(payrollAccount.totalFunds.toNumber()).to.equal(900);
+    expect(payrollAccount.pendingWithdrawal).to.be.null;
+
+    await setDelay(3600);
+    await request(100);
+    await expectAnchorError(execute(), "WithdrawalLocked");
+    await expectAnchorError(
+      program.methods
+        .disburse(new anchor.BN(100))
+        .accountsPartial({
+          payroll: withdrawPayroll,
+          payrollVault: withdrawVault,
+          recipient: authorityTokenAccount,
+          paymentToken: mint,
+          authority: authority.publicKey,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([authority])
+        .rpc(),
+      "WithdrawalTimeLocked"
+    );
+
+    await program.methods
+      .cancelWithdrawal()
+      .accountsPartial({ payroll: withdrawPayroll, signer: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    // Shortening the delay waits out the current one
+    await setDelay(0);
+    payrollAccount = await program.account.payroll.fetch(withdrawPayroll);
+    expect(payrollAccount.pendingWithdrawal).to.be.null;
+    expect(payrollAccount.withdrawalDelaySeconds.toNumber()).to.equal(3600);
+    expect(payrollAccount.pendingWithdrawalDelay.toNumber()).to.equal(0);
+    expect(payrollAccount.totalFunds.toNumber()).to.equal(900);
+  });
+
+  it("Requires M-of-N signer approvals before a withdrawal executes", async () => {
+    const msigPayrollId = "msig_payroll";
+    const msigPayroll = payrollPdaFor(msigPayrollId);
+    const msigVault = vaultPdaFor(msigPayroll);
+    const [withdrawalApprovals] = PublicKey.findProgramAddressSync(
+      [Buffer.from("withdrawal_approvals"), msigPayroll.toBuffer()],
+      program.programId
+    );
+    const signers = [
+      await newFundedKeypair(),
+      await newFundedKeypair(),
+      await newFundedKeypair(),
+    ];
+
+    await program.methods
+      .initializePayroll(msigPayrollId, 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: msigPayroll,
+        payrollVault: msigVault,
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .depositFunds(new anchor.BN(1000))
+      .accountsPartial({
+        payroll: msigPayroll,
+        payrollVault: msigVault,
+        nativeVault: null,
+        authority: authority.publicKey,
+        authorityTokenAccount: authorityTokenAccount,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    await expectAnchorError(
+      program.methods
+        .setWithdrawalSigners([signers[0].publicKey, signers[0].publicKey], 1)
+        .accountsPartial({ payroll: msigPayroll, authority: authority.publicKey })
+        .signers([authority])
+        .rpc(),
+      "InvalidWithdrawalSigners"
+    );
+    await program.methods
+      .setWithdrawalSigners(
+        signers.map((s) => s.publicKey),
+        2
+      )
+      .accountsPartial({ payroll: msigPayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    await program.methods
+      .requestWithdrawal(new anchor.BN(300))
+      .accountsPartial({
+        payroll: msigPayroll,
+        destination: authorityTokenAccount,
+        authority: authority.publicKey,
+      })
+      .signers([authority])
+      .rpc();
+
+    const approve = (signer: Keypair) =>
+      program.methods
+        .approveWithdrawal()
+        .accountsPartial({
+          payroll: msigPayroll,
+          withdrawalApprovals,
+          signer: signer.publicKey,
+        })
+        .signers([signer])
+        .rpc();
+    const execute = () =>
+      program.methods
+        .executeWithdrawal()
+        .accountsPartial({
+          payroll: msigPayroll,
+          payrollVault: msigVault,
+          nativeVault: null,
+          destination: authorityTokenAccount,
+          paymentToken: mint,
+          authority: authority.publicKey,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          withdrawalApprovals,
+        })
+        .signers([authority])
+        .rpc();
+
+    await approve(signers[0]);
+    await expectAnchorError(approve(signers[0]), "WithdrawalAlreadyApproved");
+    await expectAnchorError(execute(), "WithdrawalApprovalsNotMet");
+
+    await approve(signers[1]);
+    const executedPromise = awaitEvent("withdrawalExecuted");
+    await execute();
+    const executed = await executedPromise;
+    expect(executed.approvers.map((k: PublicKey) => k.toBase58())).to.have.members([
+      signers[0].publicKey.toBase58(),
+      signers[1].publicKey.toBase58(),
+    ]);
+
+    const payrollAccount = await program.account.payroll.fetch(msigPayroll);
+    expect(payrollAccount.totalFunds.toNumber()).to.equal(700);
+    expect(payrollAccount.pendingWithdrawal).to.be.null;
+    const approvalsAccount = await program.account.withdrawalApprovals.fetch(withdrawalApprovals);
+    expect(approvalsAccount.approvals).to.be.empty;
+  });
+
+  it("Reassigns authority through guardian recovery unless the authority cancels", async () => {
+    const recoveryPayroll = payrollPdaFor("recovery_payroll");
+    const [recovery] = PublicKey.findProgramAddressSync(
+      [Buffer.from("recovery"), recoveryPayroll.toBuffer()],
+      program.programId
+    );
+    const guardians = [await newFundedKeypair(), await newFundedKeypair(), await newFundedKeypair()];
+    const newAuthority = Keypair.generate().publicKey;
+    const delay = 3;
+
+    await program.methods
+      .initializePayroll("recovery_payroll", 0, new anchor.BN(0), { weekly: {} }, new anchor.BN(0))
+      .accountsPartial({
+        payroll: recoveryPayroll,
+        payrollVault: vaultPdaFor(recoveryPayroll),
+        authority: authority.publicKey,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        authorityTokenAccount: null,
+      })
+      .signers([authority])
+      .rpc();
+
+    // The authority can't vote on its own recovery
+    await expectAnchorError(
+      program.methods
+        .setRecoveryGuardians([authority.publicKey, guardians[0].publicKey], 2, new anchor.BN(delay))
+        .accountsPartial({ payroll: recoveryPayroll, authority: authority.publicKey })
+        .signers([authority])
+        .rpc(),
+      "InvalidRecoveryConfig"
+    );
+    await program.methods
+      .setRecoveryGuardians(guardians.map((g) => g.publicKey), 2, new anchor.BN(delay))
+      .accountsPartial({ payroll: recoveryPayroll, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const propose = (guardian: Keypair) =>
+      program.methods
+        .proposeRecovery(newAuthority)
+        .accountsPartial({ payroll: recoveryPayroll, recovery, guardian: guardian.publicKey })
+        .signers([guardian])
+        .rpc();
+    const approve = (guardian: Keypair) =>
+      program.methods
+        .approveRecovery()
+        .accountsPartial({ payroll: recoveryPayroll, recovery, guardian: guardian.publicKey })
+        .signers([guardian])
+        .rpc();
+    const execute = (proposer: Keypair, guardian: Keypair) =>
+      program.methods
+        .executeRecovery()
+        .accountsPartial({ payroll: recoveryPayroll, recovery, proposer: proposer.publicKey, guardian: guardian.publicKey })
+        .signers([guardian])
+        .rpc();
+
+    await propose(guardians[0]);
+    await expectAnchorError(execute(guardians[0], guardians[0]), "RecoveryThresholdNotMet");
+    await approve(guardians[1]);
+    await expectAnchorError(approve(guardians[1]), "RecoveryAlreadyApproved");
+    await expectAnchorError(execute(guardians[0], guardians[0]), "RecoveryDelayActive");
+
+    // A live authority vetoes the takeover
+    await program.methods
+      .cancelRecovery()
+      .accountsPartial({ payroll: recoveryPayroll, recovery, proposer: guardians[0].publicKey, canceller: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    expect((await program.account.payroll.fetch(recoveryPayroll)).authority.toBase58()).to.equal(
+      authority.publicKey.toBase58()
+    );
+
+    // With the authority silent, the proposal executes after the delay
+    await propose(guardians[1]);
+    await approve(guardians[2]);
+    await new Promise((r) => setTimeout(r, (delay + 1) * 1000));
+    const executedPromise = awaitEvent("recoveryExecuted");
+    await execute(guardians[1], guardians[2]);
+    const executed = await executedPromise;
+
+    expect(executed.oldAuthority.toBase58()).to.equal(authority.publicKey.toBase58());
+    const payrollAccount = await program.account.payroll.fetch(recoveryPayroll);
+    expect(payrollAccount.authority.toBase58()).to.equal(newAuthority.toBase58());
+    expect(payrollAccount.recoveryPending).to.equal(false);
+    expect(await provider.connection.getAccountInfo(recovery)).to.equal(null);
+  });
+
+  it("Blocks fund movements while globally paused", async () => {
+    const payrollPda = payrollPdaFor(payrollId);
+
+    await program.methods
+      .setGlobalPause(true)
+      .accountsPartial({ programConfig: programConfigPda(), admin: provider.publicKey })
+      .rpc();
+
+    await expectAnchorError(
+      program.methods
+        .depositFunds(new anchor.BN(1))
+        .accountsPartial({
+          payroll: payrollPda,
+          payrollVault: vaultPdaFor(payrollPda),
+          nativeVault: null,
+          authority: authority.publicKey,
+          authorityTokenAccount: authorityTokenAccount,
+          paymentToken: mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([authority])
+        .rpc(),
+      "GloballyPaused"
+    );
+
+    await program.methods
+      .setGlobalPause(false)
+      .accountsPartial({ programConfig: programConfigPda(), admin: provider.publicKey })
+      .rpc();
+  });
+
+  it("Derives the same PDAs as the program's seeds", async () => {
+    const [payrollPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("payroll"), Buffer.from(payrollId)],
+      program.programId
+    );
+    const [vaultPda, vaultBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("payroll_vault"), payrollPda.toBuffer()],
+      program.programId
+    );
+    const [employeePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("employee"), payrollPda.toBuffer(), Buffer.from(employeeId)],
+      program.programId
+    );
+    const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("program_config")], program.programId);
+
+    // The helpers the rest of the suite relies on agree with a direct derivation
+    expect(payrollPdaFor(payrollId).toBase58()).to.equal(payrollPda.toBase58());
+    expect(vaultPdaFor(payrollPda).toBase58()).to.equal(vaultPda.toBase58());
+    expect(employeePdaFor(payrollPda, employeeId).toBase58()).to.equal(employeePda.toBase58());
+    expect(programConfigPda().toBase58()).to.equal(configPda.toBase58());
+
+    // And each lands on the account the program created under those seeds
+    const payrollAccount = await program.account.payroll.fetch(payrollPda);
+    expect(payrollAccount.payrollId).to.equal(payrollId);
+    expect(payrollAccount.vaultBump).to.equal(vaultBump);
+    const vault = await getAccount(provider.connection, vaultPda);
+    expect(vault.mint.toBase58()).to.equal(mint.toBase58());
+    const employeeAccount = await program.account.employee.fetch(employeePda);
+    expect(employeeAccount.payroll.toBase58()).to.equal(payrollPda.toBase58());
+    expect(employeeAccount.employeeId).to.equal(employeeId);
+    await program.account.programConfig.fetch(configPda);
+
+    // The employee seed is scoped to its payroll
+    const [unscoped] = PublicKey.findProgramAddressSync(
+      [Buffer.from("employee"), Buffer.from(employeeId)],
+      program.programId
+    );
+    expect(await provider.connection.getAccountInfo(unscoped)).to.be.null;
+  });
+
+  it("Stamps a monotonic payroll sequence on events", async () => {
+    const payrollPda = payrollPdaFor(payrollId);
+    const employeePda = employeePdaFor(payrollPda, employeeId);
+    const start = (await program.account.payroll.fetch(payrollPda)).sequence.toNumber();
+    const sequences: number[] = [];
+
+    const eventPromise = awaitEvent("lowBalanceThresholdUpdated");
+    await program.methods
+      .setLowBalanceThreshold(new anchor.BN(0))
+      .accountsPartial({ payroll: payrollPda, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    sequences.push((await eventPromise).sequence.toNumber());
+
+    const depositPromise = awaitEvent("fundsDeposited");
+    await program.methods
+      .depositFunds(new anchor.BN(10))
+      .accountsPartial({
+        payroll: payrollPda,
+        payrollVault: vaultPdaFor(payrollPda),
+        nativeVault: null,
+        authority: authority.publicKey,
+        authorityTokenAccount: authorityTokenAccount,
+        paymentToken: mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+    sequences.push((await depositPromise).sequence.toNumber());
+
+    const deductionPromise = awaitEvent("deductionAdded");
+    await program.methods
+      .addDeduction("audit", new anchor.BN(1))
+      .accountsPartial({ payroll: payrollPda, employee: employeePda, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    sequences.push((await deductionPromise).sequence.toNumber());
+
+    const removedPromise = awaitEvent("deductionRemoved");
+    await program.methods
+      .removeDeduction("audit")
+      .accountsPartial({ payroll: payrollPda, employee: employeePda, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    sequences.push((await removedPromise).sequence.toNumber());
+
+    // Read-only instructions report the current sequence without advancing it
+    const previewPromise = awaitEvent("netPayPreview");
+    await program.methods
+      .previewNetPay()
+      .accountsPartial({ payroll: payrollPda, employee: employeePda, caller: authority.publicKey, priceUpdate: null })
+      .signers([authority])
       .rpc();
+    const preview = await previewPromise;
 
-    const updatedPayroll = await program.account.payroll.fetch(payrollPda);
-    const updatedEmployee = await program.account.employee.fetch(employeePda);
+    expect(sequences).to.deep.equal([start + 1, start + 2, start + 3, start + 4]);
+    expect(preview.sequence.toNumber()).to.equal(start + 4);
+    const payrollAccount = await program.account.payroll.fetch(payrollPda);
+    expect(payrollAccount.sequence.toNumber()).to.equal(start + 4);
+  });
 
-    // Check that funds were transferred
-    const employeeTokenBalance = await getAccount(provider.connection, employeeTokenAccount);
-    const expectedNetPay = 85000000; // 100000000 - (100000000 * 0.1) - 5000000
-    expect(employeeTokenBalance.amount.toString()).to.equal((Number(initialEmployeeBalance.amount) + expectedNetPay).toString());
+  it("Applies a percentage-of-gross deduction", async () => {
+    const payrollPda = payrollPdaFor(payrollId);
+    const employeePda = employeePdaFor(payrollPda, employeeId);
+    const preview = async () => {
+      const previewPromise = awaitEvent("netPayPreview");
+      await program.methods
+        .previewNetPay()
+        .accountsPartial({ payroll: payrollPda, employee: employeePda, caller: authority.publicKey, priceUpdate: null })
+        .signers([authority])
+        .rpc();
+      return previewPromise;
+    };
+    const setBps = (bps: number) =>
+      program.methods
+        .setDeductionBps(bps)
+        .accountsPartial({ payroll: payrollPda, employee: employeePda, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+    const before = await preview();
+    await setBps(500);
+    const after = await preview();
+
+    // 5% of gross lands in deductions and comes straight out of net
+    const percentage = Math.floor((before.gross.toNumber() * 500) / 10000);
+    expect(after.percentageDeduction.toNumber()).to.equal(percentage);
+    expect(after.deductions.toNumber()).to.equal(before.deductions.toNumber() + percentage);
+    expect(after.net.toNumber()).to.equal(before.net.toNumber() - percentage);
+
+    await setBps(0);
   });
 
   it("Test confidential net pay calculation", async () => {
@@ -206,9 +5473,10 @@ describe("ConfidentialPayroll", () => {
     let initSig: string | undefined;
     for (let attempt = 1; attempt <= 3; attempt++) {
       try {
-        initSig = await initCalculateNetPayCompDef(
+        initSig = await initCompDef(
           program,
           owner,
+          "calculate_net_pay",
           false,
           false
         );
@@ -236,15 +5504,56 @@ describe("ConfidentialPayroll", () => {
 
     const salary = BigInt(100000000); // 0.1 tokens
     const taxRate = BigInt(1000); // 10%
-    const deductions = BigInt(5000000); // 0.005 tokens
-    const plaintext = [salary, taxRate, deductions];
+    // Each line item is encrypted separately and only summed inside the MPC; the last slot is unused
+    const deductionItems = [BigInt(3000000), BigInt(1500000), BigInt(500000), BigInt(0)];
+    const deductions = deductionItems.reduce((sum, item) => sum + item, BigInt(0)); // 0.005 tokens
+    const plaintext = [salary, ...deductionItems];
 
     const nonce = randomBytes(16);
     const ciphertext = cipher.encrypt(plaintext, nonce);
 
+    // The tax rate is stored encrypted on the payroll rather than passed per computation
+    const taxRateNonce = randomBytes(16);
+    const taxRateCiphertext = cipher.encrypt([taxRate], taxRateNonce);
+    await program.methods
+      .setEncryptedTaxRate(
+        Array.from(taxRateCiphertext[0]),
+        Array.from(publicKey),
+        new anchor.BN(deserializeLE(taxRateNonce).toString())
+      )
+      .accountsPartial({
+        payroll: payrollPdaFor(payrollId),
+        authority: authority.publicKey,
+      })
+      .signers([authority])
+      .rpc({ commitment: "confirmed" });
+
     const netPayEventPromise = awaitEvent("netPayCalculated");
     const computationOffset = new anchor.BN(randomBytes(8), "hex");
 
+    const [payrollPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("payroll"), Buffer.from(payrollId)],
+      program.programId
+    );
+    const [employeePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("employee"), payrollPda.toBuffer(), Buffer.from(employeeId)],
+      program.programId
+    );
+
+    // The employee registers the key their net pay is sealed to; calculate_net_pay reuses it
+    await program.methods
+      .updateEncryptionPubkey(Array.from(publicKey))
+      .accountsPartial({
+        payroll: payrollPda,
+        employee: employeePda,
+        employeeWallet: employeeTokenAccount,
+        walletOwner: employee.publicKey,
+      })
+      .signers([employee])
+      .rpc({ commitment: "confirmed" });
+    const employeeAccount = await program.account.employee.fetch(employeePda);
+    expect(Buffer.from(employeeAccount.encryptionPubkey)).to.deep.equal(Buffer.from(publicKey));
+
     // send queue transaction with retries to avoid transient 'Blockhash not found' errors
     let queueSig: string | undefined;
     for (let attempt = 1; attempt <= 3; attempt++) {
@@ -253,12 +5562,13 @@ describe("ConfidentialPayroll", () => {
           .calculateNetPay(
             computationOffset,
             Array.from(ciphertext[0]),
-            Array.from(ciphertext[1]),
-            Array.from(ciphertext[2]),
-            Array.from(publicKey),
+            ciphertext.slice(1).map((item) => Array.from(item)),
             new anchor.BN(deserializeLE(nonce).toString())
           )
           .accountsPartial({
+            payer: authority.publicKey,
+            payroll: payrollPda,
+            employee: employeePda,
             computationAccount: getComputationAccAddress(
               program.programId,
               computationOffset
@@ -272,6 +5582,7 @@ describe("ConfidentialPayroll", () => {
               Buffer.from(getCompDefAccOffset("calculate_net_pay")).readUInt32LE()
             ),
           })
+          .signers([authority])
           .rpc({ skipPreflight: true, commitment: "confirmed" });
 
         console.log("Queue sig is ", queueSig);
@@ -303,21 +5614,598 @@ describe("ConfidentialPayroll", () => {
     }
 
     const netPayEvent = await netPayEventPromise;
+    expect(netPayEvent.computationOffset.toString()).to.equal(computationOffset.toString());
+    expect(netPayEvent.employeeId).to.equal(employeeId);
     const decrypted = cipher.decrypt([netPayEvent.netPay], netPayEvent.nonce)[0];
     const expectedNetPay = salary - (salary * taxRate / BigInt(10000)) - deductions;
     expect(decrypted).to.equal(expectedNetPay);
+
+    // The callback also persists the ciphertext so it can be decrypted later without rerunning
+    const stored = await program.account.employee.fetch(employeePda);
+    const storedNonce = new Uint8Array(stored.netPayNonce.toArrayLike(Buffer, "le", 16));
+    expect(cipher.decrypt([stored.encryptedNetPay], storedNonce)[0]).to.equal(expectedNetPay);
+  });
+
+  it("Estimates the Arcium fee for a net pay calculation", async () => {
+    const payrollPda = payrollPdaFor(payrollId);
+    const estimatePromise = awaitEvent("computationFeeEstimate");
+    await program.methods
+      .estimateNetPayFee()
+      .accountsPartial({
+        payroll: payrollPda,
+        clusterAccount: arciumEnv.arciumClusterPubkey,
+        mxeAccount: getMXEAccAddress(program.programId),
+        compDefAccount: getCompDefAccAddress(
+          program.programId,
+          Buffer.from(getCompDefAccOffset("calculate_net_pay")).readUInt32LE()
+        ),
+      })
+      .rpc();
+    const estimate = await estimatePromise;
+
+    expect(estimate.payrollId).to.equal(payrollId);
+    expect(estimate.lamports.toString()).to.equal(
+      estimate.cuAmount.mul(estimate.cuPrice).toString()
+    );
+  });
+
+  it("Holds payment until the settlement delay after a net pay calculation", async () => {
+    const payrollPda = payrollPdaFor(payrollId);
+    const employeePda = employeePdaFor(payrollPda, employeeId);
+    const configAccounts = { payroll: payrollPda, authority: authority.publicKey };
+    const processAccounts = {
+      payroll: payrollPda,
+      employee: employeePda,
+      payrollVault: payrollVault,
+      nativeVault: null,
+      employeeWallet: employeeTokenAccount,
+      nativeWallet: null,
+      paymentToken: mint,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      priceUpdate: null,
+      payStub: null,
+      payer: null,
+      systemProgram: null,
+      taxAccount: null,
+      platformAccount: null,
+      vaultAuthority: null,
+      memoProgram: null,
+      approval: null,
+      approver: null,
+      payHistory: null,
+      fundingSource: null,
+      authority: null,
+    };
+
+    // Make exactly one period due, so only the settlement window stands in the way
+    const before = await program.account.employee.fetch(employeePda);
+    expect(before.netPayComputedAt.toNumber()).to.be.greaterThan(0);
+    const now = await provider.connection.getBlockTime(await provider.connection.getSlot());
+    const interval = Math.max(now - before.lastPayment.toNumber() - 5, 10);
+    await program.methods
+      .setPaymentIntervalOverride(new anchor.BN(interval))
+      .accountsPartial({ payroll: payrollPda, employee: employeePda, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    await program.methods.setSettlementDelay(new anchor.BN(3600)).accountsPartial(configAccounts).signers([authority]).rpc();
+    await expectAnchorError(
+      program.methods.processPayment(Array.from(randomBytes(16)), "", null).accountsPartial(processAccounts).rpc(),
+      "SettlementPending"
+    );
+
+    await program.methods.setSettlementDelay(new anchor.BN(2)).accountsPartial(configAccounts).signers([authority]).rpc();
+    await new Promise((r) => setTimeout(r, 3000));
+    await program.methods.processPayment(Array.from(randomBytes(16)), "", null).accountsPartial(processAccounts).rpc();
+    const after = await program.account.employee.fetch(employeePda);
+    expect(after.paymentCount.toNumber()).to.equal(before.paymentCount.toNumber() + 1);
+  });
+
+  it("Test confidential tax withholding calculation", async () => {
+    const owner = readKpJson(`${os.homedir()}/.config/solana/id.json`);
+
+    let initSig: string | undefined;
+    for (let attempt = 1; attempt <= 3; attempt++) {
+      try {
+        initSig = await initCompDef(
+          program,
+          owner,
+          "calculate_tax_withholding",
+          false,
+          false
+        );
+        console.log("Calculate tax withholding computation definition initialized with signature", initSig);
+        break;
+      } catch (err: any) {
+        console.log(`Init comp def attempt ${attempt} failed:`, err.message || err);
+        if (attempt === 3) throw err;
+        await new Promise((r) => setTimeout(r, 1000));
+      }
+    }
+
+    const mxePublicKey = await getMXEPublicKeyWithRetry(
+      provider as anchor.AnchorProvider,
+      program.programId
+    );
+    const privateKey = x25519.utils.randomSecretKey();
+    const publicKey = x25519.getPublicKey(privateKey);
+    const cipher = new RescueCipher(x25519.getSharedSecret(privateKey, mxePublicKey));
+
+    const salary = BigInt(100000000);
+    const taxRate = BigInt(1000);
+    const payrollPda = payrollPdaFor(payrollId);
+    const employeePda = employeePdaFor(payrollPda, employeeId);
+
+    const taxRateNonce = randomBytes(16);
+    const taxRateCiphertext = cipher.encrypt([taxRate], taxRateNonce);
+    await program.methods
+      .setEncryptedTaxRate(
+        Array.from(taxRateCiphertext[0]),
+        Array.from(publicKey),
+        new anchor.BN(deserializeLE(taxRateNonce).toString())
+      )
+      .accountsPartial({ payroll: payrollPda, authority: authority.publicKey })
+      .signers([authority])
+      .rpc({ commitment: "confirmed" });
+
+    const nonce = randomBytes(16);
+    const ciphertext = cipher.encrypt([salary], nonce);
+    const taxEventPromise = awaitEvent("taxWithholdingCalculated");
+    const computationOffset = new anchor.BN(randomBytes(8), "hex");
+
+    await program.methods
+      .calculateTaxWithholding(
+        computationOffset,
+        Array.from(ciphertext[0]),
+        Array.from(publicKey),
+        new anchor.BN(deserializeLE(nonce).toString())
+      )
+      .accountsPartial({
+        payer: authority.publicKey,
+        payroll: payrollPda,
+        employee: employeePda,
+        computationAccount: getComputationAccAddress(program.programId, computationOffset),
+        clusterAccount: arciumEnv.arciumClusterPubkey,
+        mxeAccount: getMXEAccAddress(program.programId),
+        mempoolAccount: getMempoolAccAddress(program.programId),
+        executingPool: getExecutingPoolAccAddress(program.programId),
+        compDefAccount: getCompDefAccAddress(
+          program.programId,
+          Buffer.from(getCompDefAccOffset("calculate_tax_withholding")).readUInt32LE()
+        ),
+      })
+      .signers([authority])
+      .rpc({ skipPreflight: true, commitment: "confirmed" });
+
+    await awaitComputationFinalization(
+      provider as anchor.AnchorProvider,
+      computationOffset,
+      program.programId,
+      "confirmed"
+    );
+
+    const taxEvent = await taxEventPromise;
+    expect(taxEvent.computationOffset.toString()).to.equal(computationOffset.toString());
+    const decrypted = cipher.decrypt([taxEvent.tax], taxEvent.nonce)[0];
+    expect(decrypted).to.equal(salary * taxRate / BigInt(10000));
+  });
+
+  it("Accumulates year-to-date tax confidentially", async () => {
+    const owner = readKpJson(`${os.homedir()}/.config/solana/id.json`);
+
+    let initSig: string | undefined;
+    for (let attempt = 1; attempt <= 3; attempt++) {
+      try {
+        initSig = await initCompDef(program, owner, "accumulate_ytd_tax", false, false);
+        console.log("Accumulate YTD tax computation definition initialized with signature", initSig);
+        break;
+      } catch (err: any) {
+        console.log(`Init comp def attempt ${attempt} failed:`, err.message || err);
+        if (attempt === 3) throw err;
+        await new Promise((r) => setTimeout(r, 1000));
+      }
+    }
+
+    const mxePublicKey = await getMXEPublicKeyWithRetry(
+      provider as anchor.AnchorProvider,
+      program.programId
+    );
+    const privateKey = x25519.utils.randomSecretKey();
+    const publicKey = x25519.getPublicKey(privateKey);
+    const cipher = new RescueCipher(x25519.getSharedSecret(privateKey, mxePublicKey));
+    const payrollPda = payrollPdaFor(payrollId);
+    const employeePda = employeePdaFor(payrollPda, employeeId);
+
+    // The accumulator starts from an encrypted zero
+    const zeroNonce = randomBytes(16);
+    const zeroCiphertext = cipher.encrypt([BigInt(0)], zeroNonce);
+    await program.methods
+      .resetYtdTax(
+        Array.from(zeroCiphertext[0]),
+        Array.from(publicKey),
+        new anchor.BN(deserializeLE(zeroNonce).toString())
+      )
+      .accountsPartial({ payroll: payrollPda, employee: employeePda, authority: authority.publicKey })
+      .signers([authority])
+      .rpc({ commitment: "confirmed" });
+
+    const accumulate = async (tax: bigint) => {
+      const nonce = randomBytes(16);
+      const ciphertext = cipher.encrypt([tax], nonce);
+      const ytdEventPromise = awaitEvent("ytdTaxAccumulated");
+      const computationOffset = new anchor.BN(randomBytes(8), "hex");
+
+      await program.methods
+        .accumulateYtdTax(
+          computationOffset,
+          Array.from(ciphertext[0]),
+          Array.from(publicKey),
+          new anchor.BN(deserializeLE(nonce).toString())
+        )
+        .accountsPartial({
+          payer: authority.publicKey,
+          payroll: payrollPda,
+          employee: employeePda,
+          computationAccount: getComputationAccAddress(program.programId, computationOffset),
+          clusterAccount: arciumEnv.arciumClusterPubkey,
+          mxeAccount: getMXEAccAddress(program.programId),
+          mempoolAccount: getMempoolAccAddress(program.programId),
+          executingPool: getExecutingPoolAccAddress(program.programId),
+          compDefAccount: getCompDefAccAddress(
+            program.programId,
+            Buffer.from(getCompDefAccOffset("accumulate_ytd_tax")).readUInt32LE()
+          ),
+        })
+        .signers([authority])
+        .rpc({ skipPreflight: true, commitment: "confirmed" });
+
+      await awaitComputationFinalization(
+        provider as anchor.AnchorProvider,
+        computationOffset,
+        program.programId,
+        "confirmed"
+      );
+      return ytdEventPromise;
+    };
+
+    await accumulate(BigInt(1000000));
+    const ytdEvent = await accumulate(BigInt(500000));
+    expect(cipher.decrypt([ytdEvent.ytdTax], ytdEvent.nonce)[0]).to.equal(BigInt(1500000));
+
+    // The stored total matches the event and decrypts with the stored nonce
+    const stored = await program.account.employee.fetch(employeePda);
+    const storedNonce = stored.ytdTaxNonce.toArrayLike(Buffer, "le", 16);
+    expect(cipher.decrypt([stored.ytdTaxEncrypted], storedNonce)[0]).to.equal(BigInt(1500000));
+    expect(stored.pendingYtdComputationOffset).to.be.null;
+  });
+
+  it("Totals net pay across employees in one confidential computation", async () => {
+    const owner = readKpJson(`${os.homedir()}/.config/solana/id.json`);
+
+    let initSig: string | undefined;
+    for (let attempt = 1; attempt <= 3; attempt++) {
+      try {
+        initSig = await initCompDef(program, owner, "calculate_total_net_payroll", false, false);
+        console.log("Total net payroll computation definition initialized with signature", initSig);
+        break;
+      } catch (err: any) {
+        console.log(`Init comp def attempt ${attempt} failed:`, err.message || err);
+        if (attempt === 3) throw err;
+        await new Promise((r) => setTimeout(r, 1000));
+      }
+    }
+
+    const mxePublicKey = await getMXEPublicKeyWithRetry(
+      provider as anchor.AnchorProvider,
+      program.programId
+    );
+    const privateKey = x25519.utils.randomSecretKey();
+    const publicKey = x25519.getPublicKey(privateKey);
+    const cipher = new RescueCipher(x25519.getSharedSecret(privateKey, mxePublicKey));
+    const payrollPda = payrollPdaFor(payrollId);
+
+    // The payroll's encrypted tax rate (10%) was set by the net pay test; the last slot is unused
+    const inputs = [
+      [BigInt(1000000), BigInt(10000), BigInt(0), BigInt(0), BigInt(0)],
+      [BigInt(2000000), BigInt(0), BigInt(0), BigInt(0), BigInt(5000)],
+      [BigInt(0), BigInt(0), BigInt(0), BigInt(0), BigInt(0)],
+    ];
+    const nonce = randomBytes(16);
+    const ciphertext = cipher.encrypt(inputs.flat(), nonce);
+    const slots = inputs.map((_, i) => ciphertext.slice(i * 5, i * 5 + 5).map((item) => Array.from(item)));
+
+    const totalEventPromise = awaitEvent("totalNetPayrollCalculated");
+    const computationOffset = new anchor.BN(randomBytes(8), "hex");
+    await program.methods
+      .calculateTotalNetPayroll(
+        computationOffset,
+        slots.map((slot) => slot[0]),
+        slots.map((slot) => slot.slice(1)),
+        Array.from(publicKey),
+        new anchor.BN(deserializeLE(nonce).toString())
+      )
+      .accountsPartial({
+        payer: authority.publicKey,
+        payroll: payrollPda,
+        computationAccount: getComputationAccAddress(program.programId, computationOffset),
+        clusterAccount: arciumEnv.arciumClusterPubkey,
+        mxeAccount: getMXEAccAddress(program.programId),
+        mempoolAccount: getMempoolAccAddress(program.programId),
+        executingPool: getExecutingPoolAccAddress(program.programId),
+        compDefAccount: getCompDefAccAddress(
+          program.programId,
+          Buffer.from(getCompDefAccOffset("calculate_total_net_payroll")).readUInt32LE()
+        ),
+      })
+      .signers([authority])
+      .rpc({ skipPreflight: true, commitment: "confirmed" });
+
+    await awaitComputationFinalization(
+      provider as anchor.AnchorProvider,
+      computationOffset,
+      program.programId,
+      "confirmed"
+    );
+    const totalEvent = await totalEventPromise;
+
+    // 1,000,000 - 100,000 - 10,000 + 2,000,000 - 200,000 - 5,000
+    expect(cipher.decrypt([totalEvent.totalNetPay], totalEvent.nonce)[0]).to.equal(BigInt(2685000));
+    const stored = await program.account.payroll.fetch(payrollPda);
+    expect(Buffer.from(stored.encryptedTotalNetPay)).to.deep.equal(Buffer.from(totalEvent.totalNetPay));
+    expect(stored.pendingTotalNetPayOffset).to.be.null;
+  });
+
+  it("Validates a confidential net pay against its salary", async () => {
+    const owner = readKpJson(`${os.homedir()}/.config/solana/id.json`);
+
+    let initSig: string | undefined;
+    for (let attempt = 1; attempt <= 3; attempt++) {
+      try {
+        initSig = await initCompDef(program, owner, "validate_net_pay", false, false);
+        console.log("Validate net pay computation definition initialized with signature", initSig);
+        break;
+      } catch (err: any) {
+        console.log(`Init comp def attempt ${attempt} failed:`, err.message || err);
+        if (attempt === 3) throw err;
+        await new Promise((r) => setTimeout(r, 1000));
+      }
+    }
+
+    const mxePublicKey = await getMXEPublicKeyWithRetry(
+      provider as anchor.AnchorProvider,
+      program.programId
+    );
+    const privateKey = x25519.utils.randomSecretKey();
+    const publicKey = x25519.getPublicKey(privateKey);
+    const cipher = new RescueCipher(x25519.getSharedSecret(privateKey, mxePublicKey));
+    const payrollPda = payrollPdaFor(payrollId);
+    const employeePda = employeePdaFor(payrollPda, employeeId);
+
+    await program.methods
+      .updateEncryptionPubkey(Array.from(publicKey))
+      .accountsPartial({
+        payroll: payrollPda,
+        employee: employeePda,
+        employeeWallet: employeeTokenAccount,
+        walletOwner: employee.publicKey,
+      })
+      .signers([employee])
+      .rpc({ commitment: "confirmed" });
+
+    const queueAccounts = (computationOffset: anchor.BN, circuit: "calculate_net_pay" | "validate_net_pay") => ({
+      payer: authority.publicKey,
+      payroll: payrollPda,
+      employee: employeePda,
+      computationAccount: getComputationAccAddress(program.programId, computationOffset),
+      clusterAccount: arciumEnv.arciumClusterPubkey,
+      mxeAccount: getMXEAccAddress(program.programId),
+      mempoolAccount: getMempoolAccAddress(program.programId),
+      executingPool: getExecutingPoolAccAddress(program.programId),
+      compDefAccount: getCompDefAccAddress(
+        program.programId,
+        Buffer.from(getCompDefAccOffset(circuit)).readUInt32LE()
+      ),
+    });
+
+    const calculate = async (salary: bigint, deductionItems: bigint[]) => {
+      const nonce = randomBytes(16);
+      const ciphertext = cipher.encrypt([salary, ...deductionItems], nonce);
+      const computationOffset = new anchor.BN(randomBytes(8), "hex");
+      const calculatedPromise = awaitEvent("netPayCalculated");
+      await program.methods
+        .calculateNetPay(
+          computationOffset,
+          Array.from(ciphertext[0]),
+          ciphertext.slice(1).map((item) => Array.from(item)),
+          new anchor.BN(deserializeLE(nonce).toString())
+        )
+        .accountsPartial(queueAccounts(computationOffset, "calculate_net_pay"))
+        .signers([authority])
+        .rpc({ skipPreflight: true, commitment: "confirmed" });
+      await awaitComputationFinalization(provider as anchor.AnchorProvider, computationOffset, program.programId, "confirmed");
+      return calculatedPromise;
+    };
+
+    const validate = async () => {
+      const computationOffset = new anchor.BN(randomBytes(8), "hex");
+      const validatedPromise = awaitEvent("netPayValidated");
+      await program.methods
+        .validateNetPay(computationOffset)
+        .accountsPartial(queueAccounts(computationOffset, "validate_net_pay"))
+        .signers([authority])
+        .rpc({ skipPreflight: true, commitment: "confirmed" });
+      await awaitComputationFinalization(provider as anchor.AnchorProvider, computationOffset, program.programId, "confirmed");
+      return validatedPromise;
+    };
+
+    await calculate(BigInt(1000000), [BigInt(10000), BigInt(0), BigInt(0), BigInt(0)]);
+    const valid = await validate();
+    expect(valid.valid).to.equal(true);
+    expect((await program.account.employee.fetch(employeePda)).netPayValidated).to.equal(true);
+
+    // Deductions above the salary wrap the net pay past it
+    const inflated = await calculate(BigInt(100), [BigInt(1000), BigInt(0), BigInt(0), BigInt(0)]);
+    expect(cipher.decrypt([inflated.netPay], inflated.nonce)[0] > BigInt(100)).to.equal(true);
+    // The new result drops the earlier validation until checked itself
+    expect((await program.account.employee.fetch(employeePda)).netPayValidated).to.equal(false);
+    const rejected = await validate();
+    expect(rejected.valid).to.equal(false);
+    const stored = await program.account.employee.fetch(employeePda);
+    expect(stored.netPayValidated).to.equal(false);
+    expect(stored.pendingValidationOffset).to.be.null;
+  });
+
+  it("Re-queues a confidential net pay from its stored inputs", async () => {
+    const mxePublicKey = await getMXEPublicKeyWithRetry(
+      provider as anchor.AnchorProvider,
+      program.programId
+    );
+    const privateKey = x25519.utils.randomSecretKey();
+    const publicKey = x25519.getPublicKey(privateKey);
+    const cipher = new RescueCipher(x25519.getSharedSecret(privateKey, mxePublicKey));
+    const payrollPda = payrollPdaFor(payrollId);
+    const employeePda = employeePdaFor(payrollPda, employeeId);
+    const freshEmployee = employeePdaFor(payrollPda, "emp_no_inputs");
+
+    await program.methods
+      .updateEncryptionPubkey(Array.from(publicKey))
+      .accountsPartial({
+        payroll: payrollPda,
+        employee: employeePda,
+        employeeWallet: employeeTokenAccount,
+        walletOwner: employee.publicKey,
+      })
+      .signers([employee])
+      .rpc({ commitment: "confirmed" });
+    await program.methods
+      .addEmployee("emp_no_inputs", new anchor.BN(1000), new anchor.BN(0), { weekly: {} }, "", "", NO_ENCRYPTION_PUBKEY, { exempt: {} })
+      .accountsPartial({
+        payroll: payrollPda,
+        employee: freshEmployee,
+        authority: authority.publicKey,
+        employeeWallet: employeeTokenAccount,
+        paymentToken: mint,
+      })
+      .signers([authority])
+      .rpc({ commitment: "confirmed" });
+
+    const queueAccounts = (computationOffset: anchor.BN, target: PublicKey) => ({
+      payer: authority.publicKey,
+      payroll: payrollPda,
+      employee: target,
+      computationAccount: getComputationAccAddress(program.programId, computationOffset),
+      clusterAccount: arciumEnv.arciumClusterPubkey,
+      mxeAccount: getMXEAccAddress(program.programId),
+      mempoolAccount: getMempoolAccAddress(program.programId),
+      executingPool: getExecutingPoolAccAddress(program.programId),
+      compDefAccount: getCompDefAccAddress(
+        program.programId,
+        Buffer.from(getCompDefAccOffset("calculate_net_pay")).readUInt32LE()
+      ),
+    });
+
+    // Nothing has been calculated for this employee, so there is nothing to retry
+    const noInputsOffset = new anchor.BN(randomBytes(8), "hex");
+    await expectAnchorError(
+      program.methods
+        .retryNetPay(noInputsOffset)
+        .accountsPartial(queueAccounts(noInputsOffset, freshEmployee))
+        .signers([authority])
+        .rpc({ commitment: "confirmed" }),
+      "NoStoredNetPayInputs"
+    );
+
+    const nonce = randomBytes(16);
+    const ciphertext = cipher.encrypt([BigInt(1000000), BigInt(10000), BigInt(0), BigInt(0), BigInt(0)], nonce);
+    const calculateOffset = new anchor.BN(randomBytes(8), "hex");
+    const calculatedPromise = awaitEvent("netPayCalculated");
+    await program.methods
+      .calculateNetPay(
+        calculateOffset,
+        Array.from(ciphertext[0]),
+        ciphertext.slice(1).map((item) => Array.from(item)),
+        new anchor.BN(deserializeLE(nonce).toString())
+      )
+      .accountsPartial(queueAccounts(calculateOffset, employeePda))
+      .signers([authority])
+      .rpc({ skipPreflight: true, commitment: "confirmed" });
+    await awaitComputationFinalization(provider as anchor.AnchorProvider, calculateOffset, program.programId, "confirmed");
+    const calculated = await calculatedPromise;
+
+    // The retry takes no ciphertexts and reproduces the same net pay
+    const retryOffset = new anchor.BN(randomBytes(8), "hex");
+    const retriedPromise = awaitEvent("netPayRetried");
+    const recalculatedPromise = awaitEvent("netPayCalculated");
+    await program.methods
+      .retryNetPay(retryOffset)
+      .accountsPartial(queueAccounts(retryOffset, employeePda))
+      .signers([authority])
+      .rpc({ skipPreflight: true, commitment: "confirmed" });
+    const retried = await retriedPromise;
+    await awaitComputationFinalization(provider as anchor.AnchorProvider, retryOffset, program.programId, "confirmed");
+    const recalculated = await recalculatedPromise;
+
+    expect(retried.employeeId).to.equal(employeeId);
+    expect(retried.previousOffset).to.be.null;
+    expect(retried.computationOffset.toString()).to.equal(retryOffset.toString());
+    expect(recalculated.computationOffset.toString()).to.equal(retryOffset.toString());
+    expect(cipher.decrypt([recalculated.netPay], recalculated.nonce)).to.deep.equal(
+      cipher.decrypt([calculated.netPay], calculated.nonce)
+    );
   });
 
-  async function initCalculateNetPayCompDef(
+  function programConfigPda(): PublicKey {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("program_config")],
+      program.programId
+    )[0];
+  }
+
+  function payrollPdaFor(id: string): PublicKey {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("payroll"), Buffer.from(id)],
+      program.programId
+    )[0];
+  }
+
+  function vaultPdaFor(payroll: PublicKey): PublicKey {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("payroll_vault"), payroll.toBuffer()],
+      program.programId
+    )[0];
+  }
+
+  function employeePdaFor(payroll: PublicKey, id: string): PublicKey {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("employee"), payroll.toBuffer(), Buffer.from(id)],
+      program.programId
+    )[0];
+  }
+
+  async function newFundedKeypair(): Promise<Keypair> {
+    const keypair = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(keypair.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL)
+    );
+    return keypair;
+  }
+
+  async function initCompDef(
     program: Program<ConfidentialPayroll>,
     owner: anchor.web3.Keypair,
+    circuit:
+      | "calculate_net_pay"
+      | "calculate_tax_withholding"
+      | "accumulate_ytd_tax"
+      | "calculate_total_net_payroll"
+      | "validate_net_pay",
     uploadRawCircuit: boolean,
     offchainSource: boolean
   ): Promise<string> {
     const baseSeedCompDefAcc = getArciumAccountBaseSeed(
       "ComputationDefinitionAccount"
     );
-    const offset = getCompDefAccOffset("calculate_net_pay");
+    const offset = getCompDefAccOffset(circuit);
 
     const compDefPDA = PublicKey.findProgramAddressSync(
       [baseSeedCompDefAcc, program.programId.toBuffer(), offset],
@@ -330,8 +6218,17 @@ describe("ConfidentialPayroll", () => {
     let sig: string | undefined;
     for (let attempt = 1; attempt <= 3; attempt++) {
       try {
-        sig = await program.methods
-          .initCalculateNetPayCompDef()
+        const initMethod =
+          circuit === "calculate_net_pay"
+            ? program.methods.initCalculateNetPayCompDef()
+            : circuit === "calculate_tax_withholding"
+            ? program.methods.initCalculateTaxWithholdingCompDef()
+            : circuit === "accumulate_ytd_tax"
+            ? program.methods.initAccumulateYtdTaxCompDef()
+            : circuit === "calculate_total_net_payroll"
+            ? program.methods.initCalculateTotalNetPayrollCompDef()
+            : program.methods.initValidateNetPayCompDef();
+        sig = await initMethod
           .accounts({
             compDefAccount: compDefPDA,
             payer: owner.publicKey,
@@ -341,7 +6238,7 @@ describe("ConfidentialPayroll", () => {
           .rpc({
             commitment: "confirmed",
           });
-        console.log("Init calculate net pay computation definition transaction", sig);
+        console.log(`Init ${circuit} computation definition transaction`, sig);
         break;
       } catch (err: any) {
         console.log(`Init tx attempt ${attempt} failed:`, err.message || err);
@@ -355,11 +6252,11 @@ describe("ConfidentialPayroll", () => {
     }
 
     if (uploadRawCircuit) {
-      const rawCircuit = fs.readFileSync("build/calculate_net_pay.arcis");
+      const rawCircuit = fs.readFileSync(`build/${circuit}.arcis`);
 
       await uploadCircuit(
         provider as anchor.AnchorProvider,
-        "calculate_net_pay",
+        circuit,
         program.programId,
         rawCircuit,
         true
@@ -412,6 +6309,20 @@ async function getMXEPublicKeyWithRetry(
   );
 }
 
+async function expectAnchorError(
+  promise: Promise<unknown>,
+  code: string
+): Promise<void> {
+  let caught: any;
+  try {
+    await promise;
+  } catch (err) {
+    caught = err;
+  }
+  expect(caught, `expected ${code} error`).to.exist;
+  expect(caught.error?.errorCode?.code).to.equal(code);
+}
+
 function readKpJson(path: string): anchor.web3.Keypair {
   const file = fs.readFileSync(path);
   return anchor.web3.Keypair.fromSecretKey(